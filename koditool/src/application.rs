@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{KodiError, RpcClient, Toggle};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ApplicationProperties {
+    // 0-100
+    pub volume: u8,
+    pub muted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChange {
+    // Absolute level, clamped to 0-100
+    Set(u8),
+    // Kodi's own step size (usually 2)
+    Increment,
+    Decrement,
+}
+
+impl VolumeChange {
+    fn to_param(self) -> Value {
+        match self {
+            VolumeChange::Set(volume) => json!(volume.min(100)),
+            VolumeChange::Increment => json!("increment"),
+            VolumeChange::Decrement => json!("decrement"),
+        }
+    }
+}
+
+impl RpcClient {
+    pub async fn get_application_properties(&self) -> Result<ApplicationProperties, KodiError> {
+        self.call_method(
+            "Application.GetProperties",
+            &json!({ "properties": ["volume", "muted"] }),
+        )
+        .await
+    }
+
+    // Returns the volume Kodi settled on
+    pub async fn set_volume(&self, change: VolumeChange) -> Result<u8, KodiError> {
+        let volume: i64 = self
+            .call_method("Application.SetVolume", &json!({ "volume": change.to_param() }))
+            .await?;
+        Ok(volume.clamp(0, 100) as u8)
+    }
+
+    // Returns the new mute state
+    pub async fn set_mute(&self, mute: Toggle) -> Result<bool, KodiError> {
+        self.call_method("Application.SetMute", &json!({ "mute": mute.to_param() }))
+            .await
+    }
+}
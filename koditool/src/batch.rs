@@ -0,0 +1,110 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::{KodiError, RpcClient};
+
+// Several JSON-RPC calls sent as one array in a single POST. Each `add`
+// hands back a typed handle used to pull that call's result out afterwards.
+pub struct Batch<'a> {
+    client: &'a RpcClient,
+    requests: Vec<Value>,
+}
+
+// Handle to one call inside a batch; `T` is what its `result` decodes into
+#[derive(Debug)]
+pub struct BatchCall<T> {
+    id: u64,
+    method: String,
+    _result: PhantomData<fn() -> T>,
+}
+
+#[derive(Debug)]
+pub struct BatchResults {
+    responses: HashMap<u64, Value>,
+}
+
+impl<'a> Batch<'a> {
+    pub(crate) fn new(client: &'a RpcClient) -> Self {
+        Batch {
+            client,
+            requests: Vec::new(),
+        }
+    }
+
+    pub fn add<P: Serialize, T: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: &P,
+    ) -> BatchCall<T> {
+        let id = self.client.next_request_id();
+        self.requests.push(json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": id
+        }));
+
+        BatchCall {
+            id,
+            method: method.to_string(),
+            _result: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    // Errors here are for the batch as a whole (transport, auth, a reply
+    // that isn't an array); per-call failures come out of `BatchResults::get`
+    pub async fn send(self) -> Result<BatchResults, KodiError> {
+        if self.requests.is_empty() {
+            return Ok(BatchResults {
+                responses: HashMap::new(),
+            });
+        }
+
+        let reply = self.client.rpc_call(&Value::Array(self.requests)).await?;
+        let replies = match reply {
+            Value::Array(replies) => replies,
+            other => {
+                return Err(KodiError::Decode(format!(
+                    "expected an array of batch responses, got {}",
+                    other
+                )))
+            }
+        };
+
+        // Kodi may answer out of order, so correlate by id
+        let responses = replies
+            .into_iter()
+            .filter_map(|reply| Some((reply.get("id")?.as_u64()?, reply)))
+            .collect();
+
+        Ok(BatchResults { responses })
+    }
+}
+
+impl BatchResults {
+    pub fn get<T: DeserializeOwned>(&self, call: &BatchCall<T>) -> Result<T, KodiError> {
+        let reply = self.responses.get(&call.id).ok_or_else(|| {
+            KodiError::MissingField(format!("Batch response for {}", call.method))
+        })?;
+
+        if let Some(error) = reply.get("error") {
+            return Err(KodiError::from_rpc_error(error));
+        }
+
+        let result = reply
+            .get("result")
+            .ok_or_else(|| KodiError::MissingField(format!("{} result", call.method)))?;
+        serde_json::from_value(result.clone()).map_err(KodiError::from)
+    }
+}
@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Episode;
+
+// Which of a show's episodes an entry may pick from. Flattened into a
+// `show:` entry in show_mappings.yml:
+//
+//   - show: Bluey
+//     seasons: 1-2
+//     exclude_specials: true
+//     exclude: [412, "Halloween"]
+//     max_runtime: 10
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct EpisodeFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seasons: Option<SeasonRange>,
+    // Season 0
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub exclude_specials: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<EpisodeExclusion>,
+    // Minutes. Episodes Kodi has no runtime for are never filtered out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_runtime: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_runtime: Option<u64>,
+}
+
+// An episode id, or a title compared case-insensitively
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum EpisodeExclusion {
+    Id(u64),
+    Title(String),
+}
+
+// "2" (just season 2), "1-3" or "4-" (season 4 onwards)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "SeasonRangeRepr", into = "String")]
+pub struct SeasonRange {
+    pub first: i32,
+    pub last: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SeasonRangeRepr {
+    Number(i32),
+    Text(String),
+}
+
+impl EpisodeFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == EpisodeFilter::default()
+    }
+
+    pub fn matches(&self, episode: &Episode) -> bool {
+        if self.exclude_specials && episode.season == 0 {
+            return false;
+        }
+        if let Some(seasons) = &self.seasons {
+            if !seasons.contains(episode.season) {
+                return false;
+            }
+        }
+        if self
+            .exclude
+            .iter()
+            .any(|exclusion| exclusion.matches(episode))
+        {
+            return false;
+        }
+
+        let minutes = episode.runtime / 60;
+        if episode.runtime > 0 {
+            if self.min_runtime.is_some_and(|min| minutes < min) {
+                return false;
+            }
+            if self.max_runtime.is_some_and(|max| minutes > max) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+impl EpisodeExclusion {
+    fn matches(&self, episode: &Episode) -> bool {
+        match self {
+            EpisodeExclusion::Id(id) => episode.episodeid == *id,
+            EpisodeExclusion::Title(title) => {
+                episode.title.trim().eq_ignore_ascii_case(title.trim())
+            }
+        }
+    }
+}
+
+impl SeasonRange {
+    pub fn contains(&self, season: i32) -> bool {
+        season >= self.first && self.last.is_none_or(|last| season <= last)
+    }
+}
+
+impl FromStr for SeasonRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |part: &str| {
+            part.trim()
+                .parse::<i32>()
+                .map_err(|_| format!("Invalid season range '{}' (expected e.g. 2, 1-3 or 4-)", s))
+        };
+
+        let range = match s.split_once('-') {
+            None => {
+                let season = parse(s)?;
+                SeasonRange {
+                    first: season,
+                    last: Some(season),
+                }
+            }
+            Some((first, last)) if last.trim().is_empty() => SeasonRange {
+                first: parse(first)?,
+                last: None,
+            },
+            Some((first, last)) => SeasonRange {
+                first: parse(first)?,
+                last: Some(parse(last)?),
+            },
+        };
+
+        if range.last.is_some_and(|last| last < range.first) {
+            return Err(format!("Season range '{}' ends before it starts", s));
+        }
+        Ok(range)
+    }
+}
+
+impl TryFrom<SeasonRangeRepr> for SeasonRange {
+    type Error = String;
+
+    fn try_from(repr: SeasonRangeRepr) -> Result<Self, Self::Error> {
+        match repr {
+            SeasonRangeRepr::Number(season) => Ok(SeasonRange {
+                first: season,
+                last: Some(season),
+            }),
+            SeasonRangeRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<SeasonRange> for String {
+    fn from(range: SeasonRange) -> Self {
+        range.to_string()
+    }
+}
+
+impl fmt::Display for SeasonRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.last {
+            Some(last) if last == self.first => write!(f, "{}", self.first),
+            Some(last) => write!(f, "{}-{}", self.first, last),
+            None => write!(f, "{}-", self.first),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use serde_json::Value;
+
+// Every failure an RpcClient call can produce, grouped by class so callers
+// can tell "Kodi is down" apart from "Kodi rejected the request"
+#[derive(Debug)]
+pub enum KodiError {
+    // Connection refused, DNS failure, reset mid-body, ...
+    Transport(String),
+    // Non-2xx reply that isn't an auth failure
+    HttpStatus(u16),
+    // 401/403 from Kodi's web server - username/password are wrong
+    Unauthorized,
+    // The JSON-RPC `error` object, e.g. -32602 "Invalid params"
+    Rpc {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    // Reply was valid JSON-RPC but lacked a field we rely on
+    MissingField(String),
+    // Body was not valid JSON or didn't match the expected shape
+    Decode(String),
+    Timeout,
+    // The library doesn't contain what was asked for
+    NotFound(String),
+}
+
+impl KodiError {
+    // Build from a JSON-RPC `error` object
+    pub fn from_rpc_error(error: &Value) -> Self {
+        KodiError::Rpc {
+            code: error["code"].as_i64().unwrap_or(0),
+            message: error["message"]
+                .as_str()
+                .unwrap_or("unknown error")
+                .to_string(),
+            data: error.get("data").cloned(),
+        }
+    }
+
+    // Short, stable identifier for the failure class (used in API responses)
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KodiError::Transport(_) => "transport",
+            KodiError::HttpStatus(_) => "http_status",
+            KodiError::Unauthorized => "unauthorized",
+            KodiError::Rpc { .. } => "rpc",
+            KodiError::MissingField(_) => "missing_field",
+            KodiError::Decode(_) => "decode",
+            KodiError::Timeout => "timeout",
+            KodiError::NotFound(_) => "not_found",
+        }
+    }
+
+    // True when Kodi itself is unreachable or unhealthy, as opposed to
+    // rejecting a specific request; retrying later may succeed
+    pub fn is_transient(&self) -> bool {
+        match self {
+            KodiError::Transport(_) | KodiError::Timeout => true,
+            KodiError::HttpStatus(status) => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for KodiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KodiError::Transport(e) => write!(f, "Transport error: {}", e),
+            KodiError::HttpStatus(status) => write!(f, "HTTP error: {}", status),
+            KodiError::Unauthorized => {
+                write!(f, "Authentication failed (check username/password)")
+            }
+            KodiError::Rpc { code, message, .. } => {
+                write!(f, "JSON-RPC error {}: {}", code, message)
+            }
+            KodiError::MissingField(field) => write!(f, "{} not found in response", field),
+            KodiError::Decode(e) => write!(f, "Failed to decode response: {}", e),
+            KodiError::Timeout => write!(f, "RPC call timed out"),
+            KodiError::NotFound(what) => write!(f, "{}", what),
+        }
+    }
+}
+
+impl std::error::Error for KodiError {}
+
+impl From<reqwest::Error> for KodiError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            KodiError::Timeout
+        } else {
+            KodiError::Transport(e.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for KodiError {
+    fn from(e: serde_json::Error) -> Self {
+        KodiError::Decode(e.to_string())
+    }
+}
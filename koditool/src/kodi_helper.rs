@@ -1,4 +1,31 @@
-use rand::prelude::IndexedMutRandom;
+mod application;
+mod batch;
+mod episode_filter;
+mod error;
+mod library_cache;
+mod mappings;
+mod notifications;
+mod pagination;
+mod player;
+mod pvr;
+mod show_lookup;
+mod strategy;
+mod system;
+mod video_library;
+pub use application::*;
+pub use batch::{Batch, BatchCall, BatchResults};
+pub use episode_filter::*;
+pub use error::KodiError;
+pub use library_cache::LibraryCacheConfig;
+pub use mappings::*;
+pub use notifications::{KodiEvent, Subscription, DEFAULT_WEBSOCKET_PORT};
+pub use pagination::*;
+pub use player::*;
+pub use pvr::*;
+pub use show_lookup::*;
+pub use strategy::*;
+pub use video_library::*;
+
 use rand::rng;
 use rand::SeedableRng;
 use rand::TryRngCore;
@@ -6,17 +33,26 @@ use rand_chacha::ChaCha12Rng;
 use reqwest::header::AUTHORIZATION;
 use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
+use library_cache::LibraryCache;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use serde_json::Value;
-use std::error::Error;
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct Config {
     pub url: String,
     pub username: String,
     pub password: String,
+    // Defaults to ws://<host of url>:9090/jsonrpc
+    #[serde(default)]
+    pub websocket_url: Option<String>,
+    // Off unless present
+    #[serde(default)]
+    pub library_cache: Option<LibraryCacheConfig>,
 }
 
 impl Config {
@@ -57,6 +93,8 @@ impl Authorization {
 pub struct SelectedEpisode {
     pub _episode_id: u64,
     pub episode_file_path: String,
+    // Start from Kodi's saved resume point instead of the beginning
+    pub resume: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -66,6 +104,15 @@ pub struct Limits {
     pub end: u32,
 }
 
+// Kodi's List.LimitsReturned - the window that came back, plus the library total
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LimitsReturned {
+    pub start: u32,
+    pub end: u32,
+    pub total: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[allow(dead_code)]
 pub struct Artist {
@@ -135,18 +182,23 @@ pub struct RpcClient {
     pub auth: Authorization,
     pub client: Client,
     pub seed: Option<[u8; 32]>,
+    request_id: AtomicU64,
+    library_cache: Option<LibraryCache>,
 }
 
 impl RpcClient {
     // Create a new instance of RpcClient
-    pub fn new(config: Config) -> Result<Self, Box<dyn Error>> {
+    pub fn new(config: Config) -> Result<Self, KodiError> {
         let auth = Authorization::new(&config.username, &config.password);
         let client = Client::new();
+        let library_cache = config.library_cache.clone().map(LibraryCache::new);
         Ok(RpcClient {
             auth,
             config,
             client,
             seed: None,
+            request_id: AtomicU64::new(1),
+            library_cache,
         })
     }
 
@@ -156,125 +208,205 @@ impl RpcClient {
         self
     }
 
+    // Unique JSON-RPC id per request so batched replies can be matched up
+    pub(crate) fn next_request_id(&self) -> u64 {
+        self.request_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    // Start a batch of calls to be sent in a single POST
+    pub fn batch(&self) -> Batch<'_> {
+        Batch::new(self)
+    }
+
+    // Open Kodi's notification socket. The returned subscription reconnects
+    // on its own and reports Connected/Disconnected so callers know when
+    // they have to poll instead.
+    pub fn subscribe(&self) -> Result<Subscription, KodiError> {
+        let url = match &self.config.websocket_url {
+            Some(url) => url.clone(),
+            None => notifications::websocket_url(&self.config.url)?,
+        };
+        Ok(Subscription::spawn(url))
+    }
+
+    // Seeded from `seed` when set so tests get repeatable picks
+    pub(crate) fn selection_rng(&self) -> ChaCha12Rng {
+        let mut seed_array = [0u8; 32];
+        if let Some(seed) = self.seed {
+            seed_array = seed;
+        } else {
+            let _ = rng().try_fill_bytes(&mut seed_array);
+        }
+
+        ChaCha12Rng::from_seed(seed_array)
+    }
+
     pub async fn select_random_episode_by_title(
         &self,
         tv_show_name: &str,
-    ) -> Result<SelectedEpisode, Box<dyn Error>> {
-        // Fetch the list of TV shows
-        let tv_shows_request_params = json!({
-            "jsonrpc": "2.0",
-            "method": "VideoLibrary.GetTVShows",
-            "params": {
-                "properties": ["title"],
-                "limits": { "start": 0, "end": 1000 }
-            },
-            "id": 1
-        });
-
-        let tv_shows_response_json = self.rpc_call(&tv_shows_request_params).await?;
+    ) -> Result<SelectedEpisode, KodiError> {
+        self.select_episode_by_title(tv_show_name, SelectionStrategy::Random)
+            .await
+    }
 
-        // Extract the "tvshows" array from the "result" field
-        let tv_shows = tv_shows_response_json["result"]["tvshows"]
-            .as_array()
-            .ok_or("TV shows not found in response")?;
+    pub async fn select_episode_by_title(
+        &self,
+        tv_show_name: &str,
+        strategy: SelectionStrategy,
+    ) -> Result<SelectedEpisode, KodiError> {
+        let none = HashSet::new();
+        self.select_episode(&ShowEntry::new(tv_show_name), strategy, &none, &none)
+            .await
+    }
 
-        // Find the TV show with the given name
-        let tv_show = tv_shows
-            .iter()
-            .find(|show| show["title"].as_str() == Some(tv_show_name))
-            .ok_or_else(|| format!("TV show {} not found", tv_show_name))?;
+    // `avoid` holds recently played episode ids, skipped while others remain;
+    // `exclude` holds ids never to pick at all
+    pub(crate) async fn select_episode(
+        &self,
+        entry: &ShowEntry,
+        strategy: SelectionStrategy,
+        avoid: &HashSet<u64>,
+        exclude: &HashSet<u64>,
+    ) -> Result<SelectedEpisode, KodiError> {
+        let tv_show = self.find_tv_show(&entry.show, &entry.pin).await?;
 
         println!("Selected TV Show: {:?}", tv_show);
+        println!("Selected TV Show ID: {}", tv_show.tvshowid);
+
+        // Fetch the list of episodes, with the watch state strategies need
+        let mut episodes = self.show_episodes(tv_show.tvshowid).await?;
+
+        // Skip any entry Kodi returned without an ID, and put the episodes in
+        // playback order with specials (season 0) after the regular seasons
+        episodes.retain(|episode| episode.episodeid != 0);
+        episodes.retain(|episode| entry.filter.matches(episode));
+        if episodes.is_empty() && !entry.filter.is_empty() {
+            return Err(KodiError::NotFound(format!(
+                "No episodes of {} match the entry's filters",
+                tv_show.title
+            )));
+        }
+        episodes.retain(|episode| !exclude.contains(&episode.episodeid));
+        episodes.sort_by_key(|episode| (episode.season <= 0, episode.season, episode.episode));
+        let episodes = without_recent(episodes, avoid, |episode| episode.episodeid);
 
-        let tv_show_id = tv_show["tvshowid"].as_u64().ok_or("TV show ID not found")?;
-        println!("Selected TV Show ID: {}", tv_show_id);
-
-        // Fetch the list of episodes
-        let episodes_request_params = json!({
-            "jsonrpc": "2.0",
-            "method": "VideoLibrary.GetEpisodes",
-            "params": {
-                "tvshowid": tv_show_id, // Use the TV show ID you obtained earlier
-                "properties": ["title", "season", "episode"],
-                "limits": { "start": 0, "end": 1000 }
-            },
-            "id": 1
-        });
-
-        let episodes_response_json = self.rpc_call(&episodes_request_params).await?;
+        let episode = strategy
+            .pick(&episodes, &mut self.selection_rng())
+            .ok_or_else(|| KodiError::NotFound("No episodes available".to_string()))?;
 
-        //println!("Episodes Response: {:?}", episodes_response_json);
+        let episode_details = self
+            .get_episode_details(&GetEpisodeDetailsParams::new(episode.episodeid))
+            .await?;
 
-        // Extract the "episodes" array from the "result" field
-        let episodes = episodes_response_json["result"]["episodes"]
-            .as_array()
-            .ok_or("Episodes not found in response")?;
+        if episode_details.file.is_empty() {
+            return Err(KodiError::MissingField("Episode file path".to_string()));
+        }
 
-        //for episode in episodes {
-        //        let episode_id = episode["episodeid"].as_u64().ok_or("Episode ID not found")?;
-        //        let episode_title = episode["title"].as_str().ok_or("Episode title not found")?;
-        //        let season_number = episode["season"].as_u64().ok_or("Season number not found")?;
-        //        let episode_number = episode["episode"].as_u64().ok_or("Episode number not found")?;
+        let selected_episode = SelectedEpisode {
+            _episode_id: episode.episodeid,
+            episode_file_path: episode_details.file,
+            resume: strategy.resumes(episode),
+        };
 
-        //        println!(
-        //    	    "Episode ID: {}, Title: {}, Season: {}, Episode: {}",
-        //    	    episode_id, episode_title, season_number, episode_number
-        //    	    );
-        //}
+        Ok(selected_episode)
+    }
 
-        // Extract the episode IDs from the episodes array
-        let mut episode_ids: Vec<u64> = episodes
-            .iter()
-            .map(|episode| episode["episodeid"].as_u64().unwrap())
-            .collect();
+    // Build the JSON-RPC envelope for `method`, send it and decode `result`
+    pub async fn call_method<P: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: &P,
+    ) -> Result<R, KodiError> {
+        let request = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+            "id": self.next_request_id()
+        });
 
-        // Randomly select an episode ID
+        let response = self.rpc_call(&request).await?;
+        let result = response
+            .get("result")
+            .ok_or_else(|| KodiError::MissingField("result".to_string()))?;
+        serde_json::from_value(result.clone()).map_err(KodiError::from)
+    }
 
-        let mut seed_array = [0u8; 32];
-        if let Some(seed) = self.seed {
-            seed_array = seed;
-        } else {
-            let _ = rng().try_fill_bytes(&mut seed_array);
+    // Fetch every page of a list call. `params.limits` is the first window;
+    // its size is reused for each following page until Kodi's `total` is reached.
+    pub async fn call_paginated<P, R>(&self, method: &str, params: &P) -> Result<Vec<R::Item>, KodiError>
+    where
+        P: PagedParams + Serialize + Clone,
+        R: PagedResult + DeserializeOwned,
+    {
+        let mut params = params.clone();
+        let page_size = params
+            .limits()
+            .end
+            .saturating_sub(params.limits().start)
+            .max(1);
+        let mut items = Vec::new();
+
+        loop {
+            let result: R = self.call_method(method, &params).await?;
+            let (page, limits) = result.into_page();
+            let page_len = page.len() as u32;
+            items.extend(page);
+
+            // An empty page or a window that didn't advance means Kodi has nothing more
+            let next_start = params.limits().start + page_len;
+            if page_len == 0 || next_start >= limits.total {
+                break;
+            }
+
+            let window = params.limits_mut();
+            window.start = next_start;
+            window.end = next_start + page_size;
         }
 
-        let mut rng = ChaCha12Rng::from_seed(seed_array);
-        let random_episode_id = episode_ids
-            .choose_mut(&mut rng)
-            .ok_or("No episodes available")?;
-
-        //println!("Randomly selected episode ID: {:?}", random_episode_id);
+        Ok(items)
+    }
 
-        // Prepare the request parameters
-        let episode_details_request_params = json!({
-            "jsonrpc": "2.0",
-            "method": "VideoLibrary.GetEpisodeDetails",
-            "params": {
-                "episodeid": random_episode_id,
-                "properties": ["file"] // You can also include other properties you need
-            },
-            "id": 1
-        });
+    pub async fn get_tv_shows(&self, params: &GetTVShowsParams) -> Result<Vec<TVShow>, KodiError> {
+        self.call_paginated::<_, GetTVShowsResult>("VideoLibrary.GetTVShows", params)
+            .await
+    }
 
-        // Make the RPC call
-        let episode_details_response_json = self.rpc_call(&episode_details_request_params).await?;
+    pub async fn get_episodes(&self, params: &GetEpisodesParams) -> Result<Vec<Episode>, KodiError> {
+        self.call_paginated::<_, GetEpisodesResult>("VideoLibrary.GetEpisodes", params)
+            .await
+    }
 
-        // Extract the episode file path from the response
-        let episode_file_path = episode_details_response_json["result"]["episodedetails"]["file"]
-            .as_str()
-            .ok_or("Episode file path not found in response")?
-            .to_string(); // Convert to String
+    pub async fn get_episode_details(
+        &self,
+        params: &GetEpisodeDetailsParams,
+    ) -> Result<Episode, KodiError> {
+        let result: GetEpisodeDetailsResult = self
+            .call_method("VideoLibrary.GetEpisodeDetails", params)
+            .await?;
+        Ok(result.episodedetails)
+    }
 
-        //println!("[!] file path => {:?}", episode_file_path);
+    pub async fn get_seasons(&self, params: &GetSeasonsParams) -> Result<Vec<Season>, KodiError> {
+        self.call_paginated::<_, GetSeasonsResult>("VideoLibrary.GetSeasons", params)
+            .await
+    }
 
-        let selected_episode = SelectedEpisode {
-            _episode_id: *random_episode_id,
-            episode_file_path,
-        };
+    pub async fn get_movies(&self, params: &GetMoviesParams) -> Result<Vec<Movie>, KodiError> {
+        self.call_paginated::<_, GetMoviesResult>("VideoLibrary.GetMovies", params)
+            .await
+    }
 
-        Ok(selected_episode)
+    pub async fn get_movie_details(
+        &self,
+        params: &GetMovieDetailsParams,
+    ) -> Result<Movie, KodiError> {
+        let result: GetMovieDetailsResult = self
+            .call_method("VideoLibrary.GetMovieDetails", params)
+            .await?;
+        Ok(result.moviedetails)
     }
 
-    pub async fn rpc_call(&self, request_params: &Value) -> Result<Value, Box<dyn Error>> {
+    pub async fn rpc_call(&self, request_params: &Value) -> Result<Value, KodiError> {
         let mut headers = HeaderMap::new();
         headers.insert(AUTHORIZATION, self.auth.auth_header_value().clone());
         headers.insert(
@@ -296,9 +428,12 @@ impl RpcClient {
             .await?;
 
         // Check HTTP status code - return an error for non-2xx responses
-        if !response.status().is_success() {
-            let status = response.status();
-            return Err(format!("HTTP error: {}", status).into());
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Err(KodiError::Unauthorized);
+        }
+        if !status.is_success() {
+            return Err(KodiError::HttpStatus(status.as_u16()));
         }
 
         // Read response body as bytes and deserialize using serde_json
@@ -306,12 +441,18 @@ impl RpcClient {
         let response_str = String::from_utf8_lossy(&response_bytes);
         let response_json: Value = serde_json::from_str(&response_str)?;
 
+        // Kodi answers HTTP 200 even when the call failed, so surface the
+        // JSON-RPC error object instead of handing back a result-less reply
+        if let Some(error) = response_json.get("error") {
+            return Err(KodiError::from_rpc_error(error));
+        }
+
         Ok(response_json)
     }
 
     // Method to make an RPC call for playing an episode
-    pub async fn rpc_play(&self, selected_episode: &SelectedEpisode) -> Result<(), Box<dyn Error>> {
-        let play_episode_request_params = json!({
+    pub async fn rpc_play(&self, selected_episode: &SelectedEpisode) -> Result<(), KodiError> {
+        let mut play_episode_request_params = json!({
             "jsonrpc": "2.0",
             "method": "Player.Open",
             "params": {
@@ -319,8 +460,11 @@ impl RpcClient {
                     "file": &selected_episode.episode_file_path,
                 }
             },
-            "id": 1
+            "id": self.next_request_id()
         });
+        if selected_episode.resume {
+            play_episode_request_params["params"]["options"] = json!({ "resume": true });
+        }
 
         // Make the RPC call to play the episode
         let _play_response = self.rpc_call(&play_episode_request_params).await?;
@@ -329,27 +473,22 @@ impl RpcClient {
         Ok(())
     }
 
-    // method to stop playback
+    // method to stop playback on whichever player is active; a no-op when idle
     #[allow(dead_code)]
-    pub async fn rpc_stop(&self) -> Result<(), Box<dyn std::error::Error>> {
-        // Create a JSON-RPC request to stop playback
-        let params = serde_json::json!({
-            "jsonrpc": "2.0",
-            "method": "Player.Stop",
-            "params": {
-                "playerid": 1
-            },
-            "id": 1
-        });
+    pub async fn rpc_stop(&self) -> Result<(), KodiError> {
+        let Some(playerid) = self.active_player_id().await? else {
+            return Ok(());
+        };
 
-        // Send the request
-        let _response = self.rpc_call(&params).await?;
+        let _: Value = self
+            .call_method("Player.Stop", &json!({ "playerid": playerid }))
+            .await?;
 
         Ok(())
     }
 
     #[allow(dead_code)]
-    pub async fn get_artists(&self) -> Result<Vec<Artist>, Box<dyn Error>> {
+    pub async fn get_artists(&self) -> Result<Vec<Artist>, KodiError> {
         let params = GetArtistsParams {
             properties: vec!["artist".to_string()],
             limits: Limits {
@@ -358,21 +497,12 @@ impl RpcClient {
             },
         };
 
-        let request = json!({
-            "jsonrpc": "2.0",
-            "method": "AudioLibrary.GetArtists",
-            "params": params,
-            "id": 1
-        });
-
-        let response = self.rpc_call(&request).await?;
-        let artists_vec: Vec<Artist> = serde_json::from_value(response["result"]["artists"].clone())
-            .map_err(|_| "Artists not found in response")?;
-        Ok(artists_vec)
+        self.call_paginated::<_, GetArtistsResult>("AudioLibrary.GetArtists", &params)
+            .await
     }
 
     #[allow(dead_code)]
-    pub async fn get_albums(&self, artist_id: Option<u64>) -> Result<Vec<Album>, Box<dyn Error>> {
+    pub async fn get_albums(&self, artist_id: Option<u64>) -> Result<Vec<Album>, KodiError> {
         let params = GetAlbumsParams {
             filter: artist_id.map(|id| AudioFilter {
                 artistid: Some(id),
@@ -385,21 +515,12 @@ impl RpcClient {
             },
         };
 
-        let request = json!({
-            "jsonrpc": "2.0",
-            "method": "AudioLibrary.GetAlbums",
-            "params": params,
-            "id": 1
-        });
-
-        let response = self.rpc_call(&request).await?;
-        let albums_vec: Vec<Album> = serde_json::from_value(response["result"]["albums"].clone())
-            .map_err(|_| "Albums not found in response")?;
-        Ok(albums_vec)
+        self.call_paginated::<_, GetAlbumsResult>("AudioLibrary.GetAlbums", &params)
+            .await
     }
 
     #[allow(dead_code)]
-    pub async fn get_songs(&self, album_id: Option<u64>) -> Result<Vec<Song>, Box<dyn Error>> {
+    pub async fn get_songs(&self, album_id: Option<u64>) -> Result<Vec<Song>, KodiError> {
         let params = GetSongsParams {
             filter: album_id.map(|id| AudioFilter {
                 artistid: None,
@@ -412,34 +533,14 @@ impl RpcClient {
             },
         };
 
-        let request = json!({
-            "jsonrpc": "2.0",
-            "method": "AudioLibrary.GetSongs",
-            "params": params,
-            "id": 1
-        });
-
-        let response = self.rpc_call(&request).await?;
-        let songs_vec: Vec<Song> = serde_json::from_value(response["result"]["songs"].clone())
-            .map_err(|_| "Songs not found in response")?;
-        Ok(songs_vec)
+        self.call_paginated::<_, GetSongsResult>("AudioLibrary.GetSongs", &params)
+            .await
     }
 
 
     #[allow(dead_code)]
-    pub async fn is_active(&self) -> Result<bool, Box<dyn Error>> {
-        let active_players_request_params = json!({
-            "jsonrpc": "2.0",
-            "method": "Player.GetActivePlayers",
-            "id": 1
-        });
-
-        let active_players_response_json = self.rpc_call(&active_players_request_params).await?;
-        let active_players = active_players_response_json["result"]
-            .as_array()
-            .unwrap_or(&vec![])
-            .to_owned(); // Clone the array
-
+    pub async fn is_active(&self) -> Result<bool, KodiError> {
+        let active_players = self.get_active_players().await?;
         Ok(!active_players.is_empty())
     }
 }
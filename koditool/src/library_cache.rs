@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::video_library::properties;
+use crate::{
+    Episode, GetEpisodesParams, GetTVShowsParams, KodiError, KodiEvent, RpcClient, TVShow,
+};
+
+// Optional `library_cache` section of config.yml. Its presence turns the
+// cache on:
+//
+//   library_cache:
+//     ttl_seconds: 3600
+//     path: library_cache.json   # optional, keeps the cache across restarts
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct LibraryCacheConfig {
+    pub ttl_seconds: u64,
+    pub path: Option<PathBuf>,
+}
+
+impl Default for LibraryCacheConfig {
+    fn default() -> Self {
+        LibraryCacheConfig {
+            ttl_seconds: 3600,
+            path: None,
+        }
+    }
+}
+
+// The show list and per-show episode lists episode selection reads. Entries
+// expire after the TTL, and `invalidate` drops whatever a library
+// notification says has changed.
+#[derive(Debug)]
+pub(crate) struct LibraryCache {
+    config: LibraryCacheConfig,
+    library: Mutex<CachedLibrary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct CachedLibrary {
+    shows: Option<Cached<Vec<TVShow>>>,
+    // Keyed by tvshowid
+    episodes: HashMap<u64, Cached<Vec<Episode>>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct Cached<T> {
+    // Seconds since UNIX epoch
+    fetched_at: u64,
+    items: T,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl LibraryCache {
+    pub(crate) fn new(config: LibraryCacheConfig) -> Self {
+        // A missing or unreadable file just means starting cold
+        let library = config
+            .path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        LibraryCache {
+            config,
+            library: Mutex::new(library),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CachedLibrary> {
+        // Nothing in here can be left half-written, so a poisoned lock is fine
+        self.library.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn fresh<T: Clone>(&self, cached: Option<&Cached<T>>) -> Option<T> {
+        cached
+            .filter(|cached| now().saturating_sub(cached.fetched_at) < self.config.ttl_seconds)
+            .map(|cached| cached.items.clone())
+    }
+
+    fn shows(&self) -> Option<Vec<TVShow>> {
+        self.fresh(self.lock().shows.as_ref())
+    }
+
+    fn store_shows(&self, shows: Vec<TVShow>) {
+        let mut library = self.lock();
+        library.shows = Some(Cached {
+            fetched_at: now(),
+            items: shows,
+        });
+        self.save(&library);
+    }
+
+    fn episodes(&self, tvshowid: u64) -> Option<Vec<Episode>> {
+        self.fresh(self.lock().episodes.get(&tvshowid))
+    }
+
+    fn store_episodes(&self, tvshowid: u64, episodes: Vec<Episode>) {
+        let mut library = self.lock();
+        library.episodes.insert(
+            tvshowid,
+            Cached {
+                fetched_at: now(),
+                items: episodes,
+            },
+        );
+        self.save(&library);
+    }
+
+    pub(crate) fn clear(&self) {
+        let mut library = self.lock();
+        *library = CachedLibrary::default();
+        self.save(&library);
+    }
+
+    // Returns true if the event dropped anything
+    pub(crate) fn invalidate(&self, event: &KodiEvent) -> bool {
+        let changed = match event {
+            KodiEvent::VideoLibraryOnScanFinished => None,
+            KodiEvent::VideoLibraryOnUpdate { item } => Some(item),
+            KodiEvent::Other { method, .. } if method == "VideoLibrary.OnCleanFinished" => None,
+            KodiEvent::Other { method, data } if method == "VideoLibrary.OnRemove" => Some(data),
+            _ => return false,
+        };
+
+        let mut library = self.lock();
+        let dropped = match changed {
+            None => {
+                *library = CachedLibrary::default();
+                true
+            }
+            Some(data) => forget_item(&mut library, data),
+        };
+        if dropped {
+            self.save(&library);
+        }
+        dropped
+    }
+
+    fn save(&self, library: &CachedLibrary) {
+        let Some(path) = &self.config.path else {
+            return;
+        };
+        match serde_json::to_string(library) {
+            Ok(json) => {
+                if let Err(e) = std::fs::write(path, json) {
+                    eprintln!("Failed to save library cache to {:?}: {}", path, e);
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize library cache: {}", e),
+        }
+    }
+}
+
+// OnUpdate nests the item under `item`, OnRemove doesn't
+fn forget_item(library: &mut CachedLibrary, data: &Value) -> bool {
+    let item = data.get("item").unwrap_or(data);
+    let Some(id) = item["id"].as_u64() else {
+        return false;
+    };
+
+    match item["type"].as_str() {
+        Some("tvshow") => {
+            library.shows = None;
+            library.episodes.remove(&id);
+            true
+        }
+        Some("episode") => {
+            let show = library
+                .episodes
+                .iter()
+                .find(|(_, cached)| cached.items.iter().any(|e| e.episodeid == id))
+                .map(|(tvshowid, _)| *tvshowid);
+            match show {
+                Some(tvshowid) => {
+                    library.episodes.remove(&tvshowid);
+                }
+                // A new episode; we can't tell which show it belongs to
+                None => library.episodes.clear(),
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+impl RpcClient {
+    // Every show, with the titles and ids show lookup matches against
+    pub async fn library_shows(&self) -> Result<Vec<TVShow>, KodiError> {
+        if let Some(shows) = self.library_cache.as_ref().and_then(|c| c.shows()) {
+            return Ok(shows);
+        }
+
+        let params = GetTVShowsParams {
+            properties: properties(&["title", "originaltitle", "sorttitle", "year", "uniqueid"]),
+            ..Default::default()
+        };
+        let shows = self.get_tv_shows(&params).await?;
+        if let Some(cache) = &self.library_cache {
+            cache.store_shows(shows.clone());
+        }
+        Ok(shows)
+    }
+
+    // A show's episodes, with the watch state selection strategies need
+    pub async fn show_episodes(&self, tvshowid: u64) -> Result<Vec<Episode>, KodiError> {
+        if let Some(episodes) = self
+            .library_cache
+            .as_ref()
+            .and_then(|c| c.episodes(tvshowid))
+        {
+            return Ok(episodes);
+        }
+
+        let params = GetEpisodesParams {
+            properties: properties(&[
+                "title",
+                "season",
+                "episode",
+                "playcount",
+                "lastplayed",
+                "resume",
+                "runtime",
+            ]),
+            ..GetEpisodesParams::for_show(tvshowid)
+        };
+        let episodes = self.get_episodes(&params).await?;
+        if let Some(cache) = &self.library_cache {
+            cache.store_episodes(tvshowid, episodes.clone());
+        }
+        Ok(episodes)
+    }
+
+    // Feed library notifications here to keep the cache honest; returns true
+    // if anything was dropped. A no-op when the cache is off.
+    pub fn invalidate_library_cache(&self, event: &KodiEvent) -> bool {
+        self.library_cache
+            .as_ref()
+            .is_some_and(|cache| cache.invalidate(event))
+    }
+
+    pub fn clear_library_cache(&self) {
+        if let Some(cache) = &self.library_cache {
+            cache.clear();
+        }
+    }
+}
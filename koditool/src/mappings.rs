@@ -0,0 +1,475 @@
+use rand::prelude::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+
+use crate::strategy::without_recent;
+use crate::video_library::properties;
+use crate::{
+    find_channel, Channel, EpisodeFilter, Filter, GetChannelsParams, GetMoviesParams, KodiError,
+    Movie, RpcClient, SelectedEpisode, SelectionStrategy, ShowPin,
+};
+
+// A user's list in show_mappings.yml. Either a plain list of entries, or a
+// map when the user needs options:
+//
+//   kids:
+//     strategy: next_unwatched
+//     repeat_window:
+//       shows: 1
+//       episodes: 30
+//     shows:
+//       - Bluey
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(from = "UserMappingRepr", into = "UserMappingRepr")]
+pub struct UserMapping {
+    // Default for every entry that doesn't set its own
+    pub strategy: Option<SelectionStrategy>,
+    pub repeat_window: Option<RepeatWindow>,
+    pub shows: Vec<MappingEntry>,
+}
+
+// How many of a user's most recent plays to steer away from. Only applies
+// while there's something else left to pick.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct RepeatWindow {
+    // Entries (shows / movie filters) used by the last N plays
+    pub shows: usize,
+    // Episodes and movies from the last N plays
+    pub episodes: usize,
+}
+
+impl Default for RepeatWindow {
+    fn default() -> Self {
+        RepeatWindow {
+            shows: 1,
+            episodes: 20,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+enum UserMappingRepr {
+    List(Vec<MappingEntry>),
+    Detailed {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strategy: Option<SelectionStrategy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        repeat_window: Option<RepeatWindow>,
+        #[serde(default)]
+        shows: Vec<MappingEntry>,
+    },
+}
+
+impl From<UserMappingRepr> for UserMapping {
+    fn from(repr: UserMappingRepr) -> Self {
+        match repr {
+            UserMappingRepr::List(shows) => UserMapping {
+                shows,
+                ..Default::default()
+            },
+            UserMappingRepr::Detailed {
+                strategy,
+                repeat_window,
+                shows,
+            } => UserMapping {
+                strategy,
+                repeat_window,
+                shows,
+            },
+        }
+    }
+}
+
+// Written back in the short list form unless an option is set
+impl From<UserMapping> for UserMappingRepr {
+    fn from(mapping: UserMapping) -> Self {
+        match (mapping.strategy, mapping.repeat_window) {
+            (None, None) => UserMappingRepr::List(mapping.shows),
+            (strategy, repeat_window) => UserMappingRepr::Detailed {
+                strategy,
+                repeat_window,
+                shows: mapping.shows,
+            },
+        }
+    }
+}
+
+impl UserMapping {
+    // Weighted pick of the next entry, skipping any whose label is in
+    // `recent` unless nothing else is left. Entries with weight 0 are off.
+    pub fn choose_entry<R: Rng + ?Sized>(
+        &self,
+        recent: &[String],
+        rng: &mut R,
+    ) -> Option<&MappingEntry> {
+        let enabled: Vec<&MappingEntry> = self
+            .shows
+            .iter()
+            .filter(|entry| entry.weight() > 0)
+            .collect();
+        let fresh: Vec<&MappingEntry> = enabled
+            .iter()
+            .copied()
+            .filter(|entry| !recent.contains(&entry.to_string()))
+            .collect();
+        let pool = if fresh.is_empty() { enabled } else { fresh };
+
+        pool.choose_weighted(rng, |entry| entry.weight())
+            .ok()
+            .copied()
+    }
+}
+
+// One item in a user's list:
+//
+//   movie_night:
+//     - Bluey                     # a TV show, by title
+//     - show: Bluey               # the same, with options
+//       strategy: next_unwatched
+//       exclude_specials: true    # see EpisodeFilter for the rest
+//     - movies:                   # any movie matching every field given
+//         genre: Animation
+//         set: Studio Ghibli
+//     - channel: ABC Kids         # a live TV channel, by name
+//       channelid: 12             # optional, for names that don't find it
+//       minutes: 60               # how long it stays on (default 30)
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum MappingEntry {
+    Show(String),
+    Detailed(ShowEntry),
+    Movies {
+        movies: MovieFilter,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strategy: Option<SelectionStrategy>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<u32>,
+    },
+    Channel {
+        channel: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        channelid: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        minutes: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        weight: Option<u32>,
+    },
+}
+
+// How long a live channel stays on when its entry doesn't say. A channel
+// never ends by itself, so without a slot it would play forever.
+pub const DEFAULT_CHANNEL_MINUTES: f64 = 30.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ShowEntry {
+    pub show: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strategy: Option<SelectionStrategy>,
+    // Relative chance of being picked; 1 when unset, 0 disables the entry
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u32>,
+    // tvshowid / uniqueid, for titles that don't find the right show
+    #[serde(flatten)]
+    pub pin: ShowPin,
+    // Seasons, specials, exclusions and runtime limits
+    #[serde(flatten)]
+    pub filter: EpisodeFilter,
+}
+
+impl ShowEntry {
+    pub fn new(show: impl Into<String>) -> Self {
+        ShowEntry {
+            show: show.into(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct MovieFilter {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genre: Option<String>,
+    // Kodi's movie sets ("collections")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+impl MovieFilter {
+    // The keys under `movies:`; see `MappingEntry::SHOW_KEYS`
+    pub const KEYS: [&'static str; 4] = ["title", "genre", "set", "tag"];
+
+    fn rules(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("title", &self.title),
+            ("genre", &self.genre),
+            ("set", &self.set),
+            ("tag", &self.tag),
+        ]
+        .into_iter()
+        .filter_map(|(field, value)| value.as_deref().map(|value| (field, value)))
+        .collect()
+    }
+
+    // None when no field is set, i.e. every movie in the library
+    pub fn to_filter(&self) -> Option<Filter> {
+        let mut rules: Vec<Filter> = self
+            .rules()
+            .into_iter()
+            .map(|(field, value)| Filter::is(field, value))
+            .collect();
+
+        match rules.len() {
+            0 => None,
+            1 => rules.pop(),
+            _ => Some(Filter::And { and: rules }),
+        }
+    }
+}
+
+impl fmt::Display for MovieFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rules = self.rules();
+        if rules.is_empty() {
+            return write!(f, "Movies (any)");
+        }
+        let rules: Vec<String> = rules
+            .iter()
+            .map(|(field, value)| format!("{}: {}", field, value))
+            .collect();
+        write!(f, "Movies ({})", rules.join(", "))
+    }
+}
+
+impl MappingEntry {
+    // Every key each kind of entry reads. Serde skips unknown keys (and
+    // can't be told not to through `flatten`), so config checks compare an
+    // entry's keys against these. Tests hold them to the struct fields.
+    pub const SHOW_KEYS: [&'static str; 10] = [
+        "show",
+        "strategy",
+        "weight",
+        "tvshowid",
+        "uniqueid",
+        "seasons",
+        "exclude_specials",
+        "exclude",
+        "min_runtime",
+        "max_runtime",
+    ];
+    pub const MOVIES_KEYS: [&'static str; 3] = ["movies", "strategy", "weight"];
+    pub const CHANNEL_KEYS: [&'static str; 4] = ["channel", "channelid", "minutes", "weight"];
+
+    // The entry's own strategy, if it overrides the user's. A channel only
+    // ever has one thing to play.
+    pub fn strategy(&self) -> Option<SelectionStrategy> {
+        match self {
+            MappingEntry::Show(_) | MappingEntry::Channel { .. } => None,
+            MappingEntry::Detailed(entry) => entry.strategy,
+            MappingEntry::Movies { strategy, .. } => *strategy,
+        }
+    }
+
+    pub fn weight(&self) -> u32 {
+        match self {
+            MappingEntry::Show(_) => 1,
+            MappingEntry::Detailed(entry) => entry.weight.unwrap_or(1),
+            MappingEntry::Movies { weight, .. } | MappingEntry::Channel { weight, .. } => {
+                weight.unwrap_or(1)
+            }
+        }
+    }
+}
+
+// Per-call options for `select_for_entry`
+#[derive(Debug, Clone, Default)]
+pub struct SelectOptions {
+    // Applies unless the entry sets its own
+    pub strategy: SelectionStrategy,
+    // Recently played ids to skip while anything else is left
+    pub avoid_episodes: HashSet<u64>,
+    pub avoid_movies: HashSet<u64>,
+    // Ids never to pick, even if that leaves nothing
+    pub exclude_episodes: HashSet<u64>,
+    pub exclude_movies: HashSet<u64>,
+}
+
+impl fmt::Display for MappingEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MappingEntry::Show(title) => write!(f, "{}", title),
+            MappingEntry::Detailed(entry) => write!(f, "{}", entry.show),
+            MappingEntry::Movies { movies, .. } => write!(f, "{}", movies),
+            MappingEntry::Channel { channel, .. } => write!(f, "Channel ({})", channel),
+        }
+    }
+}
+
+// What was picked for a mapping entry, ready to hand to `play_selection`
+#[derive(Debug, Clone)]
+pub enum Selection {
+    Episode(SelectedEpisode),
+    Movie { movie: Box<Movie>, resume: bool },
+    Channel { channel: Box<Channel>, minutes: f64 },
+}
+
+impl Selection {
+    // How long to leave it on before picking again; episodes and movies
+    // play to the end
+    pub fn slot(&self) -> Option<Duration> {
+        match self {
+            Selection::Channel { minutes, .. } => Duration::try_from_secs_f64(minutes * 60.0).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn episode_id(&self) -> Option<u64> {
+        match self {
+            Selection::Episode(episode) => Some(episode._episode_id),
+            Selection::Movie { .. } | Selection::Channel { .. } => None,
+        }
+    }
+
+    pub fn movie_id(&self) -> Option<u64> {
+        match self {
+            Selection::Episode(_) | Selection::Channel { .. } => None,
+            Selection::Movie { movie, .. } => Some(movie.movieid),
+        }
+    }
+}
+
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selection::Episode(episode) => write!(f, "{}", episode.episode_file_path),
+            Selection::Movie { movie, .. } if movie.year > 0 => {
+                write!(f, "{} ({})", movie.title, movie.year)
+            }
+            Selection::Movie { movie, .. } => write!(f, "{}", movie.title),
+            Selection::Channel { channel, .. } => write!(f, "{} (live)", channel.label),
+        }
+    }
+}
+
+impl RpcClient {
+    pub async fn select_random_movie(&self, filter: &MovieFilter) -> Result<Movie, KodiError> {
+        let none = HashSet::new();
+        self.select_movie(filter, SelectionStrategy::Random, &none, &none)
+            .await
+    }
+
+    pub async fn select_movie(
+        &self,
+        filter: &MovieFilter,
+        strategy: SelectionStrategy,
+        avoid: &HashSet<u64>,
+        exclude: &HashSet<u64>,
+    ) -> Result<Movie, KodiError> {
+        let params = GetMoviesParams {
+            filter: filter.to_filter(),
+            properties: properties(&["title", "year", "file", "playcount", "lastplayed", "resume"]),
+            ..Default::default()
+        };
+        let movies = self.get_movies(&params).await?;
+
+        // Release order stands in for "playback order" within a filter
+        let mut playable: Vec<Movie> = movies
+            .into_iter()
+            .filter(|movie| movie.movieid != 0 && !movie.file.is_empty())
+            .filter(|movie| !exclude.contains(&movie.movieid))
+            .collect();
+        playable.sort_by(|a, b| a.year.cmp(&b.year).then_with(|| a.title.cmp(&b.title)));
+        let playable = without_recent(playable, avoid, |movie| movie.movieid);
+
+        strategy
+            .pick(&playable, &mut self.selection_rng())
+            .cloned()
+            .ok_or_else(|| KodiError::NotFound(format!("No movies match {}", filter)))
+    }
+
+    pub async fn select_for_entry(
+        &self,
+        entry: &MappingEntry,
+        options: &SelectOptions,
+    ) -> Result<Selection, KodiError> {
+        let strategy = entry.strategy().unwrap_or(options.strategy);
+
+        match entry {
+            MappingEntry::Show(title) => self
+                .select_episode(
+                    &ShowEntry::new(title),
+                    strategy,
+                    &options.avoid_episodes,
+                    &options.exclude_episodes,
+                )
+                .await
+                .map(Selection::Episode),
+            MappingEntry::Detailed(entry) => self
+                .select_episode(
+                    entry,
+                    strategy,
+                    &options.avoid_episodes,
+                    &options.exclude_episodes,
+                )
+                .await
+                .map(Selection::Episode),
+            MappingEntry::Movies { movies, .. } => {
+                let movie = self
+                    .select_movie(
+                        movies,
+                        strategy,
+                        &options.avoid_movies,
+                        &options.exclude_movies,
+                    )
+                    .await?;
+                Ok(Selection::Movie {
+                    resume: strategy.resumes(&movie),
+                    movie: Box::new(movie),
+                })
+            }
+            MappingEntry::Channel {
+                channel,
+                channelid,
+                minutes,
+                ..
+            } => {
+                let channels = self.get_channels(&GetChannelsParams::default()).await?;
+                let channel = find_channel(&channels, channel, *channelid)?;
+                Ok(Selection::Channel {
+                    channel: Box::new(channel.clone()),
+                    minutes: minutes
+                        .filter(|minutes| *minutes > 0.0)
+                        .unwrap_or(DEFAULT_CHANNEL_MINUTES),
+                })
+            }
+        }
+    }
+
+    // Movies are opened by id so Kodi tracks resume points and watched state
+    pub async fn play_movie(&self, movie: &Movie, resume: bool) -> Result<(), KodiError> {
+        let mut params = json!({ "item": { "movieid": movie.movieid } });
+        if resume {
+            params["options"] = json!({ "resume": true });
+        }
+        let _: Value = self.call_method("Player.Open", &params).await?;
+        Ok(())
+    }
+
+    pub async fn play_selection(&self, selection: &Selection) -> Result<(), KodiError> {
+        match selection {
+            Selection::Episode(episode) => self.rpc_play(episode).await,
+            Selection::Movie { movie, resume } => self.play_movie(movie, *resume).await,
+            Selection::Channel { channel, .. } => self.play_channel(channel.channelid).await,
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::KodiError;
+
+// Kodi's JSON-RPC TCP/WebSocket listener (Settings > Services > Control)
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 9090;
+
+const RECONNECT_DELAY_INITIAL: Duration = Duration::from_secs(1);
+const RECONNECT_DELAY_MAX: Duration = Duration::from_secs(30);
+const EVENT_BUFFER: usize = 64;
+
+// Notifications pushed by Kodi, plus the listener's own connection state so
+// consumers know when they have to fall back to polling
+#[derive(Debug, Clone, PartialEq)]
+pub enum KodiEvent {
+    Connected,
+    Disconnected,
+    PlayerOnPlay { item: Value },
+    PlayerOnAVStart { item: Value },
+    PlayerOnPause { item: Value },
+    // `ended` is true when the item played to the end rather than being stopped
+    PlayerOnStop { item: Value, ended: bool },
+    VideoLibraryOnUpdate { item: Value },
+    VideoLibraryOnScanFinished,
+    SystemOnQuit,
+    Other { method: String, data: Value },
+}
+
+impl KodiEvent {
+    // Turn a raw notification frame into an event; replies to our own
+    // requests (which carry an `id`) and junk frames yield None
+    pub fn from_notification(frame: &Value) -> Option<Self> {
+        if frame.get("id").is_some() {
+            return None;
+        }
+
+        let method = frame["method"].as_str()?;
+        let data = frame["params"]["data"].clone();
+        let item = data.get("item").cloned().unwrap_or(Value::Null);
+
+        let event = match method {
+            "Player.OnPlay" => KodiEvent::PlayerOnPlay { item },
+            "Player.OnAVStart" => KodiEvent::PlayerOnAVStart { item },
+            "Player.OnPause" => KodiEvent::PlayerOnPause { item },
+            "Player.OnStop" => KodiEvent::PlayerOnStop {
+                item,
+                ended: data["end"].as_bool().unwrap_or(false),
+            },
+            "VideoLibrary.OnUpdate" => KodiEvent::VideoLibraryOnUpdate {
+                item: data.clone(),
+            },
+            "VideoLibrary.OnScanFinished" => KodiEvent::VideoLibraryOnScanFinished,
+            "System.OnQuit" => KodiEvent::SystemOnQuit,
+            _ => KodiEvent::Other {
+                method: method.to_string(),
+                data,
+            },
+        };
+
+        Some(event)
+    }
+}
+
+// Live notification feed. The background connection (and its reconnect
+// loop) is torn down when this is dropped.
+#[derive(Debug)]
+pub struct Subscription {
+    events: mpsc::Receiver<KodiEvent>,
+    task: JoinHandle<()>,
+}
+
+impl Subscription {
+    pub(crate) fn spawn(url: String) -> Self {
+        let (sender, events) = mpsc::channel(EVENT_BUFFER);
+        let task = tokio::spawn(listen(url, sender));
+        Subscription { events, task }
+    }
+
+    // Wait for the next event; None only if the listener task has died
+    pub async fn recv(&mut self) -> Option<KodiEvent> {
+        self.events.recv().await
+    }
+
+    // Non-blocking variant for callers that drain between ticks
+    pub fn try_recv(&mut self) -> Option<KodiEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn listen(url: String, sender: mpsc::Sender<KodiEvent>) {
+    let mut delay = RECONNECT_DELAY_INITIAL;
+
+    loop {
+        match tokio_tungstenite::connect_async(url.as_str()).await {
+            Ok((mut socket, _)) => {
+                delay = RECONNECT_DELAY_INITIAL;
+                if sender.send(KodiEvent::Connected).await.is_err() {
+                    return;
+                }
+
+                while let Some(message) = socket.next().await {
+                    let text = match message {
+                        Ok(Message::Text(text)) => text,
+                        Ok(Message::Close(_)) | Err(_) => break,
+                        Ok(_) => continue,
+                    };
+
+                    let Ok(frame) = serde_json::from_str::<Value>(&text) else {
+                        continue;
+                    };
+
+                    if let Some(event) = KodiEvent::from_notification(&frame) {
+                        if sender.send(event).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+
+                if sender.send(KodiEvent::Disconnected).await.is_err() {
+                    return;
+                }
+            }
+            Err(_) => {
+                // Still unreachable; keep retrying unless nobody is listening
+                if sender.is_closed() {
+                    return;
+                }
+            }
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = std::cmp::min(delay * 2, RECONNECT_DELAY_MAX);
+    }
+}
+
+// ws://<kodi host>:9090/jsonrpc, derived from the HTTP url in config.yml
+pub(crate) fn websocket_url(http_url: &str) -> Result<String, KodiError> {
+    let mut url = reqwest::Url::parse(http_url)
+        .map_err(|e| KodiError::Transport(format!("Invalid Kodi url {}: {}", http_url, e)))?;
+
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    url.set_scheme(scheme)
+        .and_then(|_| url.set_port(Some(DEFAULT_WEBSOCKET_PORT)))
+        .map_err(|_| KodiError::Transport(format!("Cannot derive WebSocket url from {}", http_url)))?;
+    url.set_path("/jsonrpc");
+
+    Ok(url.to_string())
+}
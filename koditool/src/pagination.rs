@@ -0,0 +1,79 @@
+use crate::{
+    Album, Artist, Broadcast, Channel, ChannelGroup, Episode, GetAlbumsParams, GetArtistsParams,
+    GetBroadcastsParams, GetChannelGroupsParams, GetChannelsParams, GetEpisodesParams,
+    GetMoviesParams, GetSeasonsParams, GetSongsParams, GetTVShowsParams, Limits, LimitsReturned,
+    Movie, Season, Song, TVShow,
+};
+use serde::Deserialize;
+
+// Request params that carry a `limits` window Kodi can page through
+pub trait PagedParams {
+    fn limits(&self) -> &Limits;
+    fn limits_mut(&mut self) -> &mut Limits;
+}
+
+// A list reply: the items on this page plus Kodi's `limits` (with `total`)
+pub trait PagedResult {
+    type Item;
+    fn into_page(self) -> (Vec<Self::Item>, LimitsReturned);
+}
+
+macro_rules! impl_paged_params {
+    ($($params:ty),* $(,)?) => {
+        $(
+            impl PagedParams for $params {
+                fn limits(&self) -> &Limits {
+                    &self.limits
+                }
+
+                fn limits_mut(&mut self) -> &mut Limits {
+                    &mut self.limits
+                }
+            }
+        )*
+    };
+}
+
+impl_paged_params!(
+    GetTVShowsParams,
+    GetEpisodesParams,
+    GetSeasonsParams,
+    GetMoviesParams,
+    GetArtistsParams,
+    GetAlbumsParams,
+    GetSongsParams,
+    GetChannelGroupsParams,
+    GetChannelsParams,
+    GetBroadcastsParams,
+);
+
+// Kodi omits the list key when a page is empty, hence the defaults
+macro_rules! paged_result {
+    ($name:ident, $field:ident, $item:ty) => {
+        #[derive(Debug, Deserialize, Clone, Default)]
+        #[serde(default)]
+        pub struct $name {
+            pub $field: Vec<$item>,
+            pub limits: LimitsReturned,
+        }
+
+        impl PagedResult for $name {
+            type Item = $item;
+
+            fn into_page(self) -> (Vec<$item>, LimitsReturned) {
+                (self.$field, self.limits)
+            }
+        }
+    };
+}
+
+paged_result!(GetTVShowsResult, tvshows, TVShow);
+paged_result!(GetEpisodesResult, episodes, Episode);
+paged_result!(GetSeasonsResult, seasons, Season);
+paged_result!(GetMoviesResult, movies, Movie);
+paged_result!(GetArtistsResult, artists, Artist);
+paged_result!(GetAlbumsResult, albums, Album);
+paged_result!(GetSongsResult, songs, Song);
+paged_result!(GetChannelGroupsResult, channelgroups, ChannelGroup);
+paged_result!(GetChannelsResult, channels, Channel);
+paged_result!(GetBroadcastsResult, broadcasts, Broadcast);
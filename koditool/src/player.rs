@@ -0,0 +1,439 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+use crate::{KodiError, RpcClient};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlayerType {
+    Video,
+    Audio,
+    Picture,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActivePlayer {
+    pub playerid: i64,
+    #[serde(rename = "type")]
+    pub player_type: PlayerType,
+}
+
+// Kodi's Global.Time
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PlayerTime {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub milliseconds: u32,
+}
+
+impl PlayerTime {
+    pub fn from_seconds(total: u64) -> Self {
+        PlayerTime {
+            hours: (total / 3600) as u32,
+            minutes: ((total % 3600) / 60) as u32,
+            seconds: (total % 60) as u32,
+            milliseconds: 0,
+        }
+    }
+
+    pub fn as_seconds(&self) -> u64 {
+        self.hours as u64 * 3600 + self.minutes as u64 * 60 + self.seconds as u64
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PlayerProperties {
+    pub time: PlayerTime,
+    pub totaltime: PlayerTime,
+    pub percentage: f64,
+    pub speed: i32,
+    pub shuffled: bool,
+    pub repeat: String,
+}
+
+// What Player.Seek reports back
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SeekResult {
+    pub percentage: f64,
+    pub time: PlayerTime,
+    pub totaltime: PlayerTime,
+}
+
+// Kodi accepts true/false/"toggle" for on/off style parameters
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Toggle {
+    On,
+    Off,
+    Toggle,
+}
+
+impl Toggle {
+    pub(crate) fn to_param(self) -> Value {
+        match self {
+            Toggle::On => json!(true),
+            Toggle::Off => json!(false),
+            Toggle::Toggle => json!("toggle"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeekTarget {
+    // Jump to a position from the start of the item
+    Absolute(PlayerTime),
+    // Skip forward (positive) or back (negative) by this many seconds
+    Relative(i64),
+    Percentage(f64),
+}
+
+impl SeekTarget {
+    fn to_param(self) -> Value {
+        match self {
+            SeekTarget::Absolute(time) => json!({ "time": time }),
+            SeekTarget::Relative(seconds) => json!({ "seconds": seconds }),
+            SeekTarget::Percentage(percentage) => json!({ "percentage": percentage }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GoTo {
+    Next,
+    Previous,
+    // Zero-based playlist position
+    Position(u32),
+}
+
+impl GoTo {
+    fn to_param(self) -> Value {
+        match self {
+            GoTo::Next => json!("next"),
+            GoTo::Previous => json!("previous"),
+            GoTo::Position(position) => json!(position),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleSelection {
+    Off,
+    On,
+    Next,
+    Previous,
+    // Zero-based subtitle stream index
+    Stream(u32),
+}
+
+impl SubtitleSelection {
+    fn to_param(self) -> Value {
+        match self {
+            SubtitleSelection::Off => json!("off"),
+            SubtitleSelection::On => json!("on"),
+            SubtitleSelection::Next => json!("next"),
+            SubtitleSelection::Previous => json!("previous"),
+            SubtitleSelection::Stream(index) => json!(index),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioStreamSelection {
+    Next,
+    Previous,
+    // Zero-based audio stream index
+    Stream(u32),
+}
+
+impl AudioStreamSelection {
+    fn to_param(self) -> Value {
+        match self {
+            AudioStreamSelection::Next => json!("next"),
+            AudioStreamSelection::Previous => json!("previous"),
+            AudioStreamSelection::Stream(index) => json!(index),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    Off,
+    One,
+    All,
+    Cycle,
+}
+
+// Kodi's List.Item type field
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Episode,
+    Movie,
+    MusicVideo,
+    Song,
+    Picture,
+    Channel,
+    #[default]
+    #[serde(other)]
+    Unknown,
+}
+
+// The raw Player.GetItem item; only the properties we ask for are filled in
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct PlayerItem {
+    #[serde(rename = "type")]
+    pub media_type: MediaType,
+    pub id: i64,
+    pub label: String,
+    pub title: String,
+    pub showtitle: String,
+    // -1 when the item isn't an episode
+    pub season: i32,
+    pub episode: i32,
+    pub artist: Vec<String>,
+    pub album: String,
+    pub file: String,
+    pub thumbnail: String,
+    pub art: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetItemResult {
+    pub item: PlayerItem,
+}
+
+// What the active player is showing, flattened for display. Fields that
+// don't apply to the media type are None.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct NowPlaying {
+    pub media_type: MediaType,
+    pub player_type: PlayerType,
+    // Episode/movie/song title, or the channel name for live TV
+    pub title: String,
+    pub show_title: Option<String>,
+    pub season: Option<i32>,
+    pub episode: Option<i32>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub file: String,
+    pub elapsed: PlayerTime,
+    pub total: PlayerTime,
+    pub percentage: f64,
+    pub paused: bool,
+    // Kodi image:// url, fetchable through its /image/ endpoint
+    pub thumbnail: Option<String>,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+impl NowPlaying {
+    pub fn from_parts(
+        player_type: PlayerType,
+        item: PlayerItem,
+        properties: PlayerProperties,
+    ) -> Self {
+        let is_episode = item.media_type == MediaType::Episode;
+        let thumbnail = ["thumb", "poster", "tvshow.poster"]
+            .iter()
+            .find_map(|key| item.art.get(*key).cloned().and_then(non_empty))
+            .or_else(|| non_empty(item.thumbnail));
+        let title = if item.title.is_empty() || item.media_type == MediaType::Channel {
+            item.label
+        } else {
+            item.title
+        };
+
+        NowPlaying {
+            media_type: item.media_type,
+            player_type,
+            title,
+            show_title: non_empty(item.showtitle),
+            season: Some(item.season).filter(|season| is_episode && *season >= 0),
+            episode: Some(item.episode).filter(|episode| is_episode && *episode >= 0),
+            artist: non_empty(item.artist.join(", ")),
+            album: non_empty(item.album),
+            file: item.file,
+            elapsed: properties.time,
+            total: properties.totaltime,
+            percentage: properties.percentage,
+            paused: properties.speed == 0,
+            thumbnail,
+        }
+    }
+}
+
+const ITEM_PROPERTIES: [&str; 9] = [
+    "title",
+    "showtitle",
+    "season",
+    "episode",
+    "artist",
+    "album",
+    "file",
+    "thumbnail",
+    "art",
+];
+
+const PLAYER_PROPERTIES: [&str; 6] = [
+    "time",
+    "totaltime",
+    "percentage",
+    "speed",
+    "shuffled",
+    "repeat",
+];
+
+impl RpcClient {
+    pub async fn get_active_players(&self) -> Result<Vec<ActivePlayer>, KodiError> {
+        self.call_method("Player.GetActivePlayers", &json!({})).await
+    }
+
+    pub async fn active_player_id(&self) -> Result<Option<i64>, KodiError> {
+        let players = self.get_active_players().await?;
+        Ok(preferred_player(&players).map(|player| player.playerid))
+    }
+
+    async fn require_player_id(&self) -> Result<i64, KodiError> {
+        self.active_player_id()
+            .await?
+            .ok_or_else(|| KodiError::NotFound("No active player".to_string()))
+    }
+
+    // Returns the new playback speed (0 = paused)
+    pub async fn play_pause(&self, play: Toggle) -> Result<i32, KodiError> {
+        let playerid = self.require_player_id().await?;
+        let result: Value = self
+            .call_method(
+                "Player.PlayPause",
+                &json!({ "playerid": playerid, "play": play.to_param() }),
+            )
+            .await?;
+        Ok(result["speed"].as_i64().unwrap_or(0) as i32)
+    }
+
+    pub async fn seek(&self, target: SeekTarget) -> Result<SeekResult, KodiError> {
+        let playerid = self.require_player_id().await?;
+        self.call_method(
+            "Player.Seek",
+            &json!({ "playerid": playerid, "value": target.to_param() }),
+        )
+        .await
+    }
+
+    pub async fn go_to(&self, to: GoTo) -> Result<(), KodiError> {
+        let playerid = self.require_player_id().await?;
+        let _: Value = self
+            .call_method("Player.GoTo", &json!({ "playerid": playerid, "to": to.to_param() }))
+            .await?;
+        Ok(())
+    }
+
+    // -32..32; 1 is normal playback, 0 pauses, negative rewinds
+    pub async fn set_speed(&self, speed: i32) -> Result<i32, KodiError> {
+        let playerid = self.require_player_id().await?;
+        let result: Value = self
+            .call_method("Player.SetSpeed", &json!({ "playerid": playerid, "speed": speed }))
+            .await?;
+        Ok(result["speed"].as_i64().unwrap_or(speed as i64) as i32)
+    }
+
+    pub async fn set_subtitle(&self, subtitle: SubtitleSelection) -> Result<(), KodiError> {
+        let playerid = self.require_player_id().await?;
+        let mut params = json!({ "playerid": playerid, "subtitle": subtitle.to_param() });
+        // Picking a stream should also make it visible
+        if let SubtitleSelection::Stream(_) | SubtitleSelection::Next | SubtitleSelection::Previous =
+            subtitle
+        {
+            params["enable"] = json!(true);
+        }
+        let _: Value = self.call_method("Player.SetSubtitle", &params).await?;
+        Ok(())
+    }
+
+    pub async fn set_audio_stream(&self, stream: AudioStreamSelection) -> Result<(), KodiError> {
+        let playerid = self.require_player_id().await?;
+        let _: Value = self
+            .call_method(
+                "Player.SetAudioStream",
+                &json!({ "playerid": playerid, "stream": stream.to_param() }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_repeat(&self, repeat: RepeatMode) -> Result<(), KodiError> {
+        let playerid = self.require_player_id().await?;
+        let _: Value = self
+            .call_method("Player.SetRepeat", &json!({ "playerid": playerid, "repeat": repeat }))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_shuffle(&self, shuffle: Toggle) -> Result<(), KodiError> {
+        let playerid = self.require_player_id().await?;
+        let _: Value = self
+            .call_method(
+                "Player.SetShuffle",
+                &json!({ "playerid": playerid, "shuffle": shuffle.to_param() }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    // None when nothing is playing. Item and progress come back in one batch
+    // so the two can't describe different items.
+    pub async fn now_playing(&self) -> Result<Option<NowPlaying>, KodiError> {
+        let players = self.get_active_players().await?;
+        let Some(player) = preferred_player(&players) else {
+            return Ok(None);
+        };
+
+        let mut batch = self.batch();
+        let item = batch.add::<_, GetItemResult>(
+            "Player.GetItem",
+            &json!({ "playerid": player.playerid, "properties": ITEM_PROPERTIES }),
+        );
+        let properties = batch.add::<_, PlayerProperties>(
+            "Player.GetProperties",
+            &json!({ "playerid": player.playerid, "properties": PLAYER_PROPERTIES }),
+        );
+        let results = batch.send().await?;
+
+        Ok(Some(NowPlaying::from_parts(
+            player.player_type,
+            results.get(&item)?.item,
+            results.get(&properties)?,
+        )))
+    }
+
+    pub async fn get_player_properties(&self) -> Result<PlayerProperties, KodiError> {
+        let playerid = self.require_player_id().await?;
+        self.call_method(
+            "Player.GetProperties",
+            &json!({ "playerid": playerid, "properties": PLAYER_PROPERTIES }),
+        )
+        .await
+    }
+}
+
+// The player to drive: video wins over audio, audio over pictures
+fn preferred_player(players: &[ActivePlayer]) -> Option<&ActivePlayer> {
+    [PlayerType::Video, PlayerType::Audio, PlayerType::Picture]
+        .iter()
+        .find_map(|kind| players.iter().find(|player| player.player_type == *kind))
+        .or_else(|| players.first())
+}
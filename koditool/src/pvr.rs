@@ -0,0 +1,219 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fmt;
+
+use crate::video_library::{default_limits, properties};
+use crate::{
+    GetBroadcastsResult, GetChannelGroupsResult, GetChannelsResult, KodiError, Limits, RpcClient,
+};
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ChannelType {
+    #[default]
+    Tv,
+    Radio,
+}
+
+impl fmt::Display for ChannelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelType::Tv => write!(f, "tv"),
+            ChannelType::Radio => write!(f, "radio"),
+        }
+    }
+}
+
+// A numeric group id, or Kodi's built-in "alltv"/"allradio" groups
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ChannelGroupId {
+    Id(u64),
+    Named(String),
+}
+
+impl ChannelGroupId {
+    pub fn all(channel_type: ChannelType) -> Self {
+        ChannelGroupId::Named(format!("all{}", channel_type))
+    }
+}
+
+// ---- request params ----
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetChannelGroupsParams {
+    pub channeltype: ChannelType,
+    pub limits: Limits,
+}
+
+impl GetChannelGroupsParams {
+    pub fn new(channeltype: ChannelType) -> Self {
+        GetChannelGroupsParams {
+            channeltype,
+            limits: default_limits(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetChannelsParams {
+    pub channelgroupid: ChannelGroupId,
+    pub properties: Vec<String>,
+    pub limits: Limits,
+}
+
+impl GetChannelsParams {
+    pub fn for_group(channelgroupid: ChannelGroupId) -> Self {
+        GetChannelsParams {
+            channelgroupid,
+            properties: properties(&[
+                "channel",
+                "channeltype",
+                "channelnumber",
+                "hidden",
+                "locked",
+                "thumbnail",
+            ]),
+            limits: default_limits(),
+        }
+    }
+}
+
+impl Default for GetChannelsParams {
+    fn default() -> Self {
+        GetChannelsParams::for_group(ChannelGroupId::all(ChannelType::Tv))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetBroadcastsParams {
+    pub channelid: u64,
+    pub properties: Vec<String>,
+    pub limits: Limits,
+}
+
+impl GetBroadcastsParams {
+    pub fn for_channel(channelid: u64) -> Self {
+        GetBroadcastsParams {
+            channelid,
+            properties: properties(&[
+                "title",
+                "plot",
+                "starttime",
+                "endtime",
+                "runtime",
+                "genre",
+                "episodename",
+                "isactive",
+            ]),
+            limits: default_limits(),
+        }
+    }
+}
+
+// ---- response models ----
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ChannelGroup {
+    pub channelgroupid: u64,
+    pub label: String,
+    pub channeltype: ChannelType,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Channel {
+    pub channelid: u64,
+    pub label: String,
+    pub channel: String,
+    pub channeltype: ChannelType,
+    pub channelnumber: u32,
+    pub hidden: bool,
+    pub locked: bool,
+    pub thumbnail: String,
+}
+
+// One EPG entry; times are UTC "YYYY-MM-DD HH:MM:SS" strings as Kodi sends them
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Broadcast {
+    pub broadcastid: u64,
+    pub label: String,
+    pub title: String,
+    pub plot: String,
+    pub starttime: String,
+    pub endtime: String,
+    // Minutes
+    pub runtime: u64,
+    pub genre: Vec<String>,
+    pub episodename: String,
+    // Airing right now
+    pub isactive: bool,
+}
+
+// The TV channel a mapping entry names: by `channelid` when given, else by
+// name, compared case-insensitively with both the label and the channel name
+pub fn find_channel<'a>(
+    channels: &'a [Channel],
+    name: &str,
+    channelid: Option<u64>,
+) -> Result<&'a Channel, KodiError> {
+    if let Some(channelid) = channelid {
+        return channels
+            .iter()
+            .find(|channel| channel.channelid == channelid)
+            .ok_or_else(|| {
+                KodiError::NotFound(format!(
+                    "Channel {} not found with channelid {}",
+                    name, channelid
+                ))
+            });
+    }
+
+    channels
+        .iter()
+        .find(|channel| {
+            channel.label.eq_ignore_ascii_case(name) || channel.channel.eq_ignore_ascii_case(name)
+        })
+        .ok_or_else(|| KodiError::NotFound(format!("Channel {} not found", name)))
+}
+
+impl RpcClient {
+    pub async fn get_channel_groups(
+        &self,
+        channel_type: ChannelType,
+    ) -> Result<Vec<ChannelGroup>, KodiError> {
+        self.call_paginated::<_, GetChannelGroupsResult>(
+            "PVR.GetChannelGroups",
+            &GetChannelGroupsParams::new(channel_type),
+        )
+        .await
+    }
+
+    pub async fn get_channels(
+        &self,
+        params: &GetChannelsParams,
+    ) -> Result<Vec<Channel>, KodiError> {
+        self.call_paginated::<_, GetChannelsResult>("PVR.GetChannels", params)
+            .await
+    }
+
+    pub async fn get_broadcasts(
+        &self,
+        params: &GetBroadcastsParams,
+    ) -> Result<Vec<Broadcast>, KodiError> {
+        self.call_paginated::<_, GetBroadcastsResult>("PVR.GetBroadcasts", params)
+            .await
+    }
+
+    pub async fn play_channel(&self, channelid: u64) -> Result<(), KodiError> {
+        let _: Value = self
+            .call_method(
+                "Player.Open",
+                &json!({ "item": { "channelid": channelid } }),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
-mod kodi_helper;
-use kodi_helper::Config;
-use kodi_helper::RpcClient;
+use koditool::Config;
+use koditool::RpcClient;
+use koditool::SelectionStrategy;
 
 use std::env;
 use std::error::Error;
@@ -10,22 +10,25 @@ async fn main() -> Result<(), Box<dyn Error>> {
     // Load configuration from YAML
     let config = Config::load("config.yml")?;
 
-    // Get the TV show name from the command-line arguments
+    // Get the TV show name (and optional --strategy) from the command-line arguments
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        eprintln!("Usage: {} <TV Show Name>", args[0]);
-        return Ok(());
-    }
+    let (tv_show_name, strategy) = match args.as_slice() {
+        [_, name] => (name, SelectionStrategy::Random),
+        [_, name, flag, strategy] if flag == "--strategy" => (name, strategy.parse()?),
+        _ => {
+            eprintln!("Usage: {} <TV Show Name> [--strategy <random|next_unwatched|resume_in_progress|oldest_lastplayed>]", args[0]);
+            return Ok(());
+        }
+    };
 
-    let tv_show_name = &args[1];
-    println!("[-] target => {:?}", tv_show_name);
+    println!("[-] target => {:?} ({})", tv_show_name, strategy);
 
     // build RPC client and run
     let rpc_client = RpcClient::new(config)?;
 
-    // Call the function to select a random episode by title
+    // Call the function to select an episode by title
     let selected_episode = rpc_client
-        .select_random_episode_by_title(tv_show_name)
+        .select_episode_by_title(tv_show_name, strategy)
         .await?;
 
     // Call the function to play the selected episode
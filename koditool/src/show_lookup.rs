@@ -0,0 +1,180 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{KodiError, RpcClient, TVShow};
+
+// Pins a mapping entry to one library show when its title is ambiguous or
+// differs from Kodi's. Flattened into a `show:` entry:
+//
+//   - show: Doctor Who
+//     uniqueid: { tvdb: 78804 }
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ShowPin {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvshowid: Option<u64>,
+    // Scraper ids, e.g. imdb: tt0436992, tvdb: 78804
+    #[serde(
+        skip_serializing_if = "BTreeMap::is_empty",
+        deserialize_with = "string_values"
+    )]
+    pub uniqueid: BTreeMap<String, String>,
+}
+
+// YAML reads `tvdb: 78804` as a number
+fn string_values<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Text(String),
+        Number(u64),
+    }
+
+    let ids: BTreeMap<String, Id> = BTreeMap::deserialize(deserializer)?;
+    Ok(ids
+        .into_iter()
+        .map(|(key, id)| match id {
+            Id::Text(text) => (key, text),
+            Id::Number(number) => (key, number.to_string()),
+        })
+        .collect())
+}
+
+impl ShowPin {
+    pub fn is_empty(&self) -> bool {
+        self.tvshowid.is_none() && self.uniqueid.is_empty()
+    }
+
+    fn matches(&self, show: &TVShow) -> bool {
+        if let Some(id) = self.tvshowid {
+            return show.tvshowid == id;
+        }
+        self.uniqueid.iter().any(|(kind, id)| {
+            show.uniqueid.iter().any(|(show_kind, show_id)| {
+                show_kind.eq_ignore_ascii_case(kind) && show_id.trim() == id.trim()
+            })
+        })
+    }
+}
+
+// Lowercased, accents stripped, punctuation collapsed to single spaces:
+// "Pokémon: Indigo League" -> "pokemon indigo league"
+pub fn normalize_title(title: &str) -> String {
+    let folded: String = title
+        .nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// "doctor who 2005" -> "doctor who"
+fn without_year(normalized: &str) -> &str {
+    match normalized.rsplit_once(' ') {
+        Some((rest, year)) if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) => rest,
+        _ => normalized,
+    }
+}
+
+fn show_titles(show: &TVShow) -> impl Iterator<Item = String> + '_ {
+    [&show.title, &show.originaltitle, &show.sorttitle]
+        .into_iter()
+        .filter(|title| !title.is_empty())
+        .map(|title| normalize_title(title))
+}
+
+// Pick `title` out of the library. A pin wins outright; otherwise an exact
+// title, then a loose match on title/originaltitle/sorttitle (with and
+// without a year suffix). Misses suggest the closest titles.
+pub fn find_show<'a>(
+    shows: &'a [TVShow],
+    title: &str,
+    pin: &ShowPin,
+) -> Result<&'a TVShow, KodiError> {
+    if !pin.is_empty() {
+        return shows.iter().find(|show| pin.matches(show)).ok_or_else(|| {
+            KodiError::NotFound(format!("TV show {} not found with {:?}", title, pin))
+        });
+    }
+
+    if let Some(show) = shows.iter().find(|show| show.title == title) {
+        return Ok(show);
+    }
+
+    let wanted = normalize_title(title);
+    let loose_passes: [fn(&str) -> &str; 2] = [|key| key, without_year];
+    for key in loose_passes {
+        let matches: Vec<&TVShow> = shows
+            .iter()
+            .filter(|show| show_titles(show).any(|t| key(&t) == key(&wanted)))
+            .collect();
+        match matches.as_slice() {
+            [] => continue,
+            [show] => return Ok(show),
+            several => {
+                let names: Vec<String> = several.iter().map(|show| display_title(show)).collect();
+                return Err(KodiError::NotFound(format!(
+                    "TV show {} matches several shows ({}); pin one with tvshowid or uniqueid",
+                    title,
+                    names.join(", ")
+                )));
+            }
+        }
+    }
+
+    let suggestions = suggest(shows, &wanted);
+    if suggestions.is_empty() {
+        Err(KodiError::NotFound(format!("TV show {} not found", title)))
+    } else {
+        Err(KodiError::NotFound(format!(
+            "TV show {} not found; did you mean: {}?",
+            title,
+            suggestions.join(", ")
+        )))
+    }
+}
+
+fn display_title(show: &TVShow) -> String {
+    if show.year > 0 && !show.title.ends_with(')') {
+        format!("{} ({}, id {})", show.title, show.year, show.tvshowid)
+    } else {
+        format!("{} (id {})", show.title, show.tvshowid)
+    }
+}
+
+// Up to three titles that look like a typo or partial of `wanted`
+fn suggest(shows: &[TVShow], wanted: &str) -> Vec<String> {
+    let mut scored: Vec<(f64, &TVShow)> = shows
+        .iter()
+        .filter_map(|show| {
+            let score = show_titles(show)
+                .map(|title| {
+                    if !wanted.is_empty() && (title.contains(wanted) || wanted.contains(&title)) {
+                        0.9
+                    } else {
+                        strsim::normalized_levenshtein(&title, wanted)
+                    }
+                })
+                .fold(0.0, f64::max);
+            (score >= 0.5).then_some((score, show))
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    scored
+        .into_iter()
+        .take(3)
+        .map(|(_, show)| show.title.clone())
+        .collect()
+}
+
+impl RpcClient {
+    pub async fn find_tv_show(&self, title: &str, pin: &ShowPin) -> Result<TVShow, KodiError> {
+        let shows = self.library_shows().await?;
+        find_show(&shows, title, pin).cloned()
+    }
+}
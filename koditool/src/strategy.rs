@@ -0,0 +1,149 @@
+use rand::prelude::IndexedRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Episode, Movie};
+
+// How to pick an item out of a show's episodes (or a movie entry's matches).
+// Everything except `Random` walks the list in playback order.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionStrategy {
+    #[default]
+    Random,
+    // First item with a playcount of 0; once everything is watched,
+    // whichever was played longest ago
+    NextUnwatched,
+    // The most recently played item with a resume point, else next unwatched
+    ResumeInProgress,
+    // Never-played items first, then the one played longest ago
+    OldestLastPlayed,
+}
+
+// The watch-state fields every strategy needs
+pub trait Watchable {
+    fn playcount(&self) -> u64;
+    // "YYYY-MM-DD HH:MM:SS", empty if never played
+    fn lastplayed(&self) -> &str;
+    fn resume_position(&self) -> f64;
+}
+
+impl Watchable for Episode {
+    fn playcount(&self) -> u64 {
+        self.playcount
+    }
+
+    fn lastplayed(&self) -> &str {
+        &self.lastplayed
+    }
+
+    fn resume_position(&self) -> f64 {
+        self.resume.position
+    }
+}
+
+impl Watchable for Movie {
+    fn playcount(&self) -> u64 {
+        self.playcount
+    }
+
+    fn lastplayed(&self) -> &str {
+        &self.lastplayed
+    }
+
+    fn resume_position(&self) -> f64 {
+        self.resume.position
+    }
+}
+
+impl SelectionStrategy {
+    pub const ALL: [SelectionStrategy; 4] = [
+        SelectionStrategy::Random,
+        SelectionStrategy::NextUnwatched,
+        SelectionStrategy::ResumeInProgress,
+        SelectionStrategy::OldestLastPlayed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionStrategy::Random => "random",
+            SelectionStrategy::NextUnwatched => "next_unwatched",
+            SelectionStrategy::ResumeInProgress => "resume_in_progress",
+            SelectionStrategy::OldestLastPlayed => "oldest_lastplayed",
+        }
+    }
+
+    // `items` must already be in playback order (season/episode, year/title)
+    pub fn pick<'a, T: Watchable, R: Rng + ?Sized>(
+        &self,
+        items: &'a [T],
+        rng: &mut R,
+    ) -> Option<&'a T> {
+        match self {
+            SelectionStrategy::Random => items.choose(rng),
+            SelectionStrategy::NextUnwatched => items
+                .iter()
+                .find(|item| item.playcount() == 0)
+                .or_else(|| oldest_lastplayed(items)),
+            SelectionStrategy::ResumeInProgress => items
+                .iter()
+                .filter(|item| item.resume_position() > 0.0)
+                .max_by(|a, b| a.lastplayed().cmp(b.lastplayed()))
+                .or_else(|| SelectionStrategy::NextUnwatched.pick(items, rng)),
+            SelectionStrategy::OldestLastPlayed => oldest_lastplayed(items),
+        }
+    }
+
+    // Whether playback should pick up from the item's resume point
+    pub fn resumes<T: Watchable>(&self, item: &T) -> bool {
+        *self != SelectionStrategy::Random && item.resume_position() > 0.0
+    }
+}
+
+// min_by_key keeps the first of equal keys, so ties go to playback order
+fn oldest_lastplayed<T: Watchable>(items: &[T]) -> Option<&T> {
+    items.iter().min_by_key(|item| item.lastplayed())
+}
+
+// Drop recently played items, unless that would leave nothing to pick
+pub(crate) fn without_recent<T>(
+    items: Vec<T>,
+    avoid: &HashSet<u64>,
+    id: impl Fn(&T) -> u64,
+) -> Vec<T> {
+    if avoid.is_empty() || items.iter().all(|item| avoid.contains(&id(item))) {
+        return items;
+    }
+    items
+        .into_iter()
+        .filter(|item| !avoid.contains(&id(item)))
+        .collect()
+}
+
+impl fmt::Display for SelectionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for SelectionStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim().to_lowercase().replace('-', "_");
+        SelectionStrategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == wanted)
+            .ok_or_else(|| {
+                let names: Vec<&str> = SelectionStrategy::ALL.iter().map(|s| s.name()).collect();
+                format!(
+                    "Unknown strategy '{}' (expected one of: {})",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
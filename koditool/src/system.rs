@@ -0,0 +1,11 @@
+use serde_json::{json, Value};
+
+use crate::{KodiError, RpcClient};
+
+impl RpcClient {
+    // Puts the Kodi box to sleep; it stops answering until something wakes it
+    pub async fn suspend(&self) -> Result<(), KodiError> {
+        let _: Value = self.call_method("System.Suspend", &json!({})).await?;
+        Ok(())
+    }
+}
@@ -1,19 +1,19 @@
-mod kodi_helper;
-use kodi_helper::Config;
-use kodi_helper::RpcClient;
+use koditool::Config;
+use koditool::RpcClient;
+use koditool::SelectOptions;
+use koditool::UserMapping;
 
-use rand::prelude::IndexedRandom;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::io::{self, Write};
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 
 use serde::Deserialize;
 #[derive(Debug, Deserialize)]
 struct ShowMappings {
     #[serde(flatten)]
-    shows: HashMap<String, Vec<String>>,
+    shows: HashMap<String, UserMapping>,
 }
 
 fn load_show_mappings() -> Result<ShowMappings, Box<dyn std::error::Error>> {
@@ -22,10 +22,6 @@ fn load_show_mappings() -> Result<ShowMappings, Box<dyn std::error::Error>> {
     Ok(show_mappings)
 }
 
-fn select_random_show_name(shows: &[String]) -> Option<&String> {
-    shows.choose(&mut rand::rng())
-}
-
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Config::load("config.yml")?;
@@ -39,10 +35,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let user = &args[1];
 
     let show_mappings = load_show_mappings()?;
-    let user_shows = show_mappings
+    let user_mapping = show_mappings
         .shows
         .get(user)
         .ok_or("User not found")?;
+    let user_shows = &user_mapping.shows;
+    let strategy = user_mapping.strategy.unwrap_or_default();
+    let window = user_mapping.repeat_window.unwrap_or_default();
+
+    // Only remembered for this run; tv_mode_web keeps a persisted history
+    let mut recent: VecDeque<(String, Option<u64>, Option<u64>)> = VecDeque::new();
 
     if user_shows.is_empty() {
         eprintln!("No shows available for this user.");
@@ -51,19 +53,61 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let spinner_chars = "|/-\\";
     let mut spinner_index = 0;
+    // When the live channel we put on has had its slot
+    let mut slot_end: Option<Instant> = None;
 
     loop {
-        if !rpc_client.is_active().await? {
-            let selected_show_name =
-                select_random_show_name(user_shows).expect("No show available");
-            println!("[-] selected show => {:?}", selected_show_name);
+        let mut active = rpc_client.is_active().await?;
+        if active && slot_end.is_some_and(|end| Instant::now() >= end) {
+            println!("\n[-] channel slot is over, moving on");
+            rpc_client.rpc_stop().await?;
+            active = false;
+        }
+
+        if !active {
+            let recent_labels: Vec<String> = recent
+                .iter()
+                .take(window.shows)
+                .map(|(label, _, _)| label.clone())
+                .collect();
+            let selected_entry = user_mapping
+                .choose_entry(&recent_labels, &mut rand::rng())
+                .ok_or("No enabled shows for this user (every entry has weight 0)")?;
+            println!("[-] selected show => {}", selected_entry);
 
             println!("\n[!] no show playing, calling other Rust binary...\n");
 
-            let selected_episode = rpc_client
-                .select_random_episode_by_title(selected_show_name)
+            let selection = rpc_client
+                .select_for_entry(
+                    selected_entry,
+                    &SelectOptions {
+                        strategy,
+                        avoid_episodes: recent
+                            .iter()
+                            .take(window.episodes)
+                            .filter_map(|(_, episode, _)| *episode)
+                            .collect(),
+                        avoid_movies: recent
+                            .iter()
+                            .take(window.episodes)
+                            .filter_map(|(_, _, movie)| *movie)
+                            .collect(),
+                        ..Default::default()
+                    },
+                )
                 .await?;
-            rpc_client.rpc_play(&selected_episode).await?;
+            rpc_client.play_selection(&selection).await?;
+            println!("[+] playing => {}", selection);
+            slot_end = selection
+                .slot()
+                .and_then(|slot| Instant::now().checked_add(slot));
+
+            recent.push_front((
+                selected_entry.to_string(),
+                selection.episode_id(),
+                selection.movie_id(),
+            ));
+            recent.truncate(window.shows.max(window.episodes));
 
             // sleep for a moment after playing a new show to let Physics resolve
             sleep(Duration::from_secs(3)).await;
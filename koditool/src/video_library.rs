@@ -0,0 +1,293 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::Limits;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sort {
+    pub method: String,
+    pub order: SortOrder,
+    #[serde(default)]
+    pub ignorearticle: bool,
+}
+
+impl Sort {
+    pub fn ascending(method: &str) -> Self {
+        Sort {
+            method: method.to_string(),
+            order: SortOrder::Ascending,
+            ignorearticle: true,
+        }
+    }
+
+    pub fn descending(method: &str) -> Self {
+        Sort {
+            method: method.to_string(),
+            order: SortOrder::Descending,
+            ignorearticle: true,
+        }
+    }
+}
+
+pub(crate) fn default_limits() -> Limits {
+    Limits {
+        start: 0,
+        end: 1000,
+    }
+}
+
+pub(crate) fn properties(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+// A VideoLibrary filter: one field rule, or rules combined with and/or
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Filter {
+    Rule {
+        field: String,
+        operator: String,
+        value: String,
+    },
+    And {
+        and: Vec<Filter>,
+    },
+    Or {
+        or: Vec<Filter>,
+    },
+}
+
+impl Filter {
+    pub fn is(field: &str, value: &str) -> Self {
+        Filter::Rule {
+            field: field.to_string(),
+            operator: "is".to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+// ---- request params ----
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetTVShowsParams {
+    pub properties: Vec<String>,
+    pub limits: Limits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Sort>,
+}
+
+impl Default for GetTVShowsParams {
+    fn default() -> Self {
+        GetTVShowsParams {
+            properties: properties(&["title"]),
+            limits: default_limits(),
+            sort: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetEpisodesParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tvshowid: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub season: Option<i32>,
+    pub properties: Vec<String>,
+    pub limits: Limits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Sort>,
+}
+
+impl GetEpisodesParams {
+    pub fn for_show(tvshowid: u64) -> Self {
+        GetEpisodesParams {
+            tvshowid: Some(tvshowid),
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for GetEpisodesParams {
+    fn default() -> Self {
+        GetEpisodesParams {
+            tvshowid: None,
+            season: None,
+            properties: properties(&["title", "season", "episode"]),
+            limits: default_limits(),
+            sort: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetEpisodeDetailsParams {
+    pub episodeid: u64,
+    pub properties: Vec<String>,
+}
+
+impl GetEpisodeDetailsParams {
+    pub fn new(episodeid: u64) -> Self {
+        GetEpisodeDetailsParams {
+            episodeid,
+            properties: properties(&["file"]),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetSeasonsParams {
+    pub tvshowid: u64,
+    pub properties: Vec<String>,
+    pub limits: Limits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Sort>,
+}
+
+impl GetSeasonsParams {
+    pub fn for_show(tvshowid: u64) -> Self {
+        GetSeasonsParams {
+            tvshowid,
+            properties: properties(&["season", "episode", "watchedepisodes"]),
+            limits: default_limits(),
+            sort: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetMoviesParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    pub properties: Vec<String>,
+    pub limits: Limits,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<Sort>,
+}
+
+impl Default for GetMoviesParams {
+    fn default() -> Self {
+        GetMoviesParams {
+            filter: None,
+            properties: properties(&["title", "year"]),
+            limits: default_limits(),
+            sort: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GetMovieDetailsParams {
+    pub movieid: u64,
+    pub properties: Vec<String>,
+}
+
+impl GetMovieDetailsParams {
+    pub fn new(movieid: u64) -> Self {
+        GetMovieDetailsParams {
+            movieid,
+            properties: properties(&["title", "file"]),
+        }
+    }
+}
+
+// ---- response models ----
+// Kodi only returns the properties that were requested, so every field
+// falls back to its default rather than failing the whole list.
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Resume {
+    pub position: f64,
+    pub total: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TVShow {
+    pub tvshowid: u64,
+    pub label: String,
+    pub title: String,
+    pub originaltitle: String,
+    pub sorttitle: String,
+    pub year: u32,
+    pub rating: f64,
+    pub playcount: u64,
+    pub lastplayed: String,
+    pub episode: u64,
+    pub watchedepisodes: u64,
+    pub genre: Vec<String>,
+    pub art: HashMap<String, String>,
+    pub uniqueid: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Episode {
+    pub episodeid: u64,
+    pub tvshowid: u64,
+    pub label: String,
+    pub title: String,
+    pub showtitle: String,
+    pub season: i32,
+    pub episode: i32,
+    pub runtime: u64,
+    pub playcount: u64,
+    pub lastplayed: String,
+    pub firstaired: String,
+    pub rating: f64,
+    pub file: String,
+    pub resume: Resume,
+    pub art: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Season {
+    pub seasonid: u64,
+    pub tvshowid: u64,
+    pub label: String,
+    pub season: i32,
+    pub showtitle: String,
+    pub episode: u64,
+    pub watchedepisodes: u64,
+    pub playcount: u64,
+    pub art: HashMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct Movie {
+    pub movieid: u64,
+    pub label: String,
+    pub title: String,
+    pub year: u32,
+    pub runtime: u64,
+    pub playcount: u64,
+    pub lastplayed: String,
+    pub rating: f64,
+    pub file: String,
+    pub genre: Vec<String>,
+    pub set: String,
+    pub setid: u64,
+    pub tag: Vec<String>,
+    pub resume: Resume,
+    pub art: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetEpisodeDetailsResult {
+    pub episodedetails: Episode,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GetMovieDetailsResult {
+    pub moviedetails: Movie,
+}
@@ -1,4 +1,12 @@
-use koditool::{Authorization, Config, RpcClient, SelectedEpisode};
+use koditool::{
+    Authorization, Channel, ChannelGroupId, ChannelType, GetBroadcastsParams, GetChannelsParams, Config, GetEpisodesParams, GetMovieDetailsParams, GetMoviesParams, find_channel, MappingEntry, MovieFilter, EpisodeExclusion, EpisodeFilter, SeasonRange, SelectOptions, ShowEntry, ShowPin, TVShow, find_show, normalize_title, Selection, SelectionStrategy, UserMapping,
+    GetSeasonsParams, GetTVShowsParams, GoTo, KodiError, KodiEvent, LibraryCacheConfig, MediaType, PlayerTime, RepeatMode,
+    RpcClient, SeekTarget, SelectedEpisode, Sort, Subscription, Toggle, VolumeChange,
+};
+
+use futures_util::SinkExt;
+use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
 
 use mockito::{mock, server_url};
 use rand::prelude::IndexedMutRandom;
@@ -16,6 +24,8 @@ mod tests {
             url: server_url(),
             username: "test_user".to_string(),
             password: "test_pass".to_string(),
+            websocket_url: None,
+            library_cache: None,
         }
     }
 
@@ -61,9 +71,53 @@ mod tests {
         let client = test_client();
         let params = json!({"jsonrpc": "2.0", "method": "invalid_method", "id": 1});
 
-        let result = client.rpc_call(&params).await.unwrap();
-        assert_eq!(result["error"]["code"], json!(-32601));
-        assert_eq!(result["error"]["message"], json!("Method not found"));
+        let result = client.rpc_call(&params).await;
+        match result {
+            Err(KodiError::Rpc { code, message, .. }) => {
+                assert_eq!(code, -32601);
+                assert_eq!(message, "Method not found");
+            }
+            other => panic!("expected JSON-RPC error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rpc_error_surfaces_from_library_call() {
+        let _mock = mock("POST", "/jsonrpc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "error": {"code": -32602, "message": "Invalid params", "data": {"method": "AudioLibrary.GetArtists"}}}"#)
+            .create();
+
+        let client = test_client();
+        let err = client.get_artists().await.unwrap_err();
+        assert_eq!(err.kind(), "rpc");
+        if let KodiError::Rpc { code, data, .. } = err {
+            assert_eq!(code, -32602);
+            assert_eq!(data.unwrap()["method"], json!("AudioLibrary.GetArtists"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized() {
+        let _mock = mock("POST", "/jsonrpc").with_status(401).create();
+
+        let client = test_client();
+        let result = client.is_active().await;
+        assert!(matches!(result, Err(KodiError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_result_field() {
+        let _mock = mock("POST", "/jsonrpc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1}"#)
+            .create();
+
+        let client = test_client();
+        let result = client.get_songs(None).await;
+        assert!(matches!(result, Err(KodiError::MissingField(_))));
     }
 
     #[tokio::test]
@@ -173,23 +227,284 @@ mod tests {
         let episode = SelectedEpisode {
             _episode_id: 101,
             episode_file_path: "/path/to/test_episode.mp4".to_string(),
+            resume: false,
         };
 
         let result = client.rpc_play(&episode).await;
         assert!(result.is_ok());
     }
 
+    fn mock_active_players(players: serde_json::Value) -> mockito::Mock {
+        mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "Player.GetActivePlayers"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "jsonrpc": "2.0", "id": 1, "result": players }).to_string())
+            .create()
+    }
+
     #[tokio::test]
     async fn test_stop_playback() {
-        let _mock = mock("POST", "/jsonrpc")
+        let _players = mock_active_players(json!([{"playerid": 1, "type": "video"}]));
+        let stop_mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Stop",
+                "params": { "playerid": 1 }
+            })))
             .with_status(200)
             .with_header("content-type", "application/json")
             .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
             .create();
 
         let client = test_client();
         let result = client.rpc_stop().await;
         assert!(result.is_ok());
+        stop_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_stop_when_idle_is_noop() {
+        let _players = mock_active_players(json!([]));
+        let stop_mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "Player.Stop"})))
+            .expect(0)
+            .create();
+
+        let client = test_client();
+        assert!(client.rpc_stop().await.is_ok());
+        stop_mock.assert();
+    }
+
+    #[tokio::test]
+    async fn test_player_controls_use_discovered_player() {
+        // Only the music player is running, so commands must target playerid 0
+        let _players = mock_active_players(json!([{"playerid": 0, "type": "audio"}]));
+        let play_pause = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.PlayPause",
+                "params": { "playerid": 0, "play": "toggle" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"speed": 0}}"#)
+            .create();
+        let seek = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Seek",
+                "params": { "playerid": 0, "value": { "seconds": -30 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "percentage": 12.5,
+                    "time": { "hours": 0, "minutes": 1, "seconds": 30, "milliseconds": 0 },
+                    "totaltime": { "hours": 0, "minutes": 12, "seconds": 0, "milliseconds": 0 }
+                }
+            }).to_string())
+            .create();
+        let go_to = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.GoTo",
+                "params": { "playerid": 0, "to": "next" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .create();
+        let repeat = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.SetRepeat",
+                "params": { "playerid": 0, "repeat": "all" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .create();
+
+        let client = test_client();
+        assert_eq!(client.play_pause(Toggle::Toggle).await.unwrap(), 0);
+        let position = client.seek(SeekTarget::Relative(-30)).await.unwrap();
+        assert_eq!(position.time.as_seconds(), 90);
+        assert_eq!(position.totaltime, PlayerTime::from_seconds(720));
+        client.go_to(GoTo::Next).await.unwrap();
+        client.set_repeat(RepeatMode::All).await.unwrap();
+
+        play_pause.assert();
+        seek.assert();
+        go_to.assert();
+        repeat.assert();
+    }
+
+    #[tokio::test]
+    async fn test_get_player_properties() {
+        let _players = mock_active_players(json!([
+            {"playerid": 2, "type": "picture"},
+            {"playerid": 1, "type": "video"}
+        ]));
+        let _properties = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.GetProperties",
+                "params": { "playerid": 1 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "percentage": 50.0,
+                    "speed": 1,
+                    "time": { "hours": 0, "minutes": 11, "seconds": 0, "milliseconds": 0 },
+                    "totaltime": { "hours": 0, "minutes": 22, "seconds": 0, "milliseconds": 0 }
+                }
+            }).to_string())
+            .create();
+
+        let client = test_client();
+        let properties = client.get_player_properties().await.unwrap();
+        assert_eq!(properties.speed, 1);
+        assert_eq!(properties.time.as_seconds(), 660);
+        assert_eq!(properties.totaltime.as_seconds(), 1320);
+    }
+
+    #[tokio::test]
+    async fn test_now_playing_episode() {
+        let _players = mock_active_players(json!([{"playerid": 1, "type": "video"}]));
+        // GetActivePlayers takes id 1, so the batch is ids 2 and 3
+        let _batch = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!([
+                { "method": "Player.GetItem", "params": { "playerid": 1 } },
+                { "method": "Player.GetProperties", "params": { "playerid": 1 } }
+            ])))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!([
+                { "jsonrpc": "2.0", "id": 3, "result": {
+                    "percentage": 25.0,
+                    "speed": 0,
+                    "time": { "hours": 0, "minutes": 5, "seconds": 30, "milliseconds": 0 },
+                    "totaltime": { "hours": 0, "minutes": 22, "seconds": 0, "milliseconds": 0 }
+                } },
+                { "jsonrpc": "2.0", "id": 2, "result": { "item": {
+                    "type": "episode",
+                    "id": 42,
+                    "label": "1x03. Keepy Uppy",
+                    "title": "Keepy Uppy",
+                    "showtitle": "Bluey",
+                    "season": 1,
+                    "episode": 3,
+                    "artist": [],
+                    "file": "/media/bluey/s01e03.mkv",
+                    "art": { "thumb": "image://thumb.jpg/", "tvshow.poster": "image://poster.jpg/" }
+                } } }
+            ]).to_string())
+            .create();
+
+        let client = test_client();
+        let now_playing = client.now_playing().await.unwrap().unwrap();
+        assert_eq!(now_playing.media_type, MediaType::Episode);
+        assert_eq!(now_playing.title, "Keepy Uppy");
+        assert_eq!(now_playing.show_title.as_deref(), Some("Bluey"));
+        assert_eq!(now_playing.season, Some(1));
+        assert_eq!(now_playing.episode, Some(3));
+        assert_eq!(now_playing.artist, None);
+        assert_eq!(now_playing.file, "/media/bluey/s01e03.mkv");
+        assert_eq!(now_playing.elapsed.as_seconds(), 330);
+        assert_eq!(now_playing.total.as_seconds(), 1320);
+        assert!(now_playing.paused);
+        assert_eq!(now_playing.thumbnail.as_deref(), Some("image://thumb.jpg/"));
+    }
+
+    #[tokio::test]
+    async fn test_volume_and_mute() {
+        let _properties = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Application.GetProperties",
+                "params": { "properties": ["volume", "muted"] }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"volume": 40, "muted": false}}"#)
+            .create();
+        let set_absolute = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Application.SetVolume",
+                "params": { "volume": 100 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": 100}"#)
+            .expect(1)
+            .create();
+        let increment = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Application.SetVolume",
+                "params": { "volume": "increment" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": 42}"#)
+            .expect(1)
+            .create();
+        let toggle_mute = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Application.SetMute",
+                "params": { "mute": "toggle" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": true}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let properties = client.get_application_properties().await.unwrap();
+        assert_eq!(properties.volume, 40);
+        assert!(!properties.muted);
+
+        // Out-of-range levels are clamped before they reach Kodi
+        assert_eq!(client.set_volume(VolumeChange::Set(150)).await.unwrap(), 100);
+        assert_eq!(client.set_volume(VolumeChange::Increment).await.unwrap(), 42);
+        assert!(client.set_mute(Toggle::Toggle).await.unwrap());
+
+        set_absolute.assert();
+        increment.assert();
+        toggle_mute.assert();
+    }
+
+    #[tokio::test]
+    async fn test_suspend() {
+        let suspend = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "System.Suspend"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        assert!(client.suspend().await.is_ok());
+        suspend.assert();
+    }
+
+    #[tokio::test]
+    async fn test_now_playing_idle() {
+        let _players = mock_active_players(json!([]));
+        let client = test_client();
+        assert!(client.now_playing().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_player_control_without_active_player() {
+        let _players = mock_active_players(json!([]));
+
+        let client = test_client();
+        let result = client.set_speed(2).await;
+        assert!(matches!(result, Err(KodiError::NotFound(_))));
     }
 
     #[tokio::test]
@@ -311,7 +626,8 @@ mod tests {
         let params = json!({"jsonrpc": "2.0", "method": "test", "id": 1});
 
         let result = client.rpc_call(&params).await;
-        assert!(result.is_err());
+        assert!(matches!(result, Err(KodiError::HttpStatus(500))));
+        assert!(result.unwrap_err().is_transient());
     }
 
     #[tokio::test]
@@ -397,17 +713,1150 @@ mod tests {
 
 
     #[tokio::test]
-    async fn test_invalid_json_response() {
+    async fn test_get_tv_shows_with_sort_and_properties() {
         let _mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetTVShows",
+                "params": {
+                    "properties": ["title", "playcount", "art"],
+                    "sort": { "method": "lastplayed", "order": "descending" }
+                }
+            })))
             .with_status(200)
             .with_header("content-type", "application/json")
-            .with_body("This is not valid JSON")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "tvshows": [
+                        {
+                            "tvshowid": 7,
+                            "label": "Bluey",
+                            "title": "Bluey",
+                            "playcount": 3,
+                            "art": { "poster": "image://poster.jpg/" }
+                        }
+                    ],
+                    "limits": { "end": 1, "start": 0, "total": 1 }
+                }
+            }).to_string())
             .create();
 
         let client = test_client();
-        let params = json!({"jsonrpc": "2.0", "method": "test", "id": 1});
+        let params = GetTVShowsParams {
+            properties: vec!["title".into(), "playcount".into(), "art".into()],
+            sort: Some(Sort::descending("lastplayed")),
+            ..Default::default()
+        };
+        let shows = client.get_tv_shows(&params).await.unwrap();
+        assert_eq!(shows.len(), 1);
+        assert_eq!(shows[0].tvshowid, 7);
+        assert_eq!(shows[0].playcount, 3);
+        assert_eq!(shows[0].art["poster"], "image://poster.jpg/");
+    }
 
-        let result = client.rpc_call(&params).await;
-        assert!(result.is_err());
+    #[tokio::test]
+    async fn test_get_episodes_tolerates_odd_entries() {
+        // Kodi omits properties it has no value for; one entry here has no episodeid at all
+        let _mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetEpisodes",
+                "params": { "tvshowid": 1 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "episodes": [
+                        { "episodeid": 101, "title": "Pilot", "season": 1, "episode": 1, "runtime": 1320 },
+                        { "label": "Broken entry" }
+                    ],
+                    "limits": { "end": 2, "start": 0, "total": 2 }
+                }
+            }).to_string())
+            .create();
+
+        let client = test_client();
+        let episodes = client
+            .get_episodes(&GetEpisodesParams::for_show(1))
+            .await
+            .unwrap();
+        assert_eq!(episodes.len(), 2);
+        assert_eq!(episodes[0].runtime, 1320);
+        assert_eq!(episodes[1].episodeid, 0);
+    }
+
+    #[tokio::test]
+    async fn test_get_seasons() {
+        let _mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetSeasons",
+                "params": { "tvshowid": 3 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "seasons": [
+                        { "seasonid": 30, "label": "Specials", "season": 0, "episode": 2 },
+                        { "seasonid": 31, "label": "Season 1", "season": 1, "episode": 13 }
+                    ],
+                    "limits": { "end": 2, "start": 0, "total": 2 }
+                }
+            }).to_string())
+            .create();
+
+        let client = test_client();
+        let seasons = client.get_seasons(&GetSeasonsParams::for_show(3)).await.unwrap();
+        assert_eq!(seasons.len(), 2);
+        assert_eq!(seasons[0].season, 0);
+        assert_eq!(seasons[1].episode, 13);
+    }
+
+    #[tokio::test]
+    async fn test_get_movies_and_details() {
+        let _movies_mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetMovies"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "movies": [
+                        { "movieid": 5, "label": "Spirited Away", "title": "Spirited Away", "year": 2001 }
+                    ],
+                    "limits": { "end": 1, "start": 0, "total": 1 }
+                }
+            }).to_string())
+            .create();
+
+        let _details_mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetMovieDetails",
+                "params": { "movieid": 5 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "moviedetails": {
+                        "movieid": 5,
+                        "label": "Spirited Away",
+                        "file": "/media/movies/Spirited Away.mkv",
+                        "runtime": 7500
+                    }
+                }
+            }).to_string())
+            .create();
+
+        let client = test_client();
+        let movies = client.get_movies(&GetMoviesParams::default()).await.unwrap();
+        assert_eq!(movies.len(), 1);
+        assert_eq!(movies[0].year, 2001);
+
+        let mut params = GetMovieDetailsParams::new(movies[0].movieid);
+        params.properties.push("runtime".into());
+        let details = client.get_movie_details(&params).await.unwrap();
+        assert_eq!(details.file, "/media/movies/Spirited Away.mkv");
+        assert_eq!(details.runtime, 7500);
+    }
+
+    #[tokio::test]
+    async fn test_list_calls_follow_pagination() {
+        let first_page = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetTVShows",
+                "params": { "limits": { "start": 0, "end": 2 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "tvshows": [
+                        { "tvshowid": 1, "title": "Bluey" },
+                        { "tvshowid": 2, "title": "Futurama" }
+                    ],
+                    "limits": { "end": 2, "start": 0, "total": 3 }
+                }
+            }).to_string())
+            .expect(1)
+            .create();
+
+        let second_page = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetTVShows",
+                "params": { "limits": { "start": 2, "end": 4 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "tvshows": [
+                        { "tvshowid": 3, "title": "The Simpsons" }
+                    ],
+                    "limits": { "end": 3, "start": 2, "total": 3 }
+                }
+            }).to_string())
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let mut params = GetTVShowsParams::default();
+        params.limits.end = 2;
+        let shows = client.get_tv_shows(&params).await.unwrap();
+
+        let ids: Vec<u64> = shows.iter().map(|show| show.tvshowid).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        first_page.assert();
+        second_page.assert();
+    }
+
+    #[tokio::test]
+    async fn test_empty_library_returns_empty_list() {
+        // Kodi leaves out the list key entirely when there is nothing to return
+        let _mock = mock("POST", "/jsonrpc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"limits": {"start": 0, "end": 0, "total": 0}}}"#)
+            .create();
+
+        let client = test_client();
+        let movies = client.get_movies(&GetMoviesParams::default()).await.unwrap();
+        assert!(movies.is_empty());
+    }
+
+    async fn next_event(subscription: &mut Subscription) -> KodiEvent {
+        tokio::time::timeout(Duration::from_secs(5), subscription.recv())
+            .await
+            .expect("timed out waiting for event")
+            .expect("subscription closed")
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_receives_notifications_and_reconnects() {
+        // Fake Kodi WebSocket: push two notifications, hang up, then accept the reconnect
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}/jsonrpc", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let notifications = [
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "Player.OnPlay",
+                    "params": { "sender": "xbmc", "data": { "item": { "id": 101, "type": "episode" } } }
+                }),
+                // Replies to requests carry an id and must not surface as events
+                json!({ "jsonrpc": "2.0", "id": 1, "result": "OK" }),
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "Player.OnStop",
+                    "params": { "sender": "xbmc", "data": { "end": true, "item": { "id": 101, "type": "episode" } } }
+                }),
+            ];
+            for notification in notifications {
+                socket.send(Message::Text(notification.to_string())).await.unwrap();
+            }
+            socket.close(None).await.unwrap();
+
+            let (stream, _) = listener.accept().await.unwrap();
+            let _socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let mut config = test_config();
+        config.websocket_url = Some(ws_url);
+        let client = RpcClient::new(config).unwrap();
+        let mut subscription = client.subscribe().unwrap();
+
+        assert_eq!(next_event(&mut subscription).await, KodiEvent::Connected);
+        assert_eq!(
+            next_event(&mut subscription).await,
+            KodiEvent::PlayerOnPlay { item: json!({ "id": 101, "type": "episode" }) }
+        );
+        assert_eq!(
+            next_event(&mut subscription).await,
+            KodiEvent::PlayerOnStop { item: json!({ "id": 101, "type": "episode" }), ended: true }
+        );
+        assert_eq!(next_event(&mut subscription).await, KodiEvent::Disconnected);
+        assert_eq!(next_event(&mut subscription).await, KodiEvent::Connected);
+    }
+
+    #[tokio::test]
+    async fn test_notification_parsing() {
+        let update = KodiEvent::from_notification(&json!({
+            "jsonrpc": "2.0",
+            "method": "VideoLibrary.OnUpdate",
+            "params": { "sender": "xbmc", "data": { "item": { "id": 7, "type": "episode" }, "playcount": 1 } }
+        }));
+        assert!(matches!(update, Some(KodiEvent::VideoLibraryOnUpdate { .. })));
+
+        let quit = KodiEvent::from_notification(&json!({
+            "jsonrpc": "2.0",
+            "method": "System.OnQuit",
+            "params": { "sender": "xbmc", "data": { "exitcode": 0 } }
+        }));
+        assert_eq!(quit, Some(KodiEvent::SystemOnQuit));
+
+        assert_eq!(KodiEvent::from_notification(&json!({"not": "a notification"})), None);
+    }
+
+    #[tokio::test]
+    async fn test_batch_correlates_responses_by_id() {
+        // Replies come back out of order and the second call fails on its own
+        let _mock = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!([
+                { "method": "Player.GetActivePlayers" },
+                { "method": "Application.GetProperties" },
+                { "method": "VideoLibrary.GetTVShows" }
+            ])))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!([
+                { "jsonrpc": "2.0", "id": 3, "result": { "tvshows": [{ "tvshowid": 1, "title": "Bluey" }] } },
+                { "jsonrpc": "2.0", "id": 1, "result": [{ "playerid": 1, "type": "video" }] },
+                { "jsonrpc": "2.0", "id": 2, "error": { "code": -32602, "message": "Invalid params" } }
+            ]).to_string())
+            .create();
+
+        let client = test_client();
+        let mut batch = client.batch();
+        let players = batch.add::<_, Vec<serde_json::Value>>("Player.GetActivePlayers", &json!({}));
+        let volume = batch.add::<_, serde_json::Value>(
+            "Application.GetProperties",
+            &json!({ "properties": ["volume", "bogus"] }),
+        );
+        let shows = batch.add::<_, serde_json::Value>(
+            "VideoLibrary.GetTVShows",
+            &GetTVShowsParams::default(),
+        );
+        assert_eq!(batch.len(), 3);
+
+        let results = batch.send().await.unwrap();
+        assert_eq!(results.get(&players).unwrap()[0]["playerid"], json!(1));
+        assert!(matches!(
+            results.get(&volume),
+            Err(KodiError::Rpc { code: -32602, .. })
+        ));
+        assert_eq!(results.get(&shows).unwrap()["tvshows"][0]["title"], json!("Bluey"));
+    }
+
+    #[tokio::test]
+    async fn test_batch_rejected_as_a_whole() {
+        let _mock = mock("POST", "/jsonrpc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "Parse error."}}"#)
+            .create();
+
+        let client = test_client();
+        let mut batch = client.batch();
+        batch.add::<_, serde_json::Value>("JSONRPC.Ping", &json!({}));
+        let result = batch.send().await;
+        assert!(matches!(result, Err(KodiError::Rpc { code: -32700, .. })));
+    }
+
+    #[test]
+    fn test_find_channel() {
+        let channels = vec![
+            Channel {
+                channelid: 12,
+                label: "ABC Kids".to_string(),
+                channel: "ABC Kids".to_string(),
+                ..Default::default()
+            },
+            Channel {
+                channelid: 30,
+                label: "30 - ABC ME".to_string(),
+                channel: "ABC ME".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(find_channel(&channels, "abc kids", None).unwrap().channelid, 12);
+        // The channel name works as well as the label
+        assert_eq!(find_channel(&channels, "ABC ME", None).unwrap().channelid, 30);
+        // An id wins over the name
+        assert_eq!(find_channel(&channels, "ABC Kids", Some(30)).unwrap().channelid, 30);
+        assert!(matches!(
+            find_channel(&channels, "Nope", None),
+            Err(KodiError::NotFound(_))
+        ));
+        assert!(matches!(
+            find_channel(&channels, "ABC Kids", Some(99)),
+            Err(KodiError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_pvr_channels_and_guide() {
+        let _groups = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "PVR.GetChannelGroups",
+                "params": { "channeltype": "tv" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "channelgroups": [
+                        { "channelgroupid": 1, "label": "All channels", "channeltype": "tv" },
+                        { "channelgroupid": 5, "label": "Kids", "channeltype": "tv" }
+                    ],
+                    "limits": { "start": 0, "end": 2, "total": 2 }
+                }
+            }).to_string())
+            .create();
+        let _channels = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "PVR.GetChannels",
+                "params": { "channelgroupid": "alltv" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "channels": [
+                        { "channelid": 12, "label": "ABC Kids", "channel": "ABC Kids", "channeltype": "tv", "channelnumber": 22 }
+                    ],
+                    "limits": { "start": 0, "end": 1, "total": 1 }
+                }
+            }).to_string())
+            .create();
+        let _broadcasts = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "PVR.GetBroadcasts",
+                "params": { "channelid": 12 }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "broadcasts": [
+                        { "broadcastid": 900, "label": "Bluey", "title": "Bluey", "starttime": "2026-10-17 07:00:00", "endtime": "2026-10-17 07:07:00", "runtime": 7, "isactive": true },
+                        { "broadcastid": 901, "label": "Peppa Pig", "title": "Peppa Pig", "starttime": "2026-10-17 07:07:00", "endtime": "2026-10-17 07:12:00", "runtime": 5 }
+                    ],
+                    "limits": { "start": 0, "end": 2, "total": 2 }
+                }
+            }).to_string())
+            .create();
+        let play = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Open",
+                "params": { "item": { "channelid": 12 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let groups = client.get_channel_groups(ChannelType::Tv).await.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].label, "Kids");
+
+        let channels = client
+            .get_channels(&GetChannelsParams::for_group(ChannelGroupId::all(ChannelType::Tv)))
+            .await
+            .unwrap();
+        assert_eq!(channels[0].channelid, 12);
+        assert_eq!(channels[0].channelnumber, 22);
+
+        let guide = client
+            .get_broadcasts(&GetBroadcastsParams::for_channel(12))
+            .await
+            .unwrap();
+        assert_eq!(guide.len(), 2);
+        assert!(guide[0].isactive);
+        assert!(!guide[1].isactive);
+
+        client.play_channel(12).await.unwrap();
+        play.assert();
+    }
+
+    #[test]
+    fn test_mapping_entries_parse_shows_and_movies() {
+        let yaml = "- Bluey\n- movies:\n    genre: Animation\n    set: Studio Ghibli\n";
+        let entries: Vec<MappingEntry> = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(entries[0], MappingEntry::Show("Bluey".to_string()));
+        let MappingEntry::Movies { movies, .. } = &entries[1] else {
+            panic!("expected a movie entry, got {:?}", entries[1]);
+        };
+        assert_eq!(movies.genre.as_deref(), Some("Animation"));
+        assert_eq!(movies.set.as_deref(), Some("Studio Ghibli"));
+        assert_eq!(entries[1].to_string(), "Movies (genre: Animation, set: Studio Ghibli)");
+    }
+
+    #[tokio::test]
+    async fn test_select_and_play_movie_entry() {
+        let _movies = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetMovies",
+                "params": { "filter": { "and": [
+                    { "field": "genre", "operator": "is", "value": "Animation" },
+                    { "field": "tag", "operator": "is", "value": "family" }
+                ] } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "movies": [
+                        { "movieid": 0, "title": "Broken entry", "file": "" },
+                        { "movieid": 7, "title": "My Neighbor Totoro", "year": 1988, "file": "/movies/totoro.mkv" }
+                    ],
+                    "limits": { "start": 0, "end": 2, "total": 2 }
+                }
+            }).to_string())
+            .create();
+        let play = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Open",
+                "params": { "item": { "movieid": 7 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let entry = MappingEntry::Movies {
+            movies: MovieFilter {
+                genre: Some("Animation".to_string()),
+                tag: Some("family".to_string()),
+                ..Default::default()
+            },
+            strategy: None,
+            weight: None,
+        };
+        let selection = client
+            .select_for_entry(&entry, &SelectOptions::default())
+            .await
+            .unwrap();
+        // Entries without an id or file are never picked
+        let Selection::Movie { movie, .. } = &selection else {
+            panic!("expected a movie, got {:?}", selection);
+        };
+        assert_eq!(movie.movieid, 7);
+        assert_eq!(selection.to_string(), "My Neighbor Totoro (1988)");
+
+        client.play_selection(&selection).await.unwrap();
+        play.assert();
+    }
+
+    // The keys an entry serializes with every field set
+    fn yaml_keys(value: &impl serde::Serialize) -> Vec<String> {
+        let serde_yaml::Value::Mapping(fields) = serde_yaml::to_value(value).unwrap() else {
+            panic!("expected a mapping");
+        };
+        let mut keys: Vec<String> = fields
+            .iter()
+            .map(|(key, _)| key.as_str().unwrap().to_string())
+            .collect();
+        keys.sort();
+        keys
+    }
+
+    fn sorted(keys: &[&str]) -> Vec<String> {
+        let mut keys: Vec<String> = keys.iter().map(|key| key.to_string()).collect();
+        keys.sort();
+        keys
+    }
+
+    // The key lists config checks use must cover every field. The literals
+    // below name each field, so a new one won't compile until it's added
+    // here, and then this fails until it's in the list too.
+    #[test]
+    fn test_mapping_entry_keys_match_fields() {
+        let show = MappingEntry::Detailed(ShowEntry {
+            show: "Bluey".to_string(),
+            strategy: Some(SelectionStrategy::Random),
+            weight: Some(2),
+            pin: ShowPin {
+                tvshowid: Some(1),
+                uniqueid: [("tvdb".to_string(), "1".to_string())].into_iter().collect(),
+            },
+            filter: EpisodeFilter {
+                seasons: Some("1-2".parse().unwrap()),
+                exclude_specials: true,
+                exclude: vec![EpisodeExclusion::Id(1)],
+                min_runtime: Some(5),
+                max_runtime: Some(10),
+            },
+        });
+        assert_eq!(yaml_keys(&show), sorted(&MappingEntry::SHOW_KEYS));
+
+        let movie_filter = MovieFilter {
+            title: Some("Totoro".to_string()),
+            genre: Some("Animation".to_string()),
+            set: Some("Studio Ghibli".to_string()),
+            tag: Some("family".to_string()),
+        };
+        assert_eq!(yaml_keys(&movie_filter), sorted(&MovieFilter::KEYS));
+
+        let movies = MappingEntry::Movies {
+            movies: movie_filter,
+            strategy: Some(SelectionStrategy::Random),
+            weight: Some(2),
+        };
+        assert_eq!(yaml_keys(&movies), sorted(&MappingEntry::MOVIES_KEYS));
+
+        let channel = MappingEntry::Channel {
+            channel: "ABC Kids".to_string(),
+            channelid: Some(12),
+            minutes: Some(60.0),
+            weight: Some(2),
+        };
+        assert_eq!(yaml_keys(&channel), sorted(&MappingEntry::CHANNEL_KEYS));
+    }
+
+    #[test]
+    fn test_mapping_entries_parse_channels() {
+        let yaml = "- channel: ABC Kids\n- channel: Kids TV\n  channelid: 12\n  minutes: 45\n  weight: 2\n";
+        let entries: Vec<MappingEntry> = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            entries[1],
+            MappingEntry::Channel {
+                channel: "Kids TV".to_string(),
+                channelid: Some(12),
+                minutes: Some(45.0),
+                weight: Some(2),
+            }
+        );
+        assert_eq!(entries[0].to_string(), "Channel (ABC Kids)");
+        assert_eq!(entries[1].weight(), 2);
+        assert_eq!(entries[1].strategy(), None);
+
+        // Written back the way it was read
+        let round_trip: Vec<MappingEntry> =
+            serde_yaml::from_str(&serde_yaml::to_string(&entries).unwrap()).unwrap();
+        assert_eq!(round_trip, entries);
+    }
+
+    #[tokio::test]
+    async fn test_select_and_play_channel_entry() {
+        let _channels = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "PVR.GetChannels",
+                "params": { "channelgroupid": "alltv" }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "channels": [
+                        { "channelid": 12, "label": "ABC Kids", "channel": "ABC Kids", "channeltype": "tv", "channelnumber": 22 }
+                    ],
+                    "limits": { "start": 0, "end": 1, "total": 1 }
+                }
+            }).to_string())
+            .create();
+        let play = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Open",
+                "params": { "item": { "channelid": 12 } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let entry = MappingEntry::Channel {
+            channel: "abc kids".to_string(),
+            channelid: None,
+            minutes: None,
+            weight: None,
+        };
+        let selection = client
+            .select_for_entry(&entry, &SelectOptions::default())
+            .await
+            .unwrap();
+        let Selection::Channel { channel, .. } = &selection else {
+            panic!("expected a channel, got {:?}", selection);
+        };
+        assert_eq!(channel.channelid, 12);
+        assert_eq!(selection.to_string(), "ABC Kids (live)");
+        assert_eq!(selection.episode_id(), None);
+        assert_eq!(selection.movie_id(), None);
+        // A channel never ends, so it gets the default slot
+        assert_eq!(selection.slot(), Some(Duration::from_secs(30 * 60)));
+
+        client.play_selection(&selection).await.unwrap();
+        play.assert();
+
+        let entry = MappingEntry::Channel {
+            channel: "ABC Kids".to_string(),
+            channelid: Some(12),
+            minutes: Some(1.5),
+            weight: None,
+        };
+        let selection = client
+            .select_for_entry(&entry, &SelectOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(selection.slot(), Some(Duration::from_secs(90)));
+    }
+
+    #[tokio::test]
+    async fn test_select_movie_no_matches() {
+        let _movies = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetMovies"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"limits": {"start": 0, "end": 0, "total": 0}}}"#)
+            .create();
+
+        let client = test_client();
+        let filter = MovieFilter {
+            set: Some("Nope".to_string()),
+            ..Default::default()
+        };
+        let result = client.select_random_movie(&filter).await;
+        assert!(matches!(result, Err(KodiError::NotFound(ref msg)) if msg == "No movies match Movies (set: Nope)"));
+    }
+
+    #[test]
+    fn test_strategy_config_per_user_and_per_show() {
+        let yaml = "strategy: next_unwatched\nshows:\n  - Bluey\n  - show: Doctor Who\n    strategy: random\n";
+        let mapping: UserMapping = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(mapping.strategy, Some(SelectionStrategy::NextUnwatched));
+        assert_eq!(mapping.shows[0].strategy(), None);
+        assert_eq!(mapping.shows[1].strategy(), Some(SelectionStrategy::Random));
+        assert_eq!(mapping.shows[1].to_string(), "Doctor Who");
+
+        // The plain list form still works and writes back unchanged
+        let mapping: UserMapping = serde_yaml::from_str("- Bluey\n").unwrap();
+        assert_eq!(mapping.strategy, None);
+        assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "---\n- Bluey\n");
+
+        assert_eq!(
+            "resume-in-progress".parse::<SelectionStrategy>().unwrap(),
+            SelectionStrategy::ResumeInProgress
+        );
+        assert!("shuffle".parse::<SelectionStrategy>().is_err());
+    }
+
+    // GetTVShows/GetEpisodes/GetEpisodeDetails for one show; episodes are
+    // listed out of order to check strategies sort them first
+    fn mock_show_with_watch_state() -> Vec<mockito::Mock> {
+        let shows = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetTVShows"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "tvshows": [{ "tvshowid": 1, "title": "Bluey" }],
+                    "limits": { "start": 0, "end": 1, "total": 1 }
+                }
+            }).to_string())
+            .create();
+        let episodes = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetEpisodes"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "episodes": [
+                        { "episodeid": 13, "season": 1, "episode": 3, "playcount": 0, "lastplayed": "" },
+                        { "episodeid": 90, "season": 0, "episode": 1, "playcount": 0, "lastplayed": "" },
+                        { "episodeid": 11, "season": 1, "episode": 1, "playcount": 2, "lastplayed": "2026-01-02 19:00:00" },
+                        { "episodeid": 12, "season": 1, "episode": 2, "playcount": 0, "lastplayed": "2026-01-03 19:00:00",
+                          "resume": { "position": 300.0, "total": 420.0 } }
+                    ],
+                    "limits": { "start": 0, "end": 4, "total": 4 }
+                }
+            }).to_string())
+            .create();
+        let details = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetEpisodeDetails"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"episodedetails": {"file": "/bluey/episode.mkv"}}}"#)
+            .create();
+        vec![shows, episodes, details]
+    }
+
+    #[tokio::test]
+    async fn test_episode_strategies() {
+        let _mocks = mock_show_with_watch_state();
+        let client = test_client();
+
+        // 1x02 is the first unwatched in playback order; it has a resume point
+        let episode = client
+            .select_episode_by_title("Bluey", SelectionStrategy::NextUnwatched)
+            .await
+            .unwrap();
+        assert_eq!(episode._episode_id, 12);
+        assert!(episode.resume);
+
+        let episode = client
+            .select_episode_by_title("Bluey", SelectionStrategy::ResumeInProgress)
+            .await
+            .unwrap();
+        assert_eq!(episode._episode_id, 12);
+
+        // Never-played episodes sort first, specials after the regular seasons
+        let episode = client
+            .select_episode_by_title("Bluey", SelectionStrategy::OldestLastPlayed)
+            .await
+            .unwrap();
+        assert_eq!(episode._episode_id, 13);
+        assert!(!episode.resume);
+    }
+
+    #[tokio::test]
+    async fn test_resumed_episode_opens_with_resume_option() {
+        let play = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Open",
+                "params": { "item": { "file": "/bluey/episode.mkv" }, "options": { "resume": true } }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": "OK"}"#)
+            .expect(1)
+            .create();
+
+        let client = test_client();
+        let episode = SelectedEpisode {
+            _episode_id: 12,
+            episode_file_path: "/bluey/episode.mkv".to_string(),
+            resume: true,
+        };
+        client.rpc_play(&episode).await.unwrap();
+        play.assert();
+    }
+
+    #[tokio::test]
+    async fn test_episode_filters_in_show_entry() {
+        let yaml = r#"
+- show: Bluey
+  seasons: 1-2
+  exclude_specials: true
+  exclude: [23, "the creek"]
+  min_runtime: 5
+  max_runtime: 10
+"#;
+        let entries: Vec<MappingEntry> = serde_yaml::from_str(yaml).unwrap();
+        let MappingEntry::Detailed(entry) = &entries[0] else {
+            panic!("expected a show entry, got {:?}", entries[0]);
+        };
+        assert_eq!(entry.filter.seasons.unwrap().to_string(), "1-2");
+        assert!(entry.filter.exclude_specials);
+        assert_eq!(
+            entry.filter.exclude,
+            vec![EpisodeExclusion::Id(23), EpisodeExclusion::Title("the creek".to_string())]
+        );
+        // Written back as it was given
+        let round_trip: Vec<MappingEntry> =
+            serde_yaml::from_str(&serde_yaml::to_string(&entries).unwrap()).unwrap();
+        assert_eq!(round_trip, entries);
+        assert!("3-1".parse::<SeasonRange>().is_err());
+        assert!("4-".parse::<SeasonRange>().unwrap().contains(9));
+
+        let _shows = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetTVShows"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"tvshows": [{"tvshowid": 1, "title": "Bluey"}], "limits": {"start": 0, "end": 1, "total": 1}}}"#)
+            .create();
+        let _episodes = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetEpisodes",
+                "params": { "properties": ["title", "season", "episode", "playcount", "lastplayed", "resume", "runtime"] }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "episodes": [
+                        { "episodeid": 90, "season": 0, "episode": 1, "title": "Special", "runtime": 420 },
+                        { "episodeid": 21, "season": 2, "episode": 1, "title": "The Creek", "runtime": 420 },
+                        { "episodeid": 22, "season": 2, "episode": 2, "title": "Long One", "runtime": 1500 },
+                        { "episodeid": 23, "season": 2, "episode": 3, "title": "Hallowe'en", "runtime": 420 },
+                        { "episodeid": 24, "season": 2, "episode": 4, "title": "Short One", "runtime": 120 },
+                        { "episodeid": 25, "season": 2, "episode": 5, "title": "Keepy Uppy", "runtime": 420 },
+                        { "episodeid": 31, "season": 3, "episode": 1, "title": "Later", "runtime": 420 }
+                    ],
+                    "limits": { "start": 0, "end": 7, "total": 7 }
+                }
+            }).to_string())
+            .create();
+        let _details = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetEpisodeDetails"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"episodedetails": {"file": "/bluey/keepy-uppy.mkv"}}}"#)
+            .create();
+
+        // Only 2x05 passes every filter, so even a random pick lands on it
+        let client = test_client();
+        for _ in 0..5 {
+            let selection = client
+                .select_for_entry(&entries[0], &SelectOptions::default())
+                .await
+                .unwrap();
+            assert_eq!(selection.episode_id(), Some(25));
+        }
+
+        let nothing: MappingEntry =
+            serde_yaml::from_str("{show: Bluey, seasons: 7}").unwrap();
+        let err = client
+            .select_for_entry(&nothing, &SelectOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, KodiError::NotFound(_)), "got {:?}", err);
+    }
+
+    fn library_show(id: u64, title: &str, originaltitle: &str, year: u32) -> TVShow {
+        TVShow {
+            tvshowid: id,
+            title: title.to_string(),
+            originaltitle: originaltitle.to_string(),
+            year,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_find_show_loose_matching() {
+        let mut shows = vec![
+            library_show(1, "The Simpsons", "", 1989),
+            library_show(2, "Doctor Who (2005)", "", 2005),
+            library_show(3, "Doctor Who (1963)", "", 1963),
+            library_show(4, "Pokémon", "Poketto Monsutā", 1997),
+            library_show(5, "La Casa de Papel", "Money Heist", 2017),
+        ];
+        shows[3].uniqueid.insert("tvdb".to_string(), "76703".to_string());
+        let no_pin = ShowPin::default();
+        let found = |title: &str| find_show(&shows, title, &no_pin).map(|show| show.tvshowid);
+
+        assert_eq!(normalize_title("Pokémon: Indigo League!"), "pokemon indigo league");
+        assert_eq!(found("the simpsons").unwrap(), 1);
+        assert_eq!(found("POKEMON").unwrap(), 4);
+        assert_eq!(found("money heist").unwrap(), 5);
+        assert_eq!(found("Doctor Who 2005").unwrap(), 2);
+
+        // Year stripped on both sides matches two shows
+        let err = found("Doctor Who").unwrap_err().to_string();
+        assert!(err.contains("matches several shows"), "{}", err);
+
+        let err = found("The Simpson").unwrap_err().to_string();
+        assert_eq!(err, "TV show The Simpson not found; did you mean: The Simpsons?");
+        let err = found("Bluey").unwrap_err().to_string();
+        assert_eq!(err, "TV show Bluey not found");
+
+        // A pin beats the title, even a wrong one
+        let pin = ShowPin { tvshowid: Some(3), ..Default::default() };
+        assert_eq!(find_show(&shows, "Doctor Who", &pin).unwrap().tvshowid, 3);
+        let pin: ShowPin = serde_yaml::from_str("uniqueid: {TVDB: 76703}").unwrap();
+        assert_eq!(find_show(&shows, "Pocket Monsters", &pin).unwrap().tvshowid, 4);
+        let pin = ShowPin { tvshowid: Some(99), ..Default::default() };
+        assert!(find_show(&shows, "The Simpsons", &pin).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_find_tv_show_requests_alternate_titles() {
+        let _shows = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "VideoLibrary.GetTVShows",
+                "params": { "properties": ["title", "originaltitle", "sorttitle", "year", "uniqueid"] }
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": {
+                    "tvshows": [
+                        { "tvshowid": 8, "title": "Doctor Who (2005)", "sorttitle": "Doctor Who",
+                          "uniqueid": { "imdb": "tt0436992", "tvdb": "78804" } }
+                    ],
+                    "limits": { "start": 0, "end": 1, "total": 1 }
+                }
+            }).to_string())
+            .create();
+
+        let entry: MappingEntry =
+            serde_yaml::from_str("{show: Doctor Who, uniqueid: {imdb: tt0436992}}").unwrap();
+        let MappingEntry::Detailed(entry) = entry else {
+            panic!("expected a show entry");
+        };
+        let client = test_client();
+        let show = client.find_tv_show(&entry.show, &entry.pin).await.unwrap();
+        assert_eq!(show.tvshowid, 8);
+        let show = client.find_tv_show("doctor who", &ShowPin::default()).await.unwrap();
+        assert_eq!(show.tvshowid, 8);
+    }
+
+    #[tokio::test]
+    async fn test_library_cache_ttl_invalidation_and_disk() {
+        let shows = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetTVShows"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"tvshows": [{"tvshowid": 1, "title": "Bluey"}], "limits": {"start": 0, "end": 1, "total": 1}}}"#)
+            .expect(1)
+            .create();
+        let episodes = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetEpisodes"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"episodes": [{"episodeid": 13, "season": 1, "episode": 3}], "limits": {"start": 0, "end": 1, "total": 1}}}"#)
+            .expect(2)
+            .create();
+        let _details = mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "VideoLibrary.GetEpisodeDetails"})))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(r#"{"jsonrpc": "2.0", "id": 1, "result": {"episodedetails": {"file": "/bluey/episode.mkv"}}}"#)
+            .create();
+
+        let path = std::env::temp_dir().join(format!("koditool-cache-{}.json", std::process::id()));
+        let cached_client = || {
+            let config = Config {
+                library_cache: Some(LibraryCacheConfig {
+                    ttl_seconds: 3600,
+                    path: Some(path.clone()),
+                }),
+                ..test_config()
+            };
+            RpcClient::new(config).unwrap().with_seed(Default::default())
+        };
+
+        // Second pick is served from the cache
+        let client = cached_client();
+        client.select_random_episode_by_title("Bluey").await.unwrap();
+        client.select_random_episode_by_title("Bluey").await.unwrap();
+
+        // Watching an episode drops its show's episode list
+        let update = KodiEvent::VideoLibraryOnUpdate {
+            item: json!({"item": {"id": 13, "type": "episode"}, "playcount": 1}),
+        };
+        assert!(client.invalidate_library_cache(&update));
+        assert!(!client.invalidate_library_cache(&KodiEvent::PlayerOnPlay { item: json!({}) }));
+        client.select_random_episode_by_title("Bluey").await.unwrap();
+
+        // A new client picks the cache back up from disk
+        let restarted = cached_client();
+        restarted.select_random_episode_by_title("Bluey").await.unwrap();
+        shows.assert();
+        episodes.assert();
+
+        assert!(restarted.invalidate_library_cache(&KodiEvent::VideoLibraryOnScanFinished));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_weighted_entries_skip_recent_shows() {
+        let yaml = r#"
+shows:
+  - show: Bluey
+    weight: 3
+  - show: Peppa Pig
+    weight: 0
+  - Bob's Burgers
+"#;
+        let mapping: UserMapping = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(mapping.shows[0].weight(), 3);
+        assert_eq!(mapping.shows[2].weight(), 1);
+
+        let mut rng = ChaCha12Rng::seed_from_u64(7);
+        for _ in 0..50 {
+            // Weight 0 is never picked, and the recent show is skipped
+            let entry = mapping.choose_entry(&["Bluey".to_string()], &mut rng).unwrap();
+            assert_eq!(entry.to_string(), "Bob's Burgers");
+        }
+        // ...unless it's the only one left
+        let recent = vec!["Bluey".to_string(), "Bob's Burgers".to_string()];
+        let entry = mapping.choose_entry(&recent, &mut rng).unwrap();
+        assert_ne!(entry.to_string(), "Peppa Pig");
+
+        let off: UserMapping = serde_yaml::from_str("[{show: Bluey, weight: 0}]").unwrap();
+        assert!(off.choose_entry(&[], &mut rng).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_select_for_entry_avoids_recent_episodes() {
+        let _mocks = mock_show_with_watch_state();
+        let client = test_client();
+        let entry = MappingEntry::Show("Bluey".to_string());
+
+        // 1x02 would be next, but it was just played
+        let options = SelectOptions {
+            strategy: SelectionStrategy::NextUnwatched,
+            avoid_episodes: [12].into_iter().collect(),
+            ..Default::default()
+        };
+        let selection = client.select_for_entry(&entry, &options).await.unwrap();
+        assert_eq!(selection.episode_id(), Some(13));
+
+        // Everything recent: fall back to the whole show
+        let options = SelectOptions {
+            strategy: SelectionStrategy::NextUnwatched,
+            avoid_episodes: [11, 12, 13, 90].into_iter().collect(),
+            ..Default::default()
+        };
+        let selection = client.select_for_entry(&entry, &options).await.unwrap();
+        assert_eq!(selection.episode_id(), Some(12));
+
+        // Excluded episodes stay out even then
+        let options = SelectOptions {
+            strategy: SelectionStrategy::NextUnwatched,
+            avoid_episodes: [11, 12, 13, 90].into_iter().collect(),
+            exclude_episodes: [12].into_iter().collect(),
+            ..Default::default()
+        };
+        let selection = client.select_for_entry(&entry, &options).await.unwrap();
+        assert_eq!(selection.episode_id(), Some(13));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_json_response() {
+        let _mock = mock("POST", "/jsonrpc")
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body("This is not valid JSON")
+            .create();
+
+        let client = test_client();
+        let params = json!({"jsonrpc": "2.0", "method": "test", "id": 1});
+
+        let result = client.rpc_call(&params).await;
+        assert!(matches!(result, Err(KodiError::Decode(_))));
     }
 }
@@ -1,62 +1,163 @@
 // Import the koditool library from the workspace
 use koditool::Config;
+use koditool::RepeatWindow;
 use koditool::RpcClient;
+use koditool::SelectOptions;
+use koditool::UserMapping;
 
-use rocket::tokio::sync::RwLock;
+use rocket::tokio::sync::{oneshot, Mutex, Notify, RwLock};
+
+use crate::history::WatchHistory;
+use crate::schedule::Schedule;
+use crate::screen_time::{Budgets, ScreenTimeReport, ScreenTimeUsage};
 use std::sync::Arc;
 
 use std::env;
 use std::path::Path;
 
+use rocket::serde::de::DeserializeOwned;
 use rocket::serde::Deserialize;
 use rocket::serde::Serialize;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+// State the app keeps between runs, next to the config files
+pub const RECENTLY_PLAYED_FILE: &str = "recently_played.json";
+pub const SCREEN_TIME_FILE: &str = "screen_time.json";
+pub const EXCLUSIONS_FILE: &str = "exclusions.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ShowMappings {
     #[serde(flatten)]
-    shows: HashMap<String, Vec<String>>,
+    pub(crate) shows: HashMap<String, UserMapping>,
 }
 
 impl ShowMappings {
-    // Add a method to get alphabetically sorted shows
+    // Add a method to get alphabetically sorted shows (display labels, so
+    // movie entries read like "Movies (genre: Animation)")
     pub fn sorted_shows(&self) -> BTreeMap<String, Vec<String>> {
         let mut sorted_map = BTreeMap::new();
 
-        for (key, mut values) in self.shows.clone() {
+        for (key, mapping) in &self.shows {
+            let mut labels: Vec<String> =
+                mapping.shows.iter().map(|entry| entry.to_string()).collect();
             // Sort the values (show names) alphabetically
-            values.sort();
-            sorted_map.insert(key, values);
+            labels.sort();
+            sorted_map.insert(key.clone(), labels);
         }
 
         sorted_map
     }
+
+    // The shows, movie entries and options for one user, as configured
+    pub fn user(&self, user: &str) -> Option<&UserMapping> {
+        self.shows.get(user)
+    }
+
+    // Each user's entries in label order, so edits and reloads compare equal
+    pub(crate) fn sort_entries(&mut self) {
+        for mapping in self.shows.values_mut() {
+            mapping.shows.sort_by_key(|entry| entry.to_string());
+        }
+    }
+
+    // Users in name order. Comments in a hand-edited file don't survive this.
+    pub fn to_yaml(&self) -> Result<String, String> {
+        let users: BTreeMap<&String, &UserMapping> = self.shows.iter().collect();
+        serde_yaml::to_string(&users)
+            .map_err(|e| format!("Failed to serialize show mappings: {}", e))
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct JukectlChannel {
     pub name: String,
     pub any: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub not: Vec<String>,
 }
 
+impl JukectlChannel {
+    // What makes this channel unusable; empty if nothing does
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.name.trim().is_empty() {
+            problems.push("Channel name is empty".to_string());
+        }
+        if self.any.iter().all(|tag| tag.trim().is_empty()) {
+            problems.push("`any` has no tags, so the channel plays nothing".to_string());
+        }
+        for tag in self.any.iter().filter(|tag| self.not.contains(tag)) {
+            problems.push(format!("Tag '{}' is in both `any` and `not`", tag));
+        }
+        problems
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JukectlChannels {
     pub channels: Vec<JukectlChannel>,
 }
 
+// When the sleep timer goes off
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SleepTimerMode {
+    // After `duration_minutes`
+    #[default]
+    Duration,
+    // When the item playing now finishes, instead of starting another
+    EndOfEpisode,
+}
+
+// What happens to playback when the sleep timer goes off
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SleepAction {
+    #[default]
+    Stop,
+    Pause,
+    // Turn the volume down gradually, stop, then put the volume back
+    FadeOut,
+    // Stop, then System.Suspend
+    Suspend,
+}
+
+impl std::fmt::Display for SleepAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SleepAction::Stop => "stop",
+            SleepAction::Pause => "pause",
+            SleepAction::FadeOut => "fade out",
+            SleepAction::Suspend => "suspend",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SleepTimer {
     pub enabled: bool,
-    pub duration_hours: u32,
+    #[serde(default)]
+    pub mode: SleepTimerMode,
+    #[serde(default = "default_duration_minutes")]
+    pub duration_minutes: u32,
+    #[serde(default)]
+    pub action: SleepAction,
     // Store as seconds since UNIX epoch for easy serialization
     pub start_timestamp: Option<u64>,
     // Include remaining seconds in the serialized data
     #[serde(skip_serializing_if = "Option::is_none")]
     pub remaining_seconds: Option<u64>,
+    // State files from before minute durations; moved into duration_minutes on load
+    #[serde(default, skip_serializing)]
+    duration_hours: Option<u32>,
+}
+
+fn default_duration_minutes() -> u32 {
+    120
 }
 
 impl SleepTimer {
@@ -69,17 +170,22 @@ impl Default for SleepTimer {
     fn default() -> Self {
         Self {
             enabled: false,
-            duration_hours: 2, // Default 2 hours
+            mode: SleepTimerMode::Duration,
+            duration_minutes: default_duration_minutes(), // Default 2 hours
+            action: SleepAction::Stop,
             start_timestamp: None,
             remaining_seconds: None,
+            duration_hours: None,
         }
     }
 }
 
 impl SleepTimer {
-    pub fn start(&mut self, duration_hours: u32) {
+    pub fn start(&mut self, duration_minutes: u32, action: SleepAction) {
         self.enabled = true;
-        self.duration_hours = duration_hours;
+        self.mode = SleepTimerMode::Duration;
+        self.duration_minutes = duration_minutes;
+        self.action = action;
         // Store current time as seconds since UNIX epoch
         self.start_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -88,57 +194,71 @@ impl SleepTimer {
         self.update_remaining_time();
     }
 
+    // Goes off when the scheduler next finds nothing playing
+    pub fn start_until_end_of_episode(&mut self, action: SleepAction) {
+        self.enabled = true;
+        self.mode = SleepTimerMode::EndOfEpisode;
+        self.action = action;
+        self.start_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|d| d.as_secs());
+        self.remaining_seconds = None;
+    }
+
     pub fn stop(&mut self) {
         self.enabled = false;
         self.start_timestamp = None;
         self.remaining_seconds = None;
     }
 
+    // Only duration timers expire on their own; end-of-episode ones are up
+    // to the scheduler
     pub fn is_expired(&self) -> bool {
-        if !self.enabled {
+        if !self.enabled || self.mode != SleepTimerMode::Duration {
             return false;
         }
 
-        self.update_remaining_time_const();
-        self.remaining_seconds.is_none_or(|remaining| remaining == 0)
+        self.update_remaining_time_const() == 0
+    }
+
+    pub fn waits_for_end_of_episode(&self) -> bool {
+        self.enabled && self.mode == SleepTimerMode::EndOfEpisode
+    }
+
+    // "90 min" or "end of episode", for messages
+    pub fn describe(&self) -> String {
+        match self.mode {
+            SleepTimerMode::Duration => format!("{} min", self.duration_minutes),
+            SleepTimerMode::EndOfEpisode => "end of episode".to_string(),
+        }
     }
 
     pub fn update_remaining_time(&mut self) {
-        if !self.enabled || self.start_timestamp.is_none() {
+        if let Some(hours) = self.duration_hours.take() {
+            self.duration_minutes = hours * 60;
+        }
+        if !self.enabled || self.mode != SleepTimerMode::Duration {
             self.remaining_seconds = None;
             return;
         }
 
-        let start_timestamp = self.start_timestamp.unwrap();
-        let current_timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
-
-        let elapsed_seconds = current_timestamp.saturating_sub(start_timestamp);
-        let total_seconds = (self.duration_hours as u64) * 3600;
-
-        if elapsed_seconds >= total_seconds {
-            self.remaining_seconds = Some(0);
-        } else {
-            self.remaining_seconds = Some(total_seconds - elapsed_seconds);
-        }
+        self.remaining_seconds = Some(self.update_remaining_time_const());
     }
 
     // Non-mutating version for checking expiration
     fn update_remaining_time_const(&self) -> u64 {
-        if !self.enabled || self.start_timestamp.is_none() {
+        let Some(start_timestamp) = self.start_timestamp.filter(|_| self.enabled) else {
             return 0;
-        }
+        };
 
-        let start_timestamp = self.start_timestamp.unwrap();
         let current_timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_secs())
             .unwrap_or(0);
 
         let elapsed_seconds = current_timestamp.saturating_sub(start_timestamp);
-        let total_seconds = (self.duration_hours as u64) * 3600;
+        let total_seconds = (self.duration_minutes as u64) * 60;
 
         total_seconds.saturating_sub(elapsed_seconds)
     }
@@ -149,6 +269,9 @@ pub struct TVModeStatus {
     pub active: bool,
     pub user: Option<String>,
     pub sleep_timer: SleepTimer,
+    // The schedule slot occurrence last acted on, e.g. "kids morning@2026-10-17 07:00"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule_run: Option<String>,
 }
 
 impl TVModeStatus {
@@ -165,32 +288,265 @@ impl TVModeStatus {
     }
 }
 
+// Per-user record of what the scheduler started, newest first. Feeds the
+// repeat window so the same show/episode doesn't come straight back.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RecentlyPlayed {
+    #[serde(flatten)]
+    users: HashMap<String, VecDeque<PlayRecord>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PlayRecord {
+    // Display label of the mapping entry, e.g. "Bluey"
+    pub entry: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episodeid: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movieid: Option<u64>,
+    pub played_at: u64,
+}
+
+// More than any sensible repeat window needs
+const RECENTLY_PLAYED_LIMIT: usize = 100;
+
+impl RecentlyPlayed {
+    pub fn record(&mut self, user: &str, record: PlayRecord) {
+        let history = self.users.entry(user.to_string()).or_default();
+        history.push_front(record);
+        history.truncate(RECENTLY_PLAYED_LIMIT);
+    }
+
+    pub fn for_user(&self, user: &str) -> impl Iterator<Item = &PlayRecord> {
+        self.users.get(user).into_iter().flatten()
+    }
+
+    // Labels of the entries behind the user's last `window.shows` plays
+    pub fn recent_entries(&self, user: &str, window: &RepeatWindow) -> Vec<String> {
+        self.for_user(user)
+            .take(window.shows)
+            .map(|record| record.entry.clone())
+            .collect()
+    }
+
+    // Selection options that skip the user's last `window.episodes` items
+    pub fn select_options(&self, user: &str, mapping: &UserMapping) -> SelectOptions {
+        let window = mapping.repeat_window.unwrap_or_default();
+        let recent: Vec<&PlayRecord> = self.for_user(user).take(window.episodes).collect();
+
+        SelectOptions {
+            strategy: mapping.strategy.unwrap_or_default(),
+            avoid_episodes: recent.iter().filter_map(|r| r.episodeid).collect(),
+            avoid_movies: recent.iter().filter_map(|r| r.movieid).collect(),
+            ..Default::default()
+        }
+    }
+}
+
+// Items a user never wants TV mode to pick again, kept in exclusions.json.
+// Unlike the repeat window these hold even when nothing else is left.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Exclusions {
+    #[serde(flatten)]
+    users: BTreeMap<String, Vec<ExcludedItem>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExcludedItem {
+    // What it was, e.g. "Bluey S01E02 - Pilot"
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episodeid: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movieid: Option<u64>,
+    pub excluded_at: u64,
+}
+
+impl Exclusions {
+    // False if `user` already had it excluded
+    pub fn add(&mut self, user: &str, item: ExcludedItem) -> bool {
+        let items = self.users.entry(user.to_string()).or_default();
+        if items.iter().any(|excluded| {
+            excluded.episodeid == item.episodeid && excluded.movieid == item.movieid
+        }) {
+            return false;
+        }
+        items.push(item);
+        true
+    }
+
+    // False if `user` had nothing matching to remove
+    pub fn remove(&mut self, user: &str, matches: impl Fn(&ExcludedItem) -> bool) -> bool {
+        let Some(items) = self.users.get_mut(user) else {
+            return false;
+        };
+        let before = items.len();
+        items.retain(|item| !matches(item));
+        let removed = items.len() < before;
+        if items.is_empty() {
+            self.users.remove(user);
+        }
+        removed
+    }
+
+    pub fn for_user(&self, user: &str) -> impl Iterator<Item = &ExcludedItem> {
+        self.users.get(user).into_iter().flatten()
+    }
+
+    // Keep `user`'s excluded items out of a selection
+    pub fn apply(&self, user: &str, options: &mut SelectOptions) {
+        for item in self.for_user(user) {
+            options.exclude_episodes.extend(item.episodeid);
+            options.exclude_movies.extend(item.movieid);
+        }
+    }
+}
+
+// One Kodi box and the TV mode running on it
+#[derive(Clone, Debug)]
+pub struct Room {
+    pub name: String,
+    // Swapped whole on a config reload; see `client`
+    pub rpc_client: Arc<RwLock<Arc<RpcClient>>>,
+    pub tv_mode: Arc<RwLock<TVModeStatus>>,
+    // Calls off the fade-out an expired sleep timer left running, if any
+    pub(crate) fade_out: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    // Wakes the room's scheduler ahead of its next tick, e.g. after a skip
+    pub wake: Arc<Notify>,
+}
+
+impl Room {
+    fn new(name: String, rpc_client: RpcClient, tv_mode: TVModeStatus) -> Self {
+        Room {
+            name,
+            rpc_client: Arc::new(RwLock::new(Arc::new(rpc_client))),
+            tv_mode: Arc::new(RwLock::new(tv_mode)),
+            fade_out: Arc::new(Mutex::new(None)),
+            wake: Arc::new(Notify::new()),
+        }
+    }
+
+    // Stop a running fade-out where it is. The volume is put back and
+    // whatever is playing is left alone.
+    pub async fn cancel_fade_out(&self) {
+        // Dropping the sender is what wakes the fade
+        self.fade_out.lock().await.take();
+    }
+
+    // The room's Kodi client, cloned out of the lock so that a slow call
+    // (such as a fade-out's) never holds up a reload swapping in a new one
+    pub async fn client(&self) -> Arc<RpcClient> {
+        self.rpc_client.read().await.clone()
+    }
+}
+
+// Rooms beyond the Kodi described at the top of config.yml:
+//
+//   room: living room       # name for the top-level Kodi ("default" if unset)
+//   rooms:
+//     kids room:
+//       url: http://kids-pi:8080
+//       username: kodi
+//       password: kodi
+#[derive(Debug, Deserialize, Default)]
+pub(crate) struct RoomsConfig {
+    #[serde(default)]
+    pub(crate) room: Option<String>,
+    #[serde(default)]
+    pub(crate) rooms: BTreeMap<String, Config>,
+}
+
+pub const DEFAULT_ROOM: &str = "default";
+
 #[derive(Clone, Debug)]
 pub struct AppState {
-    pub rpc_client: Arc<RwLock<RpcClient>>,
+    // Always contains `default_room`, which the room-less routes act on
+    pub rooms: Arc<BTreeMap<String, Room>>,
+    pub default_room: String,
     pub show_mappings: Arc<RwLock<ShowMappings>>,
-    pub tv_mode: Arc<RwLock<TVModeStatus>>,
     pub jukectl_channels: Arc<RwLock<Vec<JukectlChannel>>>,
+    pub recently_played: Arc<RwLock<RecentlyPlayed>>,
+    pub exclusions: Arc<RwLock<Exclusions>>,
+    pub schedule: Arc<RwLock<Schedule>>,
+    pub budgets: Arc<RwLock<Budgets>>,
+    pub screen_time: Arc<RwLock<ScreenTimeUsage>>,
+    pub history: Arc<RwLock<WatchHistory>>,
     pub config_dir: String,
 }
 
 impl AppState {
+    pub fn room(&self, name: &str) -> Option<&Room> {
+        self.rooms.get(name)
+    }
+
+    pub fn default_room(&self) -> &Room {
+        &self.rooms[&self.default_room]
+    }
+
+    // Every room's TV mode goes into persistent_state.json, keyed by room
     pub async fn save_to_disk(&self) {
-        let tv_mode = self.tv_mode.read().await;
-        let path = Path::new(&self.config_dir).join("persistent_state.json");
+        let mut states = BTreeMap::new();
+        for (name, room) in self.rooms.iter() {
+            states.insert(name.clone(), room.tv_mode.read().await.clone());
+        }
+        self.save_json("persistent_state.json", &states, "TV mode state");
+    }
+
+    // Used by the admin API; the caller holds the show_mappings lock
+    pub fn write_show_mappings(&self, mappings: &ShowMappings) -> Result<(), String> {
+        let path = Path::new(&self.config_dir).join("show_mappings.yml");
+        let yaml = mappings.to_yaml()?;
+        write_atomically(&path, &yaml)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    // Used by the channel editor; the caller holds the jukectl_channels lock
+    pub fn write_jukectl_channels(&self, channels: &[JukectlChannel]) -> Result<(), String> {
+        let path = Path::new(&self.config_dir).join("jukectl_channels.yml");
+        let yaml = serde_yaml::to_string(&JukectlChannels {
+            channels: channels.to_vec(),
+        })
+        .map_err(|e| format!("Failed to serialize jukectl channels: {}", e))?;
+        write_atomically(&path, &yaml)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    // Used by the schedule API; the caller holds the schedule lock
+    pub fn write_schedule(&self, schedule: &Schedule) -> Result<(), String> {
+        let path = Path::new(&self.config_dir).join("schedules.yml");
+        let yaml = serde_yaml::to_string(schedule)
+            .map_err(|e| format!("Failed to serialize schedule: {}", e))?;
+        write_atomically(&path, &yaml)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    // What `user` has watched today and this week, against their budget
+    pub async fn screen_time_report(&self, user: &str) -> ScreenTimeReport {
+        let budgets = self.budgets.read().await;
+        self.screen_time.read().await.report(
+            user,
+            budgets.user(user),
+            chrono::Local::now().date_naive(),
+        )
+    }
+
+    // Save `value` as JSON to `file` in the config directory. Failures are
+    // only logged; the state in memory carries on either way.
+    pub fn save_json<T: Serialize>(&self, file: &str, value: &T, what: &str) {
+        let path = Path::new(&self.config_dir).join(file);
 
-        let json = match serde_json::to_string_pretty(&*tv_mode) {
+        let json = match serde_json::to_string_pretty(value) {
             Ok(json) => json,
             Err(e) => {
-                error!("Failed to serialize TV mode state: {}", e);
+                error!("Failed to serialize {}: {}", what, e);
                 return;
             }
         };
 
-        if let Err(e) = std::fs::write(&path, json) {
-            error!("Failed to save TV mode state to {:?}: {}", path, e);
+        if let Err(e) = write_atomically(&path, &json) {
+            error!("Failed to save {} to {:?}: {}", what, path, e);
         } else {
-            debug!("Saved TV mode state to {:?}", path);
+            debug!("Saved {} to {:?}", what, path);
         }
     }
 }
@@ -204,24 +560,40 @@ pub fn initialize() -> Result<AppState, std::io::Error> {
     let mappings_path = Path::new(&config_dir).join("show_mappings.yml");
     let jukectl_path = Path::new(&config_dir).join("jukectl_channels.yml");
     let persistent_path = Path::new(&config_dir).join("persistent_state.json");
-
-    // Load config
-    let config = match Config::load(config_path.to_str().unwrap()) {
-        Ok(config) => config,
+    let recently_played_path = Path::new(&config_dir).join(RECENTLY_PLAYED_FILE);
+    let exclusions_path = Path::new(&config_dir).join(EXCLUSIONS_FILE);
+    let schedule_path = Path::new(&config_dir).join("schedules.yml");
+    let budgets_path = Path::new(&config_dir).join("budgets.yml");
+    let screen_time_path = Path::new(&config_dir).join(SCREEN_TIME_FILE);
+
+    // Load config, plus any extra rooms
+    let (default_room, room_configs) = match load_room_configs(&config_dir) {
+        Ok(rooms) => rooms,
         Err(e) => {
             eprintln!("Failed to load config from {:?}: {}", config_path, e);
-            return Err(std::io::Error::other(e.to_string()));
+            return Err(std::io::Error::other(e));
         }
     };
 
-    // Create RPC client
-    let rpc_client = match RpcClient::new(config) {
-        Ok(client) => client,
-        Err(e) => {
-            eprintln!("Failed to create RPC client: {}", e);
-            return Err(std::io::Error::other(e.to_string()));
-        }
-    };
+    // Load persistent state (optional)
+    let mut saved_states = load_persistent_state(&persistent_path, &default_room);
+
+    let mut rooms = BTreeMap::new();
+    for (name, config) in room_configs {
+        // Create RPC client
+        let rpc_client = match RpcClient::new(config) {
+            Ok(client) => client,
+            Err(e) => {
+                eprintln!("Failed to create RPC client for room '{}': {}", name, e);
+                return Err(std::io::Error::other(e.to_string()));
+            }
+        };
+
+        let mut tv_mode = saved_states.remove(&name).unwrap_or_default();
+        // Make sure to update the timer's remaining time
+        tv_mode.sleep_timer.update_remaining_time();
+        rooms.insert(name.clone(), Room::new(name, rpc_client, tv_mode));
+    }
 
     // Load show mappings
     let show_mappings = match load_show_mappings(&mappings_path) {
@@ -247,70 +619,185 @@ pub fn initialize() -> Result<AppState, std::io::Error> {
         }
     };
 
-    // Load persistent state (optional)
-    let tv_mode = if persistent_path.exists() {
-        match std::fs::read_to_string(&persistent_path) {
-            Ok(content) => match serde_json::from_str::<TVModeStatus>(&content) {
-                Ok(mut state) => {
-                    info!("Loaded persistent TV mode state from {:?}", persistent_path);
-                    // Make sure to update the timer's remaining time
-                    state.sleep_timer.update_remaining_time();
-                    state
-                }
-                Err(e) => {
-                    warn!(
-                        "Failed to parse persistent state from {:?}: {}. Using default.",
-                        persistent_path, e
-                    );
-                    TVModeStatus::new()
-                }
-            },
+    // Load recently played history (optional)
+    let recently_played = load_json_or_default(&recently_played_path, "recently played history");
+
+    // Load what users never want played again (optional)
+    let exclusions = load_json_or_default(&exclusions_path, "exclusions");
+
+    // Load the schedule (optional)
+    let schedule = if schedule_path.exists() {
+        match load_yaml(&schedule_path) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                eprintln!("{} (running without a schedule)", e);
+                Schedule::default()
+            }
+        }
+    } else {
+        Schedule::default()
+    };
+
+    // Load screen time budgets (optional)
+    let budgets = if budgets_path.exists() {
+        match load_yaml(&budgets_path) {
+            Ok(budgets) => budgets,
             Err(e) => {
-                warn!(
-                    "Failed to read persistent state from {:?}: {}. Using default.",
-                    persistent_path, e
-                );
-                TVModeStatus::new()
+                eprintln!("{} (running without screen time budgets)", e);
+                Budgets::default()
             }
         }
     } else {
-        TVModeStatus::new()
+        Budgets::default()
     };
 
+    // Load what's been watched so far (optional)
+    let screen_time = load_json_or_default(&screen_time_path, "screen time");
+
     // Create app state with mutexes and Arc
     let app_state = AppState {
-        rpc_client: Arc::new(RwLock::new(rpc_client)),
+        rooms: Arc::new(rooms),
+        default_room,
         show_mappings: Arc::new(RwLock::new(show_mappings)),
-        tv_mode: Arc::new(RwLock::new(tv_mode)),
         jukectl_channels: Arc::new(RwLock::new(jukectl_channels)),
+        recently_played: Arc::new(RwLock::new(recently_played)),
+        exclusions: Arc::new(RwLock::new(exclusions)),
+        schedule: Arc::new(RwLock::new(schedule)),
+        budgets: Arc::new(RwLock::new(budgets)),
+        screen_time: Arc::new(RwLock::new(screen_time)),
+        history: Arc::new(RwLock::new(WatchHistory::new(&config_dir))),
         config_dir,
     };
 
     Ok(app_state)
 }
 
-fn load_show_mappings(path: &Path) -> Result<ShowMappings, String> {
-    std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
-        .and_then(|content| {
-            let mut mappings: ShowMappings = serde_yaml::from_str(&content)
-                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+// The default room's name and every room's Kodi settings, from config.yml
+pub(crate) fn load_room_configs(
+    config_dir: &str,
+) -> Result<(String, BTreeMap<String, Config>), String> {
+    let config_path = Path::new(config_dir).join("config.yml");
+    let config = Config::load(&config_path)?;
+    let rooms_config: RoomsConfig = load_yaml(&config_path)?;
+
+    let default_room = rooms_config
+        .room
+        .unwrap_or_else(|| DEFAULT_ROOM.to_string());
+    let mut room_configs = rooms_config.rooms;
+    if room_configs.insert(default_room.clone(), config).is_some() {
+        warn!(
+            "Room '{}' is defined twice; using the top-level Kodi settings",
+            default_room
+        );
+    }
 
-            // Sort each vector of show names for consistency
-            for values in mappings.shows.values_mut() {
-                values.sort();
+    for config in room_configs.values_mut() {
+        // A relative library cache file lives next to the other config files
+        if let Some(cache_path) = config
+            .library_cache
+            .as_mut()
+            .and_then(|cache| cache.path.as_mut())
+        {
+            if cache_path.is_relative() {
+                *cache_path = Path::new(config_dir).join(&*cache_path);
             }
+        }
+    }
 
-            Ok(mappings)
-        })
+    Ok((default_room, room_configs))
 }
 
-fn load_jukectl_channels(path: &Path) -> Result<Vec<JukectlChannel>, String> {
-    std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
-        .and_then(|content| {
-            let channels_config: JukectlChannels = serde_yaml::from_str(&content)
-                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
-            Ok(channels_config.channels)
-        })
+pub(crate) fn load_show_mappings(path: &Path) -> Result<ShowMappings, String> {
+    let mut mappings: ShowMappings = load_yaml(path)?;
+
+    // Sort each vector of entries for consistency
+    mappings.sort_entries();
+
+    Ok(mappings)
+}
+
+pub(crate) fn load_jukectl_channels(path: &Path) -> Result<Vec<JukectlChannel>, String> {
+    let channels_config: JukectlChannels = load_yaml(path)?;
+    Ok(channels_config.channels)
+}
+
+// Read and parse one of the YAML config files
+pub(crate) fn load_yaml<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_yaml::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+// Write to a temporary file next to `path` and rename it into place, so
+// readers (and the config watcher) never see a half-written file
+pub(crate) fn write_atomically(path: &Path, contents: &str) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("config");
+    let tmp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    std::fs::write(&tmp_path, contents)?;
+    std::fs::rename(&tmp_path, path)
+}
+
+// State saved with `AppState::save_json`. A missing or unreadable file
+// starts over with the default.
+fn load_json_or_default<T: DeserializeOwned + Default>(path: &Path, what: &str) -> T {
+    if !path.exists() {
+        return T::default();
+    }
+
+    match std::fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(value) => {
+            info!("Loaded {} from {:?}", what, path);
+            value
+        }
+        Err(e) => {
+            warn!(
+                "Failed to load {} from {:?}: {}. Starting empty.",
+                what, path, e
+            );
+            T::default()
+        }
+    }
+}
+
+// TV mode per room. Files from before rooms existed hold a single state,
+// which belongs to the default room.
+fn load_persistent_state(path: &Path, default_room: &str) -> HashMap<String, TVModeStatus> {
+    if !path.exists() {
+        return HashMap::new();
+    }
+
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            warn!(
+                "Failed to read persistent state from {:?}: {}. Using default.",
+                path, e
+            );
+            return HashMap::new();
+        }
+    };
+
+    if let Ok(states) = serde_json::from_str::<HashMap<String, TVModeStatus>>(&content) {
+        info!("Loaded persistent TV mode state from {:?}", path);
+        return states;
+    }
+    match serde_json::from_str::<TVModeStatus>(&content) {
+        Ok(state) => {
+            info!("Loaded persistent TV mode state from {:?}", path);
+            HashMap::from([(default_room.to_string(), state)])
+        }
+        Err(e) => {
+            warn!(
+                "Failed to parse persistent state from {:?}: {}. Using default.",
+                path, e
+            );
+            HashMap::new()
+        }
+    }
 }
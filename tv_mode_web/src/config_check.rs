@@ -0,0 +1,644 @@
+// Validation of config.yml, show_mappings.yml, jukectl_channels.yml,
+// schedules.yml and budgets.yml.
+//
+// Loading only fails on files that don't parse; this catches the mistakes
+// that parse fine but break the scheduler later, such as a user with no
+// shows, a channel with no tags, or (with `live`) a show title Kodi doesn't
+// have. Used by `--check-config`, `GET /api/config/validate` and at startup.
+
+use koditool::{
+    find_channel, find_show, Config, GetChannelsParams, GetMoviesParams, MappingEntry, MovieFilter,
+    RpcClient, UserMapping, DEFAULT_CHANNEL_MINUTES,
+};
+use rocket::serde::Serialize;
+use rocket::tokio::time::{timeout, Duration};
+use serde_yaml::{Mapping, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::Path;
+
+use crate::app_state::{
+    load_jukectl_channels, load_room_configs, load_show_mappings, load_yaml, JukectlChannel,
+    RoomsConfig, ShowMappings, DEFAULT_ROOM,
+};
+use crate::schedule::Schedule;
+use crate::screen_time::Budgets;
+
+const CONFIG_FILE: &str = "config.yml";
+const MAPPINGS_FILE: &str = "show_mappings.yml";
+const JUKECTL_FILE: &str = "jukectl_channels.yml";
+const SCHEDULE_FILE: &str = "schedules.yml";
+const BUDGETS_FILE: &str = "budgets.yml";
+
+// How long the live check waits on each room's Kodi
+const LIVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    // The scheduler or play API will fail on this
+    Error,
+    // Probably a mistake, but things still work
+    Warning,
+}
+
+// One problem with one entry of a config file
+#[derive(Debug, Serialize, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub file: String,
+    // e.g. "kids / Bluey", "channels[2] (Chill)" or "room living room";
+    // empty when the problem is with the file as a whole
+    pub entry: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    // No errors (warnings are fine)
+    pub valid: bool,
+    // Whether show titles were checked against Kodi
+    pub live: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        if self.entry.is_empty() {
+            write!(f, "{}: {}: {}", severity, self.file, self.message)
+        } else {
+            write!(
+                f,
+                "{}: {}: {}: {}",
+                severity, self.file, self.entry, self.message
+            )
+        }
+    }
+}
+
+#[derive(Default)]
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn push(&mut self, severity: Severity, file: &str, entry: &str, message: String) {
+        self.diagnostics.push(Diagnostic {
+            severity,
+            file: file.to_string(),
+            entry: entry.to_string(),
+            message,
+        });
+    }
+
+    fn error(&mut self, file: &str, entry: &str, message: String) {
+        self.push(Severity::Error, file, entry, message);
+    }
+
+    fn warning(&mut self, file: &str, entry: &str, message: String) {
+        self.push(Severity::Warning, file, entry, message);
+    }
+
+    fn finish(self, live: bool) -> ValidationReport {
+        let errors = self
+            .diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        ValidationReport {
+            valid: errors == 0,
+            live,
+            errors,
+            warnings: self.diagnostics.len() - errors,
+            diagnostics: self.diagnostics,
+        }
+    }
+}
+
+// What parsed, for the live check to work from
+struct Parsed {
+    rooms: Option<BTreeMap<String, Config>>,
+    mappings: Option<ShowMappings>,
+}
+
+// Structural checks only; never talks to Kodi
+pub fn check_files(config_dir: &str) -> ValidationReport {
+    let (checker, _) = check_structure(config_dir);
+    checker.finish(false)
+}
+
+// Structural checks, plus with `live` a lookup of every show and movie
+// entry in each room's library
+pub async fn validate(config_dir: &str, live: bool) -> ValidationReport {
+    let (mut checker, parsed) = check_structure(config_dir);
+    if live {
+        if let Parsed {
+            rooms: Some(rooms),
+            mappings: Some(mappings),
+        } = parsed
+        {
+            check_libraries(&mut checker, rooms, &mappings).await;
+        }
+    }
+    checker.finish(live)
+}
+
+fn check_structure(config_dir: &str) -> (Checker, Parsed) {
+    let mut checker = Checker::default();
+    let rooms = check_config(&mut checker, config_dir);
+    let mappings = check_show_mappings(&mut checker, config_dir);
+    check_jukectl_channels(&mut checker, config_dir);
+    check_schedule(&mut checker, config_dir, rooms.as_ref(), mappings.as_ref());
+    check_budgets(&mut checker, config_dir, mappings.as_ref());
+    (checker, Parsed { rooms, mappings })
+}
+
+fn check_config(checker: &mut Checker, config_dir: &str) -> Option<BTreeMap<String, Config>> {
+    let (default_room, rooms) = match load_room_configs(config_dir) {
+        Ok(rooms) => rooms,
+        Err(e) => {
+            checker.error(CONFIG_FILE, "", e);
+            return None;
+        }
+    };
+
+    // load_room_configs keeps the top-level settings when a room repeats them
+    if let Ok(rooms_config) = load_yaml::<RoomsConfig>(&Path::new(config_dir).join(CONFIG_FILE)) {
+        if rooms_config.rooms.contains_key(&default_room) {
+            let name = rooms_config.room.as_deref().unwrap_or(DEFAULT_ROOM);
+            checker.warning(
+                CONFIG_FILE,
+                &format!("rooms / {}", name),
+                "Same name as the top-level Kodi; these settings are ignored".to_string(),
+            );
+        }
+    }
+
+    for (name, config) in &rooms {
+        check_room(checker, &format!("room {}", name), config);
+    }
+    Some(rooms)
+}
+
+fn check_room(checker: &mut Checker, entry: &str, config: &Config) {
+    match reqwest::Url::parse(&config.url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+        Ok(_) => checker.error(
+            CONFIG_FILE,
+            entry,
+            format!("url '{}' must be http:// or https://", config.url),
+        ),
+        Err(e) => checker.error(
+            CONFIG_FILE,
+            entry,
+            format!("url '{}' is not a valid URL: {}", config.url, e),
+        ),
+    }
+
+    if let Some(websocket_url) = &config.websocket_url {
+        if !websocket_url.starts_with("ws://") && !websocket_url.starts_with("wss://") {
+            checker.error(
+                CONFIG_FILE,
+                entry,
+                format!("websocket_url '{}' must be ws:// or wss://", websocket_url),
+            );
+        }
+    }
+
+    if let Some(cache) = &config.library_cache {
+        if cache.ttl_seconds == 0 {
+            checker.warning(
+                CONFIG_FILE,
+                entry,
+                "library_cache.ttl_seconds is 0, so the cache is never used".to_string(),
+            );
+        }
+        if let Some(dir) = cache.path.as_ref().and_then(|path| path.parent()) {
+            if !dir.as_os_str().is_empty() && !dir.is_dir() {
+                checker.warning(
+                    CONFIG_FILE,
+                    entry,
+                    format!(
+                        "library_cache.path is in {}, which doesn't exist",
+                        dir.display()
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_show_mappings(checker: &mut Checker, config_dir: &str) -> Option<ShowMappings> {
+    let path = Path::new(config_dir).join(MAPPINGS_FILE);
+    let mappings = match load_show_mappings(&path) {
+        Ok(mappings) => mappings,
+        Err(e) => {
+            checker.error(MAPPINGS_FILE, "", e);
+            return None;
+        }
+    };
+
+    if mappings.shows.is_empty() {
+        checker.error(MAPPINGS_FILE, "", "No users configured".to_string());
+    }
+
+    // What serde skipped over is only in the file itself
+    let raw = load_yaml::<Mapping>(&path).unwrap_or_default();
+
+    let users: BTreeMap<&String, &UserMapping> = mappings.shows.iter().collect();
+    for (user, mapping) in users {
+        check_user(checker, user, mapping);
+        if let Some(raw_mapping) = raw.get(user.as_str()) {
+            check_unknown_keys(checker, user, raw_mapping);
+        }
+    }
+    Some(mappings)
+}
+
+fn check_user(checker: &mut Checker, user: &str, mapping: &UserMapping) {
+    if mapping.shows.is_empty() {
+        checker.error(MAPPINGS_FILE, user, "No shows configured".to_string());
+        return;
+    }
+
+    let enabled = mapping
+        .shows
+        .iter()
+        .filter(|entry| entry.weight() > 0)
+        .count();
+    if enabled == 0 {
+        checker.error(
+            MAPPINGS_FILE,
+            user,
+            "Every entry has weight 0, so nothing can be picked".to_string(),
+        );
+    } else if let Some(window) = &mapping.repeat_window {
+        if enabled > 1 && window.shows >= enabled {
+            checker.warning(
+                MAPPINGS_FILE,
+                user,
+                format!(
+                    "repeat_window.shows ({}) covers all {} entries, so once each \
+                     has played any of them can repeat",
+                    window.shows, enabled
+                ),
+            );
+        }
+    }
+
+    let mut seen = HashSet::new();
+    for entry in &mapping.shows {
+        let label = format!("{} / {}", user, entry);
+        if !seen.insert(entry.to_string()) {
+            checker.warning(MAPPINGS_FILE, &label, "Listed more than once".to_string());
+        }
+        check_entry(checker, &label, entry);
+    }
+}
+
+fn check_entry(checker: &mut Checker, label: &str, entry: &MappingEntry) {
+    match entry {
+        MappingEntry::Show(title) => {
+            if title.trim().is_empty() {
+                checker.error(MAPPINGS_FILE, label, "Show title is empty".to_string());
+            }
+        }
+        MappingEntry::Detailed(entry) => {
+            if entry.show.trim().is_empty() {
+                checker.error(MAPPINGS_FILE, label, "Show title is empty".to_string());
+            }
+
+            let filter = &entry.filter;
+            if let (Some(min), Some(max)) = (filter.min_runtime, filter.max_runtime) {
+                if min > max {
+                    checker.error(
+                        MAPPINGS_FILE,
+                        label,
+                        format!(
+                            "min_runtime ({}) is more than max_runtime ({}), so no \
+                             episode can match",
+                            min, max
+                        ),
+                    );
+                }
+            }
+            if filter.exclude_specials && filter.seasons.is_some_and(|s| s.last == Some(0)) {
+                checker.error(
+                    MAPPINGS_FILE,
+                    label,
+                    "seasons only covers specials, which exclude_specials removes".to_string(),
+                );
+            }
+        }
+        MappingEntry::Movies { movies, .. } => {
+            // A misspelt key (`genra:`) is silently ignored and leaves this empty
+            if movies.to_filter().is_none() {
+                checker.warning(
+                    MAPPINGS_FILE,
+                    label,
+                    "No title, genre, set or tag given, so any movie can be picked".to_string(),
+                );
+            }
+        }
+        MappingEntry::Channel {
+            channel, minutes, ..
+        } => {
+            if channel.trim().is_empty() {
+                checker.error(MAPPINGS_FILE, label, "Channel name is empty".to_string());
+            }
+            if let Some(minutes) = minutes.filter(|minutes| minutes.is_nan() || *minutes <= 0.0) {
+                checker.warning(
+                    MAPPINGS_FILE,
+                    label,
+                    format!(
+                        "minutes ({}) is not above 0, so the default of {} is used",
+                        minutes, DEFAULT_CHANNEL_MINUTES
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// Keys that aren't a field of the entry are dropped when it's parsed, so a
+// misspelt option (`exclude_special:`) would quietly not apply
+fn check_unknown_keys(checker: &mut Checker, user: &str, raw_mapping: &Value) {
+    let entries = match raw_mapping {
+        Value::Sequence(entries) => entries,
+        Value::Mapping(mapping) => match mapping.get("shows") {
+            Some(Value::Sequence(entries)) => entries,
+            _ => return,
+        },
+        _ => return,
+    };
+
+    for raw_entry in entries {
+        let Value::Mapping(fields) = raw_entry else {
+            continue;
+        };
+        let Ok(entry) = serde_yaml::from_value::<MappingEntry>(raw_entry.clone()) else {
+            continue;
+        };
+        let label = format!("{} / {}", user, entry);
+        match &entry {
+            MappingEntry::Show(_) | MappingEntry::Detailed(_) => {
+                warn_unknown_keys(checker, &label, fields, &MappingEntry::SHOW_KEYS)
+            }
+            MappingEntry::Movies { .. } => {
+                warn_unknown_keys(checker, &label, fields, &MappingEntry::MOVIES_KEYS);
+                if let Some(Value::Mapping(movies)) = fields.get("movies") {
+                    warn_unknown_keys(checker, &label, movies, &MovieFilter::KEYS);
+                }
+            }
+            MappingEntry::Channel { .. } => {
+                warn_unknown_keys(checker, &label, fields, &MappingEntry::CHANNEL_KEYS)
+            }
+        }
+    }
+}
+
+fn warn_unknown_keys(checker: &mut Checker, label: &str, fields: &Mapping, known: &[&str]) {
+    for key in fields.keys() {
+        let key = match key {
+            Value::String(key) => key.clone(),
+            other => serde_yaml::to_string(other)
+                .unwrap_or_default()
+                .trim()
+                .to_string(),
+        };
+        if !known.contains(&key.as_str()) {
+            checker.warning(
+                MAPPINGS_FILE,
+                label,
+                format!("Unknown key '{}', which is ignored", key),
+            );
+        }
+    }
+}
+
+fn check_jukectl_channels(checker: &mut Checker, config_dir: &str) {
+    // The file is optional
+    let path = Path::new(config_dir).join(JUKECTL_FILE);
+    if !path.exists() {
+        return;
+    }
+    let channels = match load_jukectl_channels(&path) {
+        Ok(channels) => channels,
+        Err(e) => {
+            checker.error(JUKECTL_FILE, "", e);
+            return;
+        }
+    };
+
+    let mut seen = HashSet::new();
+    for (index, channel) in channels.iter().enumerate() {
+        let label = format!("channels[{}] ({})", index, channel.name);
+        check_channel(checker, &label, channel);
+        if !seen.insert(channel.name.trim().to_lowercase()) {
+            checker.warning(
+                JUKECTL_FILE,
+                &label,
+                "Another channel has the same name".to_string(),
+            );
+        }
+    }
+}
+
+fn check_channel(checker: &mut Checker, label: &str, channel: &JukectlChannel) {
+    for problem in channel.problems() {
+        checker.error(JUKECTL_FILE, label, problem);
+    }
+}
+
+// Users and rooms are only checked if their files loaded
+fn check_schedule(
+    checker: &mut Checker,
+    config_dir: &str,
+    rooms: Option<&BTreeMap<String, Config>>,
+    mappings: Option<&ShowMappings>,
+) {
+    // The file is optional
+    let path = Path::new(config_dir).join(SCHEDULE_FILE);
+    if !path.exists() {
+        return;
+    }
+    let schedule: Schedule = match load_yaml(&path) {
+        Ok(schedule) => schedule,
+        Err(e) => {
+            checker.error(SCHEDULE_FILE, "", e);
+            return;
+        }
+    };
+
+    let problems = schedule.problems(
+        |user| mappings.is_none_or(|mappings| mappings.user(user).is_some()),
+        |room| rooms.is_none_or(|rooms| rooms.contains_key(room)),
+    );
+    for (entry, message) in problems {
+        checker.error(SCHEDULE_FILE, &entry, message);
+    }
+}
+
+// A budget that can't do anything is a warning; nothing breaks because of it
+fn check_budgets(checker: &mut Checker, config_dir: &str, mappings: Option<&ShowMappings>) {
+    // The file is optional
+    let path = Path::new(config_dir).join(BUDGETS_FILE);
+    if !path.exists() {
+        return;
+    }
+    let budgets: Budgets = match load_yaml(&path) {
+        Ok(budgets) => budgets,
+        Err(e) => {
+            checker.error(BUDGETS_FILE, "", e);
+            return;
+        }
+    };
+
+    for (user, budget) in &budgets.users {
+        if mappings.is_some_and(|mappings| mappings.user(user).is_none()) {
+            checker.warning(
+                BUDGETS_FILE,
+                user,
+                format!("User '{}' not found in show mappings", user),
+            );
+        }
+        match (budget.daily_minutes, budget.weekly_minutes) {
+            (None, None) => checker.warning(
+                BUDGETS_FILE,
+                user,
+                "No daily_minutes or weekly_minutes, so there is no limit".to_string(),
+            ),
+            (Some(daily), Some(weekly)) if daily >= weekly => checker.warning(
+                BUDGETS_FILE,
+                user,
+                format!(
+                    "daily_minutes ({}) is not less than weekly_minutes ({}), so it never \
+                     applies",
+                    daily, weekly
+                ),
+            ),
+            _ => {}
+        }
+    }
+}
+
+async fn check_libraries(
+    checker: &mut Checker,
+    rooms: BTreeMap<String, Config>,
+    mappings: &ShowMappings,
+) {
+    let several_rooms = rooms.len() > 1;
+    let users: BTreeMap<&String, &UserMapping> = mappings.shows.iter().collect();
+
+    for (name, mut config) in rooms {
+        let entry = format!("room {}", name);
+        // Check against the library as it is now, and leave the cache file alone
+        config.library_cache = None;
+        let client = match RpcClient::new(config) {
+            Ok(client) => client,
+            Err(e) => {
+                checker.error(CONFIG_FILE, &entry, e.to_string());
+                continue;
+            }
+        };
+
+        let shows = match timeout(LIVE_TIMEOUT, client.library_shows()).await {
+            Ok(Ok(shows)) => shows,
+            Ok(Err(e)) => {
+                checker.error(
+                    CONFIG_FILE,
+                    &entry,
+                    format!("Could not fetch the show list from Kodi: {}", e),
+                );
+                continue;
+            }
+            Err(_) => {
+                checker.error(
+                    CONFIG_FILE,
+                    &entry,
+                    format!("Kodi did not answer within {:?}", LIVE_TIMEOUT),
+                );
+                continue;
+            }
+        };
+
+        let in_room = |message: String| {
+            if several_rooms {
+                format!("{} (room {})", message, name)
+            } else {
+                message
+            }
+        };
+
+        for (user, mapping) in &users {
+            for mapping_entry in &mapping.shows {
+                let label = format!("{} / {}", user, mapping_entry);
+                match mapping_entry {
+                    MappingEntry::Show(title) => {
+                        if let Err(e) = find_show(&shows, title, &Default::default()) {
+                            checker.error(MAPPINGS_FILE, &label, in_room(e.to_string()));
+                        }
+                    }
+                    MappingEntry::Detailed(show_entry) => {
+                        if let Err(e) = find_show(&shows, &show_entry.show, &show_entry.pin) {
+                            checker.error(MAPPINGS_FILE, &label, in_room(e.to_string()));
+                        }
+                    }
+                    MappingEntry::Movies { movies, .. } => {
+                        let params = GetMoviesParams {
+                            filter: movies.to_filter(),
+                            properties: vec!["title".to_string()],
+                            ..Default::default()
+                        };
+                        match timeout(LIVE_TIMEOUT, client.get_movies(&params)).await {
+                            Ok(Ok(found)) if found.is_empty() => checker.error(
+                                MAPPINGS_FILE,
+                                &label,
+                                in_room("No movies in the library match".to_string()),
+                            ),
+                            Ok(Ok(_)) => {}
+                            Ok(Err(e)) => checker.error(
+                                MAPPINGS_FILE,
+                                &label,
+                                in_room(format!("Could not fetch movies from Kodi: {}", e)),
+                            ),
+                            Err(_) => checker.error(
+                                MAPPINGS_FILE,
+                                &label,
+                                in_room(format!("Kodi did not answer within {:?}", LIVE_TIMEOUT)),
+                            ),
+                        }
+                    }
+                    MappingEntry::Channel {
+                        channel, channelid, ..
+                    } => {
+                        let params = GetChannelsParams::default();
+                        match timeout(LIVE_TIMEOUT, client.get_channels(&params)).await {
+                            Ok(Ok(channels)) => {
+                                if let Err(e) = find_channel(&channels, channel, *channelid) {
+                                    checker.error(MAPPINGS_FILE, &label, in_room(e.to_string()));
+                                }
+                            }
+                            Ok(Err(e)) => checker.error(
+                                MAPPINGS_FILE,
+                                &label,
+                                in_room(format!("Could not fetch channels from Kodi: {}", e)),
+                            ),
+                            Err(_) => checker.error(
+                                MAPPINGS_FILE,
+                                &label,
+                                in_room(format!("Kodi did not answer within {:?}", LIVE_TIMEOUT)),
+                            ),
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// Re-reading config.yml, show_mappings.yml, jukectl_channels.yml,
+// schedules.yml and budgets.yml while running, from `POST /api/config/reload`
+// or whenever one of them changes on disk. Files are validated first; on any error the old config stays.
+
+use koditool::{Config, RpcClient};
+use notify::{EventKind, RecursiveMode, Watcher};
+use rocket::serde::Serialize;
+use rocket::tokio::sync::mpsc;
+use rocket::tokio::time::{sleep, Duration};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::app_state::{
+    load_jukectl_channels, load_room_configs, load_show_mappings, load_yaml, AppState,
+    JukectlChannel, ShowMappings,
+};
+use crate::config_check::{self, ValidationReport};
+use crate::schedule::Schedule;
+use crate::screen_time::Budgets;
+
+const WATCHED_FILES: [&str; 5] = [
+    "config.yml",
+    "show_mappings.yml",
+    "jukectl_channels.yml",
+    "schedules.yml",
+    "budgets.yml",
+];
+
+// Editors save in several steps (write, rename, chmod); wait for them to settle
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Serialize)]
+pub struct ReloadReport {
+    // False if validation failed and the old config was kept
+    pub reloaded: bool,
+    // What changed, e.g. "show_mappings.yml" or "room kids room: Kodi connection"
+    pub changes: Vec<String>,
+    // Changes that only take effect after a restart, e.g. added rooms
+    pub restart_required: Vec<String>,
+    pub validation: ValidationReport,
+}
+
+struct LoadedConfig {
+    default_room: String,
+    rooms: BTreeMap<String, Config>,
+    show_mappings: ShowMappings,
+    jukectl_channels: Vec<JukectlChannel>,
+    schedule: Schedule,
+    budgets: Budgets,
+}
+
+fn load(config_dir: &str) -> Result<LoadedConfig, String> {
+    let (default_room, rooms) = load_room_configs(config_dir)?;
+    let show_mappings = load_show_mappings(&Path::new(config_dir).join("show_mappings.yml"))?;
+
+    // Optional, as at startup
+    let jukectl_path = Path::new(config_dir).join("jukectl_channels.yml");
+    let jukectl_channels = if jukectl_path.exists() {
+        load_jukectl_channels(&jukectl_path)?
+    } else {
+        Vec::new()
+    };
+
+    let schedule_path = Path::new(config_dir).join("schedules.yml");
+    let schedule = if schedule_path.exists() {
+        load_yaml(&schedule_path)?
+    } else {
+        Schedule::default()
+    };
+
+    let budgets_path = Path::new(config_dir).join("budgets.yml");
+    let budgets = if budgets_path.exists() {
+        load_yaml(&budgets_path)?
+    } else {
+        Budgets::default()
+    };
+
+    Ok(LoadedConfig {
+        default_room,
+        rooms,
+        show_mappings,
+        jukectl_channels,
+        schedule,
+        budgets,
+    })
+}
+
+pub async fn reload(app_state: &AppState) -> ReloadReport {
+    let report = apply(app_state).await;
+    log_report(&report);
+    report
+}
+
+async fn apply(app_state: &AppState) -> ReloadReport {
+    let loaded = load(&app_state.config_dir);
+    let validation = config_check::check_files(&app_state.config_dir);
+    let loaded = match loaded {
+        Ok(loaded) if validation.valid => loaded,
+        // A load error shows up in the validation report too
+        _ => {
+            return ReloadReport {
+                reloaded: false,
+                changes: Vec::new(),
+                restart_required: Vec::new(),
+                validation,
+            }
+        }
+    };
+
+    let mut changes = Vec::new();
+    let mut restart_required = Vec::new();
+
+    if loaded.default_room != app_state.default_room {
+        restart_required.push(format!("default room renamed to {}", loaded.default_room));
+    }
+    for name in app_state.rooms.keys() {
+        if !loaded.rooms.contains_key(name) && *name != app_state.default_room {
+            restart_required.push(format!("room {} removed", name));
+        }
+    }
+
+    // Build every new client before swapping anything in
+    let mut new_clients = Vec::new();
+    for (name, config) in loaded.rooms {
+        let Some(room) = app_state.room(&name) else {
+            restart_required.push(format!("room {} added", name));
+            continue;
+        };
+        if room.client().await.config == config {
+            continue;
+        }
+        match RpcClient::new(config) {
+            Ok(client) => new_clients.push((room, client)),
+            Err(e) => {
+                error!("Keeping the old Kodi client for room '{}': {}", name, e);
+            }
+        }
+    }
+
+    for (room, client) in new_clients {
+        // Drops the old client's library cache, which may be another Kodi's
+        *room.rpc_client.write().await = Arc::new(client);
+        changes.push(format!("room {}: Kodi connection", room.name));
+    }
+
+    {
+        let mut show_mappings = app_state.show_mappings.write().await;
+        if *show_mappings != loaded.show_mappings {
+            *show_mappings = loaded.show_mappings;
+            changes.push("show_mappings.yml".to_string());
+        }
+    }
+    {
+        let mut jukectl_channels = app_state.jukectl_channels.write().await;
+        if *jukectl_channels != loaded.jukectl_channels {
+            *jukectl_channels = loaded.jukectl_channels;
+            changes.push("jukectl_channels.yml".to_string());
+        }
+    }
+    {
+        let mut schedule = app_state.schedule.write().await;
+        if *schedule != loaded.schedule {
+            *schedule = loaded.schedule;
+            changes.push("schedules.yml".to_string());
+        }
+    }
+    {
+        let mut budgets = app_state.budgets.write().await;
+        if *budgets != loaded.budgets {
+            *budgets = loaded.budgets;
+            changes.push("budgets.yml".to_string());
+        }
+    }
+
+    ReloadReport {
+        reloaded: true,
+        changes,
+        restart_required,
+        validation,
+    }
+}
+
+fn log_report(report: &ReloadReport) {
+    if !report.reloaded {
+        error!("Config has errors, keeping the previous one:");
+        for diagnostic in &report.validation.diagnostics {
+            error!("  {}", diagnostic);
+        }
+        return;
+    }
+
+    if report.changes.is_empty() {
+        debug!("Config reloaded, nothing changed");
+    } else {
+        info!("Config reloaded: {}", report.changes.join(", "));
+    }
+    for change in &report.restart_required {
+        warn!("Restart to apply config change: {}", change);
+    }
+}
+
+// Reload whenever one of the config files changes. Runs until shutdown;
+// without a working watcher only the reload endpoint is left.
+pub fn start_config_watcher(app_state: AppState) {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let Ok(event) = event else {
+            return;
+        };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        let touches_config = event.paths.iter().any(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| WATCHED_FILES.contains(&name))
+        });
+        if touches_config {
+            let _ = tx.send(());
+        }
+    });
+
+    // Watch the directory rather than the files, since editors and
+    // atomic writes replace files instead of modifying them
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!(
+                "Config file watcher unavailable, use POST /api/config/reload: {}",
+                e
+            );
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(
+        Path::new(&app_state.config_dir),
+        RecursiveMode::NonRecursive,
+    ) {
+        warn!(
+            "Can't watch {} for config changes, use POST /api/config/reload: {}",
+            app_state.config_dir, e
+        );
+        return;
+    }
+    info!("Watching {} for config changes", app_state.config_dir);
+
+    rocket::tokio::spawn(async move {
+        // Dropping the watcher stops the events
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            sleep(DEBOUNCE).await;
+            while rx.try_recv().is_ok() {}
+
+            reload(&app_state).await;
+        }
+    });
+}
@@ -0,0 +1,411 @@
+// What TV mode has played, one JSON object per line in watch_history.jsonl
+// under CONFIG_DIR. Lines are only ever appended: an entry is written once
+// its item stops, and until then is held in memory for its room.
+
+use chrono::{DateTime, Local, NaiveDate};
+use koditool::NowPlaying;
+use rocket::serde::{Deserialize, Serialize};
+use rocket::tokio::sync::RwLock;
+use rocket::tokio::task;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const HISTORY_FILE: &str = "watch_history.jsonl";
+
+// An item that stops short of this while TV mode is still on was skipped
+const WATCHED_PERCENTAGE: f64 = 90.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub user: String,
+    pub room: String,
+    // Display label of the mapping entry, e.g. "Bluey"
+    pub entry: String,
+    // What Kodi reported once it was playing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub show_title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episode: Option<i32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub episodeid: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub movieid: Option<u64>,
+    pub started_at: u64,
+    // None while it's still playing
+    pub ended_at: Option<u64>,
+    // Playing time, not counting pauses
+    #[serde(default)]
+    pub watched_seconds: u64,
+    // How far in it last was, 0-100
+    #[serde(default)]
+    pub percentage: f64,
+    #[serde(default)]
+    pub skipped: bool,
+    // Tells the item apart from whatever plays next
+    #[serde(skip)]
+    file: Option<String>,
+    // Its length in seconds, to move `percentage` along between looks
+    #[serde(skip)]
+    total_seconds: u64,
+}
+
+fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// The local day a timestamp falls on
+pub fn local_date(timestamp: u64) -> NaiveDate {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .with_timezone(&Local)
+        .date_naive()
+}
+
+impl HistoryEntry {
+    pub fn new(
+        user: &str,
+        room: &str,
+        entry: &str,
+        episodeid: Option<u64>,
+        movieid: Option<u64>,
+    ) -> Self {
+        HistoryEntry {
+            user: user.to_string(),
+            room: room.to_string(),
+            entry: entry.to_string(),
+            title: None,
+            show_title: None,
+            season: None,
+            episode: None,
+            episodeid,
+            movieid,
+            started_at: now_timestamp(),
+            ended_at: None,
+            watched_seconds: 0,
+            percentage: 0.0,
+            skipped: false,
+            file: None,
+            total_seconds: 0,
+        }
+    }
+
+    // Kodi's show title, or the mapping entry for movies and items Kodi
+    // never described
+    pub fn show(&self) -> &str {
+        self.show_title.as_deref().unwrap_or(&self.entry)
+    }
+
+    // "Bluey S01E02 - Pilot", just the title for movies, or the mapping
+    // entry before Kodi has said anything
+    pub fn describe(&self) -> String {
+        match (&self.title, &self.show_title, self.season, self.episode) {
+            (Some(title), Some(show), Some(season), Some(episode)) => {
+                format!("{} S{:02}E{:02} - {}", show, season, episode, title)
+            }
+            (Some(title), ..) => title.clone(),
+            (None, ..) => self.entry.clone(),
+        }
+    }
+}
+
+// Which entries /api/history and /api/stats cover; dates are local and
+// inclusive
+#[derive(Debug, Default)]
+pub struct HistoryFilter {
+    pub user: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl HistoryFilter {
+    fn matches(&self, entry: &HistoryEntry) -> bool {
+        let day = local_date(entry.started_at);
+        self.user.as_ref().is_none_or(|user| *user == entry.user)
+            && self.from.is_none_or(|from| day >= from)
+            && self.to.is_none_or(|to| day <= to)
+    }
+}
+
+#[derive(Debug)]
+pub struct WatchHistory {
+    path: PathBuf,
+    // Room name to the entry for what TV mode is playing there
+    playing: HashMap<String, HistoryEntry>,
+}
+
+impl WatchHistory {
+    pub fn new(config_dir: &str) -> Self {
+        WatchHistory {
+            path: Path::new(config_dir).join(HISTORY_FILE),
+            playing: HashMap::new(),
+        }
+    }
+
+    // TV mode started something in the entry's room. Returns the entry this
+    // closed, if something else was still open there.
+    pub fn start(&mut self, entry: HistoryEntry) -> Option<HistoryEntry> {
+        let closed = self.finish(&entry.room, true);
+        self.playing.insert(entry.room.clone(), entry);
+        closed
+    }
+
+    // The seconds watched in `room` since the last call, and what Kodi says
+    // is playing there if it was asked. Another file means the entry's item
+    // was moved on from. Between looks, `percentage` moves along with the
+    // seconds watched. Returns the entry if that closed it.
+    pub fn progress(
+        &mut self,
+        room: &str,
+        now_playing: Option<&NowPlaying>,
+        watched_seconds: u64,
+    ) -> Option<HistoryEntry> {
+        let entry = self.playing.get_mut(room)?;
+        entry.watched_seconds += watched_seconds;
+
+        let Some(now_playing) = now_playing else {
+            if entry.total_seconds > 0 {
+                let advanced = watched_seconds as f64 * 100.0 / entry.total_seconds as f64;
+                entry.percentage = (entry.percentage + advanced).min(100.0);
+            }
+            return None;
+        };
+        if entry
+            .file
+            .as_ref()
+            .is_some_and(|file| *file != now_playing.file)
+        {
+            return self.finish(room, true);
+        }
+
+        entry.file = Some(now_playing.file.clone());
+        entry.title = Some(now_playing.title.clone());
+        entry.show_title = now_playing.show_title.clone();
+        entry.season = now_playing.season;
+        entry.episode = now_playing.episode;
+        entry.percentage = now_playing.percentage;
+        entry.total_seconds = now_playing.total.as_seconds();
+        None
+    }
+
+    // The item in `room` has stopped. While TV mode is `still_on`, stopping
+    // short of the end counts as skipping it. Returns the closed entry, which
+    // `update` then appends to the file.
+    pub fn finish(&mut self, room: &str, still_on: bool) -> Option<HistoryEntry> {
+        let entry = self.playing.remove(room)?;
+        let skipped = still_on && entry.percentage < WATCHED_PERCENTAGE;
+        Some(close(entry, skipped))
+    }
+
+    // Someone asked for something else, however far in it was. Returns the
+    // closed entry, as `finish` does.
+    pub fn skip(&mut self, room: &str) -> Option<HistoryEntry> {
+        self.playing.remove(room).map(|entry| close(entry, true))
+    }
+
+    // What TV mode is playing in `room`, if it started anything
+    pub fn playing(&self, room: &str) -> Option<&HistoryEntry> {
+        self.playing.get(room)
+    }
+
+    // The file and what's playing now, so entries can be read once the lock
+    // is released
+    pub fn reader(&self) -> HistoryReader {
+        let mut playing: Vec<HistoryEntry> = self.playing.values().cloned().collect();
+        playing.sort_by_key(|entry| entry.started_at);
+        HistoryReader {
+            path: self.path.clone(),
+            playing,
+        }
+    }
+}
+
+fn close(mut entry: HistoryEntry, skipped: bool) -> HistoryEntry {
+    entry.ended_at = Some(now_timestamp());
+    entry.skipped = skipped;
+    entry
+}
+
+// Applies `change` under the lock, then appends the entry it closed, if any,
+// off the async runtime once the lock is released
+pub async fn update(
+    history: &RwLock<WatchHistory>,
+    change: impl FnOnce(&mut WatchHistory) -> Option<HistoryEntry>,
+) {
+    let (path, closed) = {
+        let mut history = history.write().await;
+        let closed = change(&mut history);
+        (history.path.clone(), closed)
+    };
+    let Some(entry) = closed else {
+        return;
+    };
+    if let Err(e) = task::spawn_blocking(move || append(&path, &entry)).await {
+        error!("Failed to append to watch history: {}", e);
+    }
+}
+
+fn append(path: &Path, entry: &HistoryEntry) {
+    let line = match serde_json::to_string(entry) {
+        Ok(line) => line,
+        Err(e) => {
+            error!("Failed to serialize watch history entry: {}", e);
+            return;
+        }
+    };
+
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        error!("Failed to append to watch history {:?}: {}", path, e);
+    }
+}
+
+#[derive(Debug)]
+pub struct HistoryReader {
+    path: PathBuf,
+    playing: Vec<HistoryEntry>,
+}
+
+impl HistoryReader {
+    // Entries matching `filter`, oldest first, with what's playing now last.
+    // This reads the file, so async code should use `entries` instead.
+    pub fn entries(self, filter: &HistoryFilter) -> Vec<HistoryEntry> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                error!("Failed to read watch history {:?}: {}", self.path, e);
+                String::new()
+            }
+        };
+
+        // Lines are appended as items end, so once one ended before `from`
+        // every line above it did too
+        let mut entries = Vec::new();
+        for line in content.lines().rev() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: HistoryEntry = match serde_json::from_str(line) {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Skipping bad line in {:?}: {}", self.path, e);
+                    continue;
+                }
+            };
+            let ended = entry.ended_at.unwrap_or(entry.started_at);
+            if filter.from.is_some_and(|from| local_date(ended) < from) {
+                break;
+            }
+            entries.push(entry);
+        }
+        entries.reverse();
+
+        entries.extend(self.playing);
+        entries.retain(|entry| filter.matches(entry));
+        entries
+    }
+}
+
+// `HistoryReader::entries` off the async runtime, without holding the lock
+// while the file is read
+pub async fn entries(history: &RwLock<WatchHistory>, filter: HistoryFilter) -> Vec<HistoryEntry> {
+    let reader = history.read().await.reader();
+    match task::spawn_blocking(move || reader.entries(&filter)).await {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to read watch history: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ShowStats {
+    pub show: String,
+    pub plays: usize,
+    pub skipped: usize,
+    pub hours: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryStats {
+    // Most watched first, by playing time
+    pub top_shows: Vec<ShowStats>,
+    // User to ISO week ("2026-W42") to hours watched
+    pub hours_per_user_per_week: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+// Shows beyond this many are left out of top_shows
+const TOP_SHOWS: usize = 10;
+
+fn hours(seconds: u64) -> f64 {
+    (seconds as f64 / 36.0).round() / 100.0
+}
+
+#[derive(Default)]
+struct ShowTotals {
+    plays: usize,
+    skipped: usize,
+    seconds: u64,
+}
+
+pub fn stats(entries: &[HistoryEntry]) -> HistoryStats {
+    let mut shows: HashMap<&str, ShowTotals> = HashMap::new();
+    let mut weeks: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+
+    for entry in entries {
+        let show = shows.entry(entry.show()).or_default();
+        show.plays += 1;
+        show.skipped += entry.skipped as usize;
+        show.seconds += entry.watched_seconds;
+
+        let week = local_date(entry.started_at).format("%G-W%V").to_string();
+        *weeks
+            .entry(entry.user.clone())
+            .or_default()
+            .entry(week)
+            .or_default() += entry.watched_seconds;
+    }
+
+    let mut top_shows: Vec<(&str, ShowTotals)> = shows.into_iter().collect();
+    top_shows.sort_by(|(a, a_totals), (b, b_totals)| {
+        b_totals.seconds.cmp(&a_totals.seconds).then(a.cmp(b))
+    });
+    top_shows.truncate(TOP_SHOWS);
+
+    HistoryStats {
+        top_shows: top_shows
+            .into_iter()
+            .map(|(show, totals)| ShowStats {
+                show: show.to_string(),
+                plays: totals.plays,
+                skipped: totals.skipped,
+                hours: hours(totals.seconds),
+            })
+            .collect(),
+        hours_per_user_per_week: weeks
+            .into_iter()
+            .map(|(user, weeks)| {
+                let weeks = weeks
+                    .into_iter()
+                    .map(|(week, seconds)| (week, hours(seconds)))
+                    .collect();
+                (user, weeks)
+            })
+            .collect(),
+    }
+}
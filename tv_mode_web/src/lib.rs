@@ -2,11 +2,17 @@
 extern crate rocket;
 
 pub mod app_state;
+pub mod config_check;
+pub mod config_reload;
+pub mod history;
 pub mod routes;
+pub mod schedule;
+pub mod screen_time;
 pub mod scheduler;
 
 use rocket_dyn_templates::Template;
 use std::env;
+use crate::config_reload::start_config_watcher;
 use crate::scheduler::start_scheduler;
 
 pub fn init_logging() {
@@ -36,6 +42,11 @@ pub fn build_rocket() -> rocket::Rocket<rocket::Build> {
         }
     };
 
+    // Mistakes that parse fine but will bite the scheduler later
+    for diagnostic in config_check::check_files(&app_state.config_dir).diagnostics {
+        warn!("{}", diagnostic);
+    }
+
     info!("Starting Rocket web server...");
 
     // Build the rocket instance with routes and scheduler
@@ -47,6 +58,7 @@ pub fn build_rocket() -> rocket::Rocket<rocket::Build> {
             "Initialize Scheduler",
             |_rocket| {
                 Box::pin(async move {
+                    start_config_watcher(app_state.clone());
                     start_scheduler(app_state).await;
                 })
             },
@@ -1,9 +1,44 @@
-#[macro_use]
-extern crate rocket;
+use std::env;
+use tv_mode_web::{build_rocket, config_check, init_logging};
 
-use tv_mode_web::build_rocket;
+// `--check-config [--live]` validates the files in CONFIG_DIR and exits
+// non-zero if there are errors, instead of starting the server
+async fn check_config(live: bool) -> i32 {
+    init_logging();
+    let config_dir = env::var("CONFIG_DIR").unwrap_or_else(|_| ".".to_string());
+    let report = config_check::validate(&config_dir, live).await;
 
-#[launch]
-fn rocket() -> _ {
-    build_rocket()
+    for diagnostic in &report.diagnostics {
+        println!("{}", diagnostic);
+    }
+    println!(
+        "{} error(s), {} warning(s){}",
+        report.errors,
+        report.warnings,
+        if live {
+            ""
+        } else {
+            " (pass --live to check titles against Kodi)"
+        }
+    );
+
+    if report.valid {
+        0
+    } else {
+        1
+    }
+}
+
+#[rocket::main]
+async fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--check-config") {
+        let live = args.iter().any(|arg| arg == "--live");
+        std::process::exit(check_config(live).await);
+    }
+
+    if let Err(e) = build_rocket().launch().await {
+        eprintln!("Rocket failed to launch: {}", e);
+        std::process::exit(1);
+    }
 }
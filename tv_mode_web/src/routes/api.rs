@@ -7,10 +7,29 @@ use rocket::tokio::time::Duration;
 use rocket::Route;
 use rocket::State;
 
+use chrono::{Days, Local, NaiveDate};
 use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::SystemTime;
+
+use koditool::{
+    normalize_title, KodiError, MappingEntry, NowPlaying, SeekTarget, Toggle, UserMapping,
+    VolumeChange,
+};
 
 use crate::app_state::AppState;
+use crate::app_state::ExcludedItem;
+use crate::app_state::Exclusions;
+use crate::app_state::Room;
+use crate::app_state::ShowMappings;
 use crate::app_state::TVModeStatus;
+use crate::app_state::EXCLUSIONS_FILE;
+use crate::app_state::{SleepAction, SleepTimer, SleepTimerMode};
+use crate::config_check::{self, ValidationReport};
+use crate::config_reload::{self, ReloadReport};
+use crate::history::{self, HistoryEntry, HistoryFilter, HistoryStats};
+use crate::schedule::Schedule;
+use crate::screen_time::ScreenTimeReport;
 
 type ApiResponse<T> = Result<Json<T>, Custom<Json<StatusResponse>>>;
 
@@ -20,6 +39,8 @@ const RPC_TIMEOUT: Duration = Duration::from_secs(5);
 #[derive(Debug, Serialize)]
 pub struct UsersResponse {
     show_mappings: BTreeMap<String, Vec<String>>,
+    // Watched today and this week, and what's left of any budget
+    screen_time: BTreeMap<String, ScreenTimeReport>,
 }
 
 #[derive(Debug, Serialize)]
@@ -29,16 +50,141 @@ pub struct StatusResponse {
     tv_mode: Option<TVModeStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
     error_details: Option<String>,
+    // Failure class from KodiError::kind() so the UI can tell "offline" from "misconfigured"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    now_playing: Option<NowPlaying>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomsResponse {
+    default_room: String,
+    rooms: Vec<RoomSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoomSummary {
+    name: String,
+    tv_mode: TVModeStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VolumeResponse {
+    volume: u8,
+    muted: bool,
+}
+
+// Either an absolute `volume` (0-100) or a `step` of "up"/"down"
+#[derive(Debug, Deserialize)]
+pub struct VolumeRequest {
+    // Wider than u8 so out-of-range values get our 400 rather than a 422
+    volume: Option<i64>,
+    step: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
+pub struct MuteRequest {
+    // Omitted means toggle
+    muted: Option<bool>,
+}
+
+// Sleep timer fields are optional; minutes win over the older whole hours,
+// and the timer defaults to 2 hours with the stop action
+#[derive(Debug, Deserialize, Default)]
 pub struct PlayRequest {
+    sleep_timer_minutes: Option<u32>,
     sleep_timer_hours: Option<u32>,
+    sleep_timer_mode: Option<SleepTimerMode>,
+    sleep_action: Option<SleepAction>,
 }
 
+// Either `minutes` (or the older `hours`), or mode "end_of_episode"
 #[derive(Debug, Deserialize)]
 pub struct SleepTimerRequest {
-    hours: u32,
+    minutes: Option<u32>,
+    hours: Option<u32>,
+    #[serde(default)]
+    mode: SleepTimerMode,
+    #[serde(default)]
+    action: SleepAction,
+}
+
+// Longest sleep timer we accept, in minutes
+const MAX_SLEEP_MINUTES: u32 = 24 * 60;
+
+impl SleepTimerRequest {
+    // The timer this asks for, or None for "no timer" (zero minutes)
+    fn to_timer(&self) -> Result<Option<SleepTimer>, String> {
+        let mut timer = SleepTimer::new();
+        if self.mode == SleepTimerMode::EndOfEpisode {
+            timer.start_until_end_of_episode(self.action);
+            return Ok(Some(timer));
+        }
+
+        let minutes = match (self.minutes, self.hours) {
+            (Some(minutes), _) => minutes,
+            (None, Some(hours)) => hours.saturating_mul(60),
+            (None, None) => {
+                return Err("Give the sleep timer `minutes` or mode \"end_of_episode\"".to_string())
+            }
+        };
+        if minutes > MAX_SLEEP_MINUTES {
+            return Err(format!(
+                "Invalid sleep timer duration. Must be at most {} minutes",
+                MAX_SLEEP_MINUTES
+            ));
+        }
+        if minutes == 0 {
+            return Ok(None);
+        }
+
+        timer.start(minutes, self.action);
+        Ok(Some(timer))
+    }
+}
+
+impl From<&PlayRequest> for SleepTimerRequest {
+    fn from(request: &PlayRequest) -> Self {
+        let defaulted =
+            request.sleep_timer_minutes.is_none() && request.sleep_timer_hours.is_none();
+        SleepTimerRequest {
+            minutes: request.sleep_timer_minutes.or(defaulted.then_some(120)),
+            hours: request.sleep_timer_hours,
+            mode: request.sleep_timer_mode.unwrap_or_default(),
+            action: request.sleep_action.unwrap_or_default(),
+        }
+    }
+}
+
+// A new user needs at least one show
+#[derive(Debug, Deserialize)]
+pub struct NewUserRequest {
+    user: String,
+    shows: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddShowRequest {
+    show: String,
+}
+
+// The schedule as in schedules.yml, plus what it means right now
+#[derive(Debug, Serialize)]
+pub struct ScheduleResponse {
+    #[serde(flatten)]
+    schedule: Schedule,
+    // "HH:MM" while quiet hours are on
+    quiet_until: Option<String>,
+    // Room name to the slot that is on there now
+    active_slots: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LibraryShow {
+    tvshowid: u64,
+    title: String,
+    year: u32,
 }
 
 impl StatusResponse {
@@ -48,6 +194,8 @@ impl StatusResponse {
             message,
             tv_mode,
             error_details: None,
+            error_kind: None,
+            now_playing: None,
         }
     }
 
@@ -61,15 +209,38 @@ impl StatusResponse {
             message,
             tv_mode,
             error_details,
+            error_kind: None,
+            now_playing: None,
         }
     }
 
-    fn media_active(tv_mode: TVModeStatus) -> Self {
+    fn kodi_error(error: &KodiError, tv_mode: Option<TVModeStatus>) -> Self {
+        let message = match error {
+            KodiError::Unauthorized => "Media server rejected our credentials",
+            KodiError::Rpc { .. } | KodiError::MissingField(_) | KodiError::Decode(_) => {
+                "Media server returned an unexpected response"
+            }
+            _ => "Unable to connect to media server",
+        };
+
+        Self {
+            status: "error".to_string(),
+            message: message.to_string(),
+            tv_mode,
+            error_details: Some(error.to_string()),
+            error_kind: Some(error.kind().to_string()),
+            now_playing: None,
+        }
+    }
+
+    fn media_active(tv_mode: TVModeStatus, now_playing: NowPlaying) -> Self {
         Self {
             status: "active".to_string(),
             message: "Media is currently playing".to_string(),
             tv_mode: Some(tv_mode),
             error_details: None,
+            error_kind: None,
+            now_playing: Some(now_playing),
         }
     }
 
@@ -79,25 +250,306 @@ impl StatusResponse {
             message: "No media is currently playing".to_string(),
             tv_mode: Some(tv_mode),
             error_details: None,
+            error_kind: None,
+            now_playing: None,
         }
     }
 }
 
+// Run an RPC call under RPC_TIMEOUT
+async fn with_rpc_timeout<T>(
+    call: impl Future<Output = Result<T, KodiError>>,
+) -> Result<T, KodiError> {
+    match rocket::tokio::time::timeout(RPC_TIMEOUT, call).await {
+        Ok(result) => result,
+        Err(_) => Err(KodiError::Timeout),
+    }
+}
+
+// For endpoints that exist only to talk to Kodi: 503 while it's unreachable,
+// 502 when it answered but rejected us
+fn kodi_failure(error: KodiError) -> Custom<Json<StatusResponse>> {
+    warn!("Media server request failed: {}", error);
+    let status = if error.is_transient() {
+        Status::ServiceUnavailable
+    } else {
+        Status::BadGateway
+    };
+    Custom(status, Json(StatusResponse::kodi_error(&error, None)))
+}
+
+async fn users_response(app_state: &AppState, show_mappings: &ShowMappings) -> UsersResponse {
+    let users = show_mappings.sorted_shows();
+    let mut screen_time = BTreeMap::new();
+    for user in users.keys() {
+        screen_time.insert(user.clone(), app_state.screen_time_report(user).await);
+    }
+
+    UsersResponse {
+        show_mappings: users,
+        screen_time,
+    }
+}
+
 #[get("/api/users")]
 pub async fn get_users(app_state: &State<AppState>) -> ApiResponse<UsersResponse> {
-    let users = app_state.show_mappings.read().await.sorted_shows();
+    let show_mappings = app_state.show_mappings.read().await;
+    Ok(Json(users_response(app_state, &show_mappings).await))
+}
 
-    Ok(Json(UsersResponse {
-        show_mappings: users,
-    }))
+// Apply `edit` to a copy of the show mappings, write that to
+// show_mappings.yml, and only then swap it in. Answers with the users as
+// GET /api/users lists them.
+async fn edit_show_mappings(
+    app_state: &AppState,
+    edit: impl FnOnce(&mut ShowMappings) -> Result<(), (Status, String)>,
+) -> ApiResponse<UsersResponse> {
+    let mut show_mappings = app_state.show_mappings.write().await;
+    let mut edited = show_mappings.clone();
+    edit(&mut edited).map_err(|(status, message)| {
+        Custom(status, Json(StatusResponse::error(message, None, None)))
+    })?;
+    edited.sort_entries();
+
+    if let Err(e) = app_state.write_show_mappings(&edited) {
+        error!("{}", e);
+        return Err(Custom(
+            Status::InternalServerError,
+            Json(StatusResponse::error(
+                "Failed to save show mappings".to_string(),
+                None,
+                Some(e),
+            )),
+        ));
+    }
+    *show_mappings = edited;
+
+    Ok(Json(users_response(app_state, &show_mappings).await))
 }
 
-#[post("/api/play/<user>", data = "<request>")]
-pub async fn play_random_show(
+fn unknown_user(user: &str) -> (Status, String) {
+    (
+        Status::NotFound,
+        format!("User '{}' not found in show mappings", user),
+    )
+}
+
+#[post("/api/users", data = "<request>")]
+pub async fn add_user(
+    app_state: &State<AppState>,
+    request: Json<NewUserRequest>,
+) -> ApiResponse<UsersResponse> {
+    let user = request.user.trim().to_string();
+    let mut shows: Vec<MappingEntry> = Vec::new();
+    for title in request.shows.iter().map(|title| title.trim()) {
+        let entry = MappingEntry::Show(title.to_string());
+        if !title.is_empty() && !shows.contains(&entry) {
+            shows.push(entry);
+        }
+    }
+
+    edit_show_mappings(app_state, |mappings| {
+        if user.is_empty() {
+            return Err((Status::BadRequest, "User name is empty".to_string()));
+        }
+        // The scheduler can't do anything with a user without shows
+        if shows.is_empty() {
+            return Err((
+                Status::BadRequest,
+                format!("User '{}' needs at least one show", user),
+            ));
+        }
+        if mappings.shows.contains_key(&user) {
+            return Err((Status::Conflict, format!("User '{}' already exists", user)));
+        }
+
+        info!("Adding user '{}' with {} show(s)", user, shows.len());
+        mappings.shows.insert(
+            user.clone(),
+            UserMapping {
+                shows,
+                ..Default::default()
+            },
+        );
+        Ok(())
+    })
+    .await
+}
+
+#[delete("/api/users/<user>")]
+pub async fn remove_user(app_state: &State<AppState>, user: &str) -> ApiResponse<UsersResponse> {
+    // The scheduler would fail every tick on a user it can't find
+    for room in app_state.rooms.values() {
+        let tv_mode = room.tv_mode.read().await;
+        if tv_mode.active && tv_mode.user.as_deref() == Some(user) {
+            return Err(Custom(
+                Status::Conflict,
+                Json(StatusResponse::error(
+                    format!(
+                        "TV mode is running for '{}' in room '{}'; stop it first",
+                        user, room.name
+                    ),
+                    Some(tv_mode.clone()),
+                    None,
+                )),
+            ));
+        }
+    }
+
+    edit_show_mappings(app_state, |mappings| {
+        mappings.shows.remove(user).ok_or_else(|| unknown_user(user))?;
+        info!("Removed user '{}'", user);
+        Ok(())
+    })
+    .await
+}
+
+#[post("/api/users/<user>/shows", data = "<request>")]
+pub async fn add_user_show(
+    app_state: &State<AppState>,
+    user: &str,
+    request: Json<AddShowRequest>,
+) -> ApiResponse<UsersResponse> {
+    let show = request.show.trim().to_string();
+
+    edit_show_mappings(app_state, |mappings| {
+        if show.is_empty() {
+            return Err((Status::BadRequest, "Show title is empty".to_string()));
+        }
+        let mapping = mappings.shows.get_mut(user).ok_or_else(|| unknown_user(user))?;
+        if mapping.shows.iter().any(|entry| entry.to_string() == show) {
+            return Err((
+                Status::Conflict,
+                format!("'{}' is already one of {}'s shows", show, user),
+            ));
+        }
+
+        info!("Adding '{}' to {}'s shows", show, user);
+        mapping.shows.push(MappingEntry::Show(show.clone()));
+        Ok(())
+    })
+    .await
+}
+
+// `show` is the entry's label as GET /api/users lists it, so movie entries
+// can be removed too
+#[delete("/api/users/<user>/shows/<show>")]
+pub async fn remove_user_show(
+    app_state: &State<AppState>,
+    user: &str,
+    show: &str,
+) -> ApiResponse<UsersResponse> {
+    edit_show_mappings(app_state, |mappings| {
+        let mapping = mappings.shows.get_mut(user).ok_or_else(|| unknown_user(user))?;
+        let index = mapping
+            .shows
+            .iter()
+            .position(|entry| entry.to_string() == show)
+            .ok_or_else(|| {
+                (
+                    Status::NotFound,
+                    format!("'{}' is not one of {}'s shows", show, user),
+                )
+            })?;
+        if mapping.shows.len() == 1 {
+            return Err((
+                Status::BadRequest,
+                format!("'{}' is {}'s only show; remove the user instead", show, user),
+            ));
+        }
+
+        info!("Removing '{}' from {}'s shows", show, user);
+        mapping.shows.remove(index);
+        Ok(())
+    })
+    .await
+}
+
+// Kodi's shows for the admin page's picker, narrowed by `query` (matched
+// loosely, like show mappings are) and from the default room unless `room`
+#[get("/api/library/shows?<query>&<room>")]
+pub async fn search_library_shows(
     app_state: &State<AppState>,
+    query: Option<&str>,
+    room: Option<&str>,
+) -> ApiResponse<Vec<LibraryShow>> {
+    let room = match room {
+        Some(name) => app_state.room(name).ok_or_else(|| unknown_room(name))?,
+        None => app_state.default_room(),
+    };
+    let client = room.client().await;
+    let shows = with_rpc_timeout(client.library_shows())
+        .await
+        .map_err(kodi_failure)?;
+
+    let wanted = normalize_title(query.unwrap_or(""));
+    let mut matches: Vec<LibraryShow> = shows
+        .into_iter()
+        .filter(|show| {
+            [&show.title, &show.originaltitle]
+                .iter()
+                .any(|title| normalize_title(title).contains(&wanted))
+        })
+        .map(|show| LibraryShow {
+            tvshowid: show.tvshowid,
+            title: show.title,
+            year: show.year,
+        })
+        .collect();
+    matches.sort_by_key(|show| show.title.to_lowercase());
+
+    Ok(Json(matches))
+}
+
+// Manual starts are refused while quiet hours are on
+async fn refuse_in_quiet_hours(app_state: &AppState) -> Result<(), Custom<Json<StatusResponse>>> {
+    let now = Local::now().naive_local();
+    let Some(until) = app_state.schedule.read().await.quiet_until(now) else {
+        return Ok(());
+    };
+
+    warn!("Refusing to start TV mode during quiet hours");
+    Err(Custom(
+        Status::Forbidden,
+        Json(StatusResponse::error(
+            format!("Quiet hours until {}", until.format("%H:%M")),
+            None,
+            Some("Quiet hours are set in schedules.yml or via /api/schedule".to_string()),
+        )),
+    ))
+}
+
+// Manual starts are refused once the user's screen time is used up
+async fn refuse_when_budget_used_up(
+    app_state: &AppState,
+    user: &str,
+) -> Result<(), Custom<Json<StatusResponse>>> {
+    let Some(period) = app_state.screen_time_report(user).await.used_up() else {
+        return Ok(());
+    };
+
+    warn!(
+        "Refusing to start TV mode for '{}', screen time is used up",
+        user
+    );
+    Err(Custom(
+        Status::Forbidden,
+        Json(StatusResponse::error(
+            format!("Screen time for '{}' is used up for {}", user, period),
+            None,
+            Some("Budgets are set in budgets.yml".to_string()),
+        )),
+    ))
+}
+
+async fn play_in_room(
+    app_state: &AppState,
+    room: &Room,
     user: &str,
     request: Option<Json<PlayRequest>>,
 ) -> ApiResponse<StatusResponse> {
+    refuse_in_quiet_hours(app_state).await?;
+
     // Validate user exists in mappings first
     let shows = app_state.show_mappings.read().await.sorted_shows();
     if !shows.contains_key(user) {
@@ -125,64 +577,54 @@ pub async fn play_random_show(
         ));
     }
 
-    // Extract sleep timer duration from request, default to 2 hours
-    let sleep_timer_hours = if let Some(req) = request {
-        req.sleep_timer_hours.unwrap_or(2)
-    } else {
-        2
-    };
+    refuse_when_budget_used_up(app_state, user).await?;
 
-    // Validate sleep timer hours (allow 0 for no timer)
-    if ![0, 1, 2, 4, 8, 12].contains(&sleep_timer_hours) {
-        return Err(Custom(
-            Status::BadRequest,
-            Json(StatusResponse::error(
-                "Invalid sleep timer duration. Must be 0 (no timer), 1, 2, 4, 8, or 12 hours".to_string(),
-                None,
-                None,
-            )),
-        ));
-    }
+    // Sleep timer from the request, 2 hours with the stop action by default
+    let request = request.map(Json::into_inner).unwrap_or_default();
+    let sleep_timer = SleepTimerRequest::from(&request)
+        .to_timer()
+        .map_err(|message| {
+            Custom(
+                Status::BadRequest,
+                Json(StatusResponse::error(message, None, None)),
+            )
+        })?;
 
+    // A fade-out from an earlier timer would stop what TV mode starts
+    room.cancel_fade_out().await;
     {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
         tv_mode.active = true;
         tv_mode.user = Some(user.to_string());
 
-        if sleep_timer_hours == 0 {
-            // No sleep timer
-            tv_mode.sleep_timer.stop();
-        } else {
+        match &sleep_timer {
             // With sleep timer
-            tv_mode.sleep_timer.start(sleep_timer_hours);
+            Some(timer) => tv_mode.sleep_timer = timer.clone(),
+            // No sleep timer
+            None => tv_mode.sleep_timer.stop(),
         }
     }
 
     app_state.save_to_disk().await;
-    
-    let tv_mode = app_state.tv_mode.read().await;
-    if sleep_timer_hours == 0 {
-        info!("Enabling TV mode for user: {} with no sleep timer", user);
-        Ok(Json(StatusResponse::success(
-            format!(
-                "Enabled TV mode for user '{}' with {} shows available (no sleep timer)",
-                user,
-                user_shows.len()
-            ),
-            Some(tv_mode.clone()),
-        )))
-    } else {
-        info!("Enabling TV mode for user: {} with {}h sleep timer", user, sleep_timer_hours);
-        Ok(Json(StatusResponse::success(
-            format!(
-                "Enabled TV mode for user '{}' with {} shows available ({}h sleep timer)",
-                user,
-                user_shows.len(),
-                sleep_timer_hours
-            ),
-            Some(tv_mode.clone()),
-        )))
-    }
+
+    let tv_mode = room.tv_mode.read().await;
+    let timer_text = match &sleep_timer {
+        Some(timer) => format!("{} sleep timer, then {}", timer.describe(), timer.action),
+        None => "no sleep timer".to_string(),
+    };
+    info!(
+        "Enabling TV mode for user: {} in room '{}' with {}",
+        user, room.name, timer_text
+    );
+    Ok(Json(StatusResponse::success(
+        format!(
+            "Enabled TV mode for user '{}' with {} shows available ({})",
+            user,
+            user_shows.len(),
+            timer_text
+        ),
+        Some(tv_mode.clone()),
+    )))
 }
 
 // Legacy endpoint without sleep timer data for backward compatibility
@@ -191,6 +633,8 @@ pub async fn play_random_show_legacy(
     app_state: &State<AppState>,
     user: &str,
 ) -> ApiResponse<StatusResponse> {
+    refuse_in_quiet_hours(app_state).await?;
+
     // Validate user exists in mappings first
     let shows = app_state.show_mappings.read().await.sorted_shows();
     if !shows.contains_key(user) {
@@ -218,10 +662,14 @@ pub async fn play_random_show_legacy(
         ));
     }
 
+    refuse_when_budget_used_up(app_state, user).await?;
+
     info!("Enabling TV mode for user: {} with no sleep timer (legacy endpoint)", user);
 
+    let room = app_state.default_room();
+    room.cancel_fade_out().await;
     {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
         tv_mode.active = true;
         tv_mode.user = Some(user.to_string());
         // Don't start sleep timer for legacy endpoint
@@ -229,7 +677,7 @@ pub async fn play_random_show_legacy(
     }
 
     app_state.save_to_disk().await;
-    let tv_mode = app_state.tv_mode.read().await;
+    let tv_mode = room.tv_mode.read().await;
 
     Ok(Json(StatusResponse::success(
         format!(
@@ -241,25 +689,34 @@ pub async fn play_random_show_legacy(
     )))
 }
 
-#[post("/api/sleep-timer", data = "<request>")]
-pub async fn set_sleep_timer(
-    app_state: &State<AppState>,
+async fn set_sleep_timer_in_room(
+    app_state: &AppState,
+    room: &Room,
     request: Json<SleepTimerRequest>,
 ) -> ApiResponse<StatusResponse> {
-    // Validate sleep timer hours
-    if ![1, 2, 4, 8, 12].contains(&request.hours) {
-        return Err(Custom(
-            Status::BadRequest,
-            Json(StatusResponse::error(
-                "Invalid sleep timer duration. Must be 1, 2, 4, 8, or 12 hours".to_string(),
-                None,
-                None,
-            )),
-        ));
-    }
+    // Turning the timer off is DELETE's job
+    let timer = match request.to_timer() {
+        Ok(Some(timer)) => timer,
+        Ok(None) => {
+            return Err(Custom(
+                Status::BadRequest,
+                Json(StatusResponse::error(
+                    "Invalid sleep timer duration. Use DELETE to disable the timer".to_string(),
+                    None,
+                    None,
+                )),
+            ))
+        }
+        Err(message) => {
+            return Err(Custom(
+                Status::BadRequest,
+                Json(StatusResponse::error(message, None, None)),
+            ))
+        }
+    };
 
     {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
 
         if !tv_mode.active {
             return Err(Custom(
@@ -272,24 +729,32 @@ pub async fn set_sleep_timer(
             ));
         }
 
-        tv_mode.sleep_timer.start(request.hours);
+        tv_mode.sleep_timer = timer.clone();
     }
+    room.cancel_fade_out().await;
 
     app_state.save_to_disk().await;
-    let tv_mode = app_state.tv_mode.read().await;
+    let tv_mode = room.tv_mode.read().await;
 
-    info!("Sleep timer updated to {} hours", request.hours);
+    info!(
+        "Sleep timer in room '{}' set to {}, then {}",
+        room.name,
+        timer.describe(),
+        timer.action
+    );
 
     Ok(Json(StatusResponse::success(
-        format!("Sleep timer set to {} hours", request.hours),
+        format!("Sleep timer set to {}, then {}", timer.describe(), timer.action),
         Some(tv_mode.clone()),
     )))
 }
 
-#[delete("/api/sleep-timer")]
-pub async fn disable_sleep_timer(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+async fn disable_sleep_timer_in_room(
+    app_state: &AppState,
+    room: &Room,
+) -> ApiResponse<StatusResponse> {
     {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
 
         if !tv_mode.active {
             return Err(Custom(
@@ -306,7 +771,7 @@ pub async fn disable_sleep_timer(app_state: &State<AppState>) -> ApiResponse<Sta
     }
 
     app_state.save_to_disk().await;
-    let tv_mode = app_state.tv_mode.read().await;
+    let tv_mode = room.tv_mode.read().await;
 
     info!("Sleep timer disabled");
 
@@ -316,13 +781,12 @@ pub async fn disable_sleep_timer(app_state: &State<AppState>) -> ApiResponse<Sta
     )))
 }
 
-#[post("/api/stop")]
-pub async fn stop_tv_mode(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+async fn stop_tv_mode_in_room(app_state: &AppState, room: &Room) -> ApiResponse<StatusResponse> {
     let was_active;
     let previous_user;
 
     {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
 
         was_active = tv_mode.active;
         previous_user = tv_mode.user.clone();
@@ -331,9 +795,10 @@ pub async fn stop_tv_mode(app_state: &State<AppState>) -> ApiResponse<StatusResp
         tv_mode.user = None;
         tv_mode.sleep_timer.stop();
     }
+    room.cancel_fade_out().await;
 
     app_state.save_to_disk().await;
-    let tv_mode = app_state.tv_mode.read().await;
+    let tv_mode = room.tv_mode.read().await;
 
     let message = if was_active {
         match previous_user {
@@ -357,49 +822,654 @@ pub async fn stop_tv_mode(app_state: &State<AppState>) -> ApiResponse<StatusResp
     )))
 }
 
-#[get("/api/status")]
-pub async fn get_status(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+async fn status_in_room(room: &Room) -> ApiResponse<StatusResponse> {
     // Get mutable reference to update timer, then clone for response
     let tv_mode_status = {
-        let mut tv_mode = app_state.tv_mode.write().await;
+        let mut tv_mode = room.tv_mode.write().await;
         tv_mode.with_updated_timer();
         tv_mode.clone()
     };
 
     // Use a timeout wrapper for the RPC call
-    let active_result = {
-        let client = app_state.rpc_client.read().await;
-
-        match rocket::tokio::time::timeout(RPC_TIMEOUT, client.is_active()).await {
-            Ok(Ok(result)) => Ok(result),
-            Ok(Err(rpc_error)) => Err(format!("RPC error: {}", rpc_error)),
-            Err(_) => Err("RPC call timed out after 5 seconds".to_string()),
-        }
+    let now_playing_result = {
+        let client = room.client().await;
+        with_rpc_timeout(client.now_playing()).await
     };
 
-    match active_result {
-        Ok(is_active) => {
-            if is_active {
-                Ok(Json(StatusResponse::media_active(tv_mode_status)))
-            } else {
-                Ok(Json(StatusResponse::media_inactive(tv_mode_status)))
-            }
-        }
-        Err(error_msg) => {
+    match now_playing_result {
+        Ok(Some(now_playing)) => Ok(Json(StatusResponse::media_active(
+            tv_mode_status,
+            now_playing,
+        ))),
+        Ok(None) => Ok(Json(StatusResponse::media_inactive(tv_mode_status))),
+        Err(kodi_error) => {
             // Log the error but don't spam - use warn level
-            warn!("Media server connectivity issue: {}", error_msg);
+            warn!("Media server connectivity issue: {}", kodi_error);
 
             // Return HTTP 200 with error status to indicate API is working
             // but media server is unreachable
-            Ok(Json(StatusResponse::error(
-                "Unable to connect to media server".to_string(),
+            Ok(Json(StatusResponse::kodi_error(
+                &kodi_error,
                 Some(tv_mode_status),
-                Some(error_msg),
             )))
         }
     }
 }
 
+async fn current_volume(room: &Room) -> ApiResponse<VolumeResponse> {
+    let client = room.client().await;
+    let properties = with_rpc_timeout(client.get_application_properties())
+        .await
+        .map_err(kodi_failure)?;
+
+    Ok(Json(VolumeResponse {
+        volume: properties.volume,
+        muted: properties.muted,
+    }))
+}
+
+async fn set_volume_in_room(room: &Room, request: Json<VolumeRequest>) -> ApiResponse<VolumeResponse> {
+    let change = match (request.volume, request.step.as_deref()) {
+        (Some(volume @ 0..=100), None) => VolumeChange::Set(volume as u8),
+        (None, Some("up")) => VolumeChange::Increment,
+        (None, Some("down")) => VolumeChange::Decrement,
+        _ => {
+            return Err(Custom(
+                Status::BadRequest,
+                Json(StatusResponse::error(
+                    "Send either a volume between 0 and 100 or a step of \"up\" or \"down\""
+                        .to_string(),
+                    None,
+                    None,
+                )),
+            ));
+        }
+    };
+
+    {
+        let client = room.client().await;
+        with_rpc_timeout(client.set_volume(change))
+            .await
+            .map_err(kodi_failure)?;
+    }
+
+    debug!("Volume changed in room '{}': {:?}", room.name, change);
+    current_volume(room).await
+}
+
+async fn set_mute_in_room(
+    room: &Room,
+    request: Option<Json<MuteRequest>>,
+) -> ApiResponse<VolumeResponse> {
+    let mute = match request.and_then(|req| req.muted) {
+        Some(true) => Toggle::On,
+        Some(false) => Toggle::Off,
+        None => Toggle::Toggle,
+    };
+
+    {
+        let client = room.client().await;
+        with_rpc_timeout(client.set_mute(mute))
+            .await
+            .map_err(kodi_failure)?;
+    }
+
+    current_volume(room).await
+}
+
+// Skip, replay and never-play only act on what TV mode is running
+async fn require_tv_mode(
+    room: &Room,
+    action: &str,
+) -> Result<TVModeStatus, Custom<Json<StatusResponse>>> {
+    let tv_mode = room.tv_mode.read().await.clone();
+    if !tv_mode.active {
+        return Err(Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(
+                format!("Cannot {} when TV mode is not active", action),
+                Some(tv_mode),
+                None,
+            )),
+        ));
+    }
+    Ok(tv_mode)
+}
+
+// Stop what's playing and note it as skipped. The caller then wakes the
+// scheduler so it picks the next item now; quiet hours, budgets and sleep
+// timers get their say on that tick as on any other.
+async fn skip_current(
+    app_state: &AppState,
+    room: &Room,
+) -> Result<(), Custom<Json<StatusResponse>>> {
+    {
+        let client = room.client().await;
+        with_rpc_timeout(client.rpc_stop())
+            .await
+            .map_err(kodi_failure)?;
+    }
+    history::update(&app_state.history, |history| history.skip(&room.name)).await;
+    Ok(())
+}
+
+async fn skip_in_room(app_state: &AppState, room: &Room) -> ApiResponse<StatusResponse> {
+    let tv_mode = require_tv_mode(room, "skip").await?;
+    skip_current(app_state, room).await?;
+    room.wake.notify_one();
+
+    let user = tv_mode.user.clone().unwrap_or_default();
+    info!(
+        "Skipped in room '{}', picking something else for '{}'",
+        room.name, user
+    );
+    Ok(Json(StatusResponse::success(
+        format!("Skipped, picking something else for '{}'", user),
+        Some(tv_mode),
+    )))
+}
+
+async fn replay_in_room(room: &Room) -> ApiResponse<StatusResponse> {
+    let tv_mode = require_tv_mode(room, "replay").await?;
+
+    let result = {
+        let client = room.client().await;
+        with_rpc_timeout(client.seek(SeekTarget::Percentage(0.0))).await
+    };
+    match result {
+        Ok(_) => {}
+        Err(KodiError::NotFound(_)) => {
+            return Err(Custom(
+                Status::BadRequest,
+                Json(StatusResponse::error(
+                    "Nothing is playing to replay".to_string(),
+                    Some(tv_mode),
+                    None,
+                )),
+            ))
+        }
+        Err(e) => return Err(kodi_failure(e)),
+    }
+
+    info!("Replaying from the start in room '{}'", room.name);
+    Ok(Json(StatusResponse::success(
+        "Playing again from the start".to_string(),
+        Some(tv_mode),
+    )))
+}
+
+// Skip what TV mode is playing, then keep it away from its user for good
+async fn never_play_in_room(app_state: &AppState, room: &Room) -> ApiResponse<StatusResponse> {
+    let tv_mode = require_tv_mode(room, "exclude what's playing").await?;
+    let Some(entry) = app_state.history.read().await.playing(&room.name).cloned() else {
+        return Err(Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(
+                "Nothing TV mode started is playing".to_string(),
+                Some(tv_mode),
+                None,
+            )),
+        ));
+    };
+
+    // Live channels have no id to exclude; they come out of the user's list
+    if entry.episodeid.is_none() && entry.movieid.is_none() {
+        return Err(Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(
+                format!("'{}' is not an episode or movie", entry.describe()),
+                Some(tv_mode),
+                Some("Remove the entry from the user's show mappings instead".to_string()),
+            )),
+        ));
+    }
+
+    // Excluded only once the stop has worked, and before the scheduler is
+    // woken to pick again
+    skip_current(app_state, room).await?;
+
+    let title = entry.describe();
+    let mut exclusions = app_state.exclusions.write().await;
+    let added = exclusions.add(
+        &entry.user,
+        ExcludedItem {
+            title: title.clone(),
+            episodeid: entry.episodeid,
+            movieid: entry.movieid,
+            excluded_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        },
+    );
+    if added {
+        app_state.save_json(EXCLUSIONS_FILE, &*exclusions, "exclusions");
+    }
+    drop(exclusions);
+    room.wake.notify_one();
+
+    info!(
+        "Excluded '{}' for '{}' and skipped it in room '{}'",
+        title, entry.user, room.name
+    );
+    Ok(Json(StatusResponse::success(
+        format!("Won't play '{}' for '{}' again", title, entry.user),
+        Some(tv_mode),
+    )))
+}
+
+// Room-less routes act on the default room
+
+#[post("/api/play/<user>", data = "<request>")]
+pub async fn play_random_show(
+    app_state: &State<AppState>,
+    user: &str,
+    request: Option<Json<PlayRequest>>,
+) -> ApiResponse<StatusResponse> {
+    play_in_room(app_state, app_state.default_room(), user, request).await
+}
+
+#[post("/api/sleep-timer", data = "<request>")]
+pub async fn set_sleep_timer(
+    app_state: &State<AppState>,
+    request: Json<SleepTimerRequest>,
+) -> ApiResponse<StatusResponse> {
+    set_sleep_timer_in_room(app_state, app_state.default_room(), request).await
+}
+
+#[delete("/api/sleep-timer")]
+pub async fn disable_sleep_timer(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    disable_sleep_timer_in_room(app_state, app_state.default_room()).await
+}
+
+#[post("/api/stop")]
+pub async fn stop_tv_mode(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    stop_tv_mode_in_room(app_state, app_state.default_room()).await
+}
+
+#[get("/api/status")]
+pub async fn get_status(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    status_in_room(app_state.default_room()).await
+}
+
+#[get("/api/volume")]
+pub async fn get_volume(app_state: &State<AppState>) -> ApiResponse<VolumeResponse> {
+    current_volume(app_state.default_room()).await
+}
+
+#[post("/api/volume", data = "<request>")]
+pub async fn set_volume(
+    app_state: &State<AppState>,
+    request: Json<VolumeRequest>,
+) -> ApiResponse<VolumeResponse> {
+    set_volume_in_room(app_state.default_room(), request).await
+}
+
+#[post("/api/mute", data = "<request>")]
+pub async fn set_mute(
+    app_state: &State<AppState>,
+    request: Option<Json<MuteRequest>>,
+) -> ApiResponse<VolumeResponse> {
+    set_mute_in_room(app_state.default_room(), request).await
+}
+
+#[post("/api/skip")]
+pub async fn skip(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    skip_in_room(app_state, app_state.default_room()).await
+}
+
+#[post("/api/replay")]
+pub async fn replay(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    replay_in_room(app_state.default_room()).await
+}
+
+#[post("/api/never-play")]
+pub async fn never_play(app_state: &State<AppState>) -> ApiResponse<StatusResponse> {
+    never_play_in_room(app_state, app_state.default_room()).await
+}
+
+fn unknown_room(room: &str) -> Custom<Json<StatusResponse>> {
+    Custom(
+        Status::NotFound,
+        Json(StatusResponse::error(
+            format!("Room '{}' not found", room),
+            None,
+            Some("Check available rooms via /api/rooms endpoint".to_string()),
+        )),
+    )
+}
+
+#[get("/api/rooms")]
+pub async fn get_rooms(app_state: &State<AppState>) -> ApiResponse<RoomsResponse> {
+    let mut rooms = Vec::new();
+    for room in app_state.rooms.values() {
+        let tv_mode = room.tv_mode.write().await.with_updated_timer().clone();
+        rooms.push(RoomSummary {
+            name: room.name.clone(),
+            tv_mode,
+        });
+    }
+
+    Ok(Json(RoomsResponse {
+        default_room: app_state.default_room.clone(),
+        rooms,
+    }))
+}
+
+#[post("/api/rooms/<room>/play/<user>", data = "<request>")]
+pub async fn play_random_show_in_room(
+    app_state: &State<AppState>,
+    room: &str,
+    user: &str,
+    request: Option<Json<PlayRequest>>,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    play_in_room(app_state, room, user, request).await
+}
+
+#[post("/api/rooms/<room>/sleep-timer", data = "<request>")]
+pub async fn set_room_sleep_timer(
+    app_state: &State<AppState>,
+    room: &str,
+    request: Json<SleepTimerRequest>,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    set_sleep_timer_in_room(app_state, room, request).await
+}
+
+#[delete("/api/rooms/<room>/sleep-timer")]
+pub async fn disable_room_sleep_timer(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    disable_sleep_timer_in_room(app_state, room).await
+}
+
+#[post("/api/rooms/<room>/stop")]
+pub async fn stop_room_tv_mode(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    stop_tv_mode_in_room(app_state, room).await
+}
+
+#[get("/api/rooms/<room>/status")]
+pub async fn get_room_status(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    status_in_room(room).await
+}
+
+#[get("/api/rooms/<room>/volume")]
+pub async fn get_room_volume(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<VolumeResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    current_volume(room).await
+}
+
+#[post("/api/rooms/<room>/volume", data = "<request>")]
+pub async fn set_room_volume(
+    app_state: &State<AppState>,
+    room: &str,
+    request: Json<VolumeRequest>,
+) -> ApiResponse<VolumeResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    set_volume_in_room(room, request).await
+}
+
+#[post("/api/rooms/<room>/mute", data = "<request>")]
+pub async fn set_room_mute(
+    app_state: &State<AppState>,
+    room: &str,
+    request: Option<Json<MuteRequest>>,
+) -> ApiResponse<VolumeResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    set_mute_in_room(room, request).await
+}
+
+#[post("/api/rooms/<room>/skip")]
+pub async fn skip_in_named_room(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    skip_in_room(app_state, room).await
+}
+
+#[post("/api/rooms/<room>/replay")]
+pub async fn replay_in_named_room(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    replay_in_room(room).await
+}
+
+#[post("/api/rooms/<room>/never-play")]
+pub async fn never_play_in_named_room(
+    app_state: &State<AppState>,
+    room: &str,
+) -> ApiResponse<StatusResponse> {
+    let room = app_state.room(room).ok_or_else(|| unknown_room(room))?;
+    never_play_in_room(app_state, room).await
+}
+
+fn schedule_response(app_state: &AppState, schedule: Schedule) -> ScheduleResponse {
+    let now = Local::now().naive_local();
+    let active_slots = app_state
+        .rooms
+        .keys()
+        .filter_map(|room| {
+            let active = schedule.active_slot(room, &app_state.default_room, now)?;
+            Some((room.clone(), active.slot.name.clone()))
+        })
+        .collect();
+
+    ScheduleResponse {
+        quiet_until: schedule
+            .quiet_until(now)
+            .map(|until| until.format("%H:%M").to_string()),
+        active_slots,
+        schedule,
+    }
+}
+
+#[get("/api/schedule")]
+pub async fn get_schedule(app_state: &State<AppState>) -> Json<ScheduleResponse> {
+    let schedule = app_state.schedule.read().await.clone();
+    Json(schedule_response(app_state, schedule))
+}
+
+// Replaces the whole schedule and writes it to schedules.yml. Slots take
+// effect on the scheduler's next tick.
+#[put("/api/schedule", data = "<schedule>")]
+pub async fn set_schedule(
+    app_state: &State<AppState>,
+    schedule: Json<Schedule>,
+) -> ApiResponse<ScheduleResponse> {
+    let schedule = schedule.into_inner();
+    let problems = {
+        let show_mappings = app_state.show_mappings.read().await;
+        schedule.problems(
+            |user| show_mappings.user(user).is_some(),
+            |room| app_state.room(room).is_some(),
+        )
+    };
+    if let Some((entry, message)) = problems.into_iter().next() {
+        return Err(Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(format!("{}: {}", entry, message), None, None)),
+        ));
+    }
+
+    {
+        let mut current = app_state.schedule.write().await;
+        if let Err(e) = app_state.write_schedule(&schedule) {
+            error!("{}", e);
+            return Err(Custom(
+                Status::InternalServerError,
+                Json(StatusResponse::error(
+                    "Failed to save schedule".to_string(),
+                    None,
+                    Some(e),
+                )),
+            ));
+        }
+        *current = schedule.clone();
+    }
+    info!(
+        "Schedule updated: {} slot(s), {} quiet period(s)",
+        schedule.slots.len(),
+        schedule.quiet_hours.len()
+    );
+
+    Ok(Json(schedule_response(app_state, schedule)))
+}
+
+// Entries returned by /api/history unless `limit` says otherwise
+const HISTORY_LIMIT: usize = 100;
+
+// Stats cover this many days unless `from` says otherwise
+const STATS_DAYS: u64 = 28;
+
+fn history_filter(
+    user: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<HistoryFilter, String> {
+    let parse = |name: &str, value: Option<&str>| {
+        value
+            .map(|value| NaiveDate::parse_from_str(value, "%Y-%m-%d"))
+            .transpose()
+            .map_err(|_| format!("`{}` must be a date like 2026-10-17", name))
+    };
+
+    Ok(HistoryFilter {
+        user: user.map(str::to_string),
+        from: parse("from", from)?,
+        to: parse("to", to)?,
+    })
+}
+
+// What TV mode played, newest first, including what's playing now
+#[get("/api/history?<user>&<from>&<to>&<limit>")]
+pub async fn get_history(
+    app_state: &State<AppState>,
+    user: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+    limit: Option<usize>,
+) -> ApiResponse<Vec<HistoryEntry>> {
+    let filter = history_filter(user, from, to).map_err(|message| {
+        Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(message, None, None)),
+        )
+    })?;
+    let mut entries = history::entries(&app_state.history, filter).await;
+    entries.reverse();
+    entries.truncate(limit.unwrap_or(HISTORY_LIMIT));
+
+    Ok(Json(entries))
+}
+
+// Top shows and hours per user per week, over the last four weeks by default
+#[get("/api/stats?<user>&<from>&<to>")]
+pub async fn get_stats(
+    app_state: &State<AppState>,
+    user: Option<&str>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> ApiResponse<HistoryStats> {
+    let mut filter = history_filter(user, from, to).map_err(|message| {
+        Custom(
+            Status::BadRequest,
+            Json(StatusResponse::error(message, None, None)),
+        )
+    })?;
+    if filter.from.is_none() {
+        filter.from = Some(Local::now().date_naive() - Days::new(STATS_DAYS - 1));
+    }
+    let entries = history::entries(&app_state.history, filter).await;
+
+    Ok(Json(history::stats(&entries)))
+}
+
+// What each user has asked never to be played again
+#[get("/api/exclusions")]
+pub async fn get_exclusions(app_state: &State<AppState>) -> Json<Exclusions> {
+    Json(app_state.exclusions.read().await.clone())
+}
+
+async fn remove_exclusion(
+    app_state: &AppState,
+    user: &str,
+    matches: impl Fn(&ExcludedItem) -> bool,
+) -> ApiResponse<Exclusions> {
+    let mut exclusions = app_state.exclusions.write().await;
+    if !exclusions.remove(user, matches) {
+        return Err(Custom(
+            Status::NotFound,
+            Json(StatusResponse::error(
+                format!("'{}' has no such item excluded", user),
+                None,
+                Some("Check excluded items via /api/exclusions endpoint".to_string()),
+            )),
+        ));
+    }
+    app_state.save_json(EXCLUSIONS_FILE, &*exclusions, "exclusions");
+    info!("Removed an exclusion for '{}'", user);
+
+    Ok(Json(exclusions.clone()))
+}
+
+#[delete("/api/exclusions/<user>/episodes/<episodeid>")]
+pub async fn remove_excluded_episode(
+    app_state: &State<AppState>,
+    user: &str,
+    episodeid: u64,
+) -> ApiResponse<Exclusions> {
+    remove_exclusion(app_state, user, |item| item.episodeid == Some(episodeid)).await
+}
+
+#[delete("/api/exclusions/<user>/movies/<movieid>")]
+pub async fn remove_excluded_movie(
+    app_state: &State<AppState>,
+    user: &str,
+    movieid: u64,
+) -> ApiResponse<Exclusions> {
+    remove_exclusion(app_state, user, |item| item.movieid == Some(movieid)).await
+}
+
+// Checks the config files on disk; `live=true` also looks every show and
+// movie entry up in each room's Kodi
+#[get("/api/config/validate?<live>")]
+pub async fn validate_config(
+    app_state: &State<AppState>,
+    live: Option<bool>,
+) -> Json<ValidationReport> {
+    Json(config_check::validate(&app_state.config_dir, live.unwrap_or(false)).await)
+}
+
+// Re-reads the config files now rather than waiting for the file watcher.
+// Invalid files leave the running config alone and return 422.
+#[post("/api/config/reload")]
+pub async fn reload_config(app_state: &State<AppState>) -> Custom<Json<ReloadReport>> {
+    let report = config_reload::reload(app_state).await;
+    let status = if report.reloaded {
+        Status::Ok
+    } else {
+        Status::UnprocessableEntity
+    };
+    Custom(status, Json(report))
+}
+
 // Health check endpoint
 #[get("/api/health")]
 pub async fn health_check() -> Json<StatusResponse> {
@@ -410,12 +1480,44 @@ pub async fn health_check() -> Json<StatusResponse> {
 pub fn routes() -> Vec<Route> {
     routes![
         get_users,
+        add_user,
+        remove_user,
+        add_user_show,
+        remove_user_show,
+        search_library_shows,
         get_status,
         play_random_show,
         play_random_show_legacy,
         set_sleep_timer,
         disable_sleep_timer,
         stop_tv_mode,
+        get_volume,
+        set_volume,
+        set_mute,
+        skip,
+        replay,
+        never_play,
+        get_rooms,
+        play_random_show_in_room,
+        set_room_sleep_timer,
+        disable_room_sleep_timer,
+        stop_room_tv_mode,
+        get_room_status,
+        get_room_volume,
+        set_room_volume,
+        set_room_mute,
+        skip_in_named_room,
+        replay_in_named_room,
+        never_play_in_named_room,
+        get_schedule,
+        set_schedule,
+        get_history,
+        get_stats,
+        get_exclusions,
+        remove_excluded_episode,
+        remove_excluded_movie,
+        validate_config,
+        reload_config,
         health_check
     ]
 }
@@ -10,7 +10,19 @@ pub async fn index(_app_state: &State<AppState>) -> Template {
     Template::render("index", context)
 }
 
+// Show mappings editor; all the data comes from the API
+#[get("/admin")]
+pub async fn admin() -> Template {
+    Template::render("admin", "")
+}
+
+// Watch history and stats; all the data comes from the API
+#[get("/history")]
+pub async fn history() -> Template {
+    Template::render("history", "")
+}
+
 // Return routes defined in this module
 pub fn routes() -> Vec<Route> {
-    routes![index,]
+    routes![index, admin, history,]
 }
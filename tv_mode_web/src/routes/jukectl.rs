@@ -4,7 +4,7 @@ use rocket::serde::json::Json;
 use rocket::Route;
 use rocket::State;
 use rocket_dyn_templates::Template;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::env;
 
 use crate::app_state::{AppState, JukectlChannel};
@@ -200,6 +200,208 @@ pub async fn proxy_get_now_playing() -> ApiResponse<serde_json::Value> {
     }
 }
 
+// Body of POST /jukectl/channels/order: every channel's name, in the new order
+#[derive(Deserialize)]
+pub struct ChannelOrder {
+    names: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct NewChannelName {
+    name: String,
+}
+
+// The part of jukectl's GET /tags a channel captures
+#[derive(Deserialize)]
+struct CurrentTags {
+    #[serde(default)]
+    any: Vec<String>,
+    #[serde(default)]
+    not: Vec<String>,
+}
+
+fn channel_error(status: Status, error: String) -> Custom<Json<ErrorResponse>> {
+    Custom(status, Json(ErrorResponse { error }))
+}
+
+// Trimmed name and tags, blank tags dropped
+fn tidy(channel: JukectlChannel) -> JukectlChannel {
+    let tags = |tags: Vec<String>| -> Vec<String> {
+        tags.iter()
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect()
+    };
+    JukectlChannel {
+        name: channel.name.trim().to_string(),
+        any: tags(channel.any),
+        not: tags(channel.not),
+    }
+}
+
+fn find_channel(channels: &[JukectlChannel], name: &str) -> Option<usize> {
+    channels
+        .iter()
+        .position(|channel| channel.name.eq_ignore_ascii_case(name.trim()))
+}
+
+// Checks `channel` and puts it at `index` (replacing what's there) or at the
+// end. Names must stay unique, ignoring case.
+fn place_channel(
+    channels: &mut Vec<JukectlChannel>,
+    index: Option<usize>,
+    channel: JukectlChannel,
+) -> Result<(), (Status, String)> {
+    if let Some(problem) = channel.problems().into_iter().next() {
+        return Err((Status::BadRequest, problem));
+    }
+    if find_channel(channels, &channel.name).is_some_and(|existing| Some(existing) != index) {
+        return Err((
+            Status::Conflict,
+            format!("A channel named '{}' already exists", channel.name),
+        ));
+    }
+
+    match index {
+        Some(index) => channels[index] = channel,
+        None => channels.push(channel),
+    }
+    Ok(())
+}
+
+// Apply `edit` to a copy of the channels, write that to jukectl_channels.yml,
+// and only then swap it in. Answers with the full list.
+async fn edit_channels(
+    app_state: &AppState,
+    edit: impl FnOnce(&mut Vec<JukectlChannel>) -> Result<(), (Status, String)>,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    let mut channels = app_state.jukectl_channels.write().await;
+    let mut edited = channels.clone();
+    edit(&mut edited).map_err(|(status, error)| channel_error(status, error))?;
+
+    if let Err(e) = app_state.write_jukectl_channels(&edited) {
+        error!("{}", e);
+        return Err(channel_error(Status::InternalServerError, e));
+    }
+    *channels = edited;
+
+    Ok(Json(channels.clone()))
+}
+
+#[get("/jukectl/channels")]
+pub async fn get_channels(app_state: &State<AppState>) -> Json<Vec<JukectlChannel>> {
+    Json(app_state.jukectl_channels.read().await.clone())
+}
+
+#[post("/jukectl/channels", data = "<channel>")]
+pub async fn create_channel(
+    app_state: &State<AppState>,
+    channel: Json<JukectlChannel>,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    let channel = tidy(channel.into_inner());
+    edit_channels(app_state, |channels| {
+        info!("Adding jukectl channel '{}'", channel.name);
+        place_channel(channels, None, channel)
+    })
+    .await
+}
+
+// Replaces the channel called `name`; the body may rename it
+#[put("/jukectl/channels/<name>", data = "<channel>")]
+pub async fn update_channel(
+    app_state: &State<AppState>,
+    name: &str,
+    channel: Json<JukectlChannel>,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    let channel = tidy(channel.into_inner());
+    edit_channels(app_state, |channels| {
+        let index = find_channel(channels, name).ok_or_else(|| unknown_channel(name))?;
+        info!("Updating jukectl channel '{}'", name);
+        place_channel(channels, Some(index), channel)
+    })
+    .await
+}
+
+#[delete("/jukectl/channels/<name>")]
+pub async fn delete_channel(
+    app_state: &State<AppState>,
+    name: &str,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    edit_channels(app_state, |channels| {
+        let index = find_channel(channels, name).ok_or_else(|| unknown_channel(name))?;
+        info!("Removing jukectl channel '{}'", name);
+        channels.remove(index);
+        Ok(())
+    })
+    .await
+}
+
+#[post("/jukectl/channels/order", data = "<order>")]
+pub async fn reorder_channels(
+    app_state: &State<AppState>,
+    order: Json<ChannelOrder>,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    edit_channels(app_state, |channels| {
+        let mut remaining = std::mem::take(channels);
+        for name in &order.names {
+            let index = find_channel(&remaining, name).ok_or_else(|| unknown_channel(name))?;
+            channels.push(remaining.remove(index));
+        }
+        if !remaining.is_empty() {
+            let missing: Vec<&str> = remaining.iter().map(|c| c.name.as_str()).collect();
+            return Err((
+                Status::BadRequest,
+                format!("The new order leaves out: {}", missing.join(", ")),
+            ));
+        }
+        Ok(())
+    })
+    .await
+}
+
+// Saves whatever jukectl is playing right now as a new channel
+#[post("/jukectl/channels/from-current", data = "<request>")]
+pub async fn save_current_as_channel(
+    app_state: &State<AppState>,
+    request: Json<NewChannelName>,
+) -> ApiResponse<Vec<JukectlChannel>> {
+    let jukectl_url = env::var("JUKECTL_API_URL")
+        .unwrap_or_else(|_| "http://localhost:8000".to_string());
+
+    let tags: CurrentTags = match reqwest::get(format!("{}/tags", jukectl_url)).await {
+        Ok(resp) if resp.status().is_success() => resp.json().await.map_err(|e| {
+            channel_error(Status::InternalServerError, format!("Parse error: {}", e))
+        })?,
+        Ok(resp) => {
+            return Err(channel_error(
+                Status::BadGateway,
+                format!("Backend error: {}", resp.status()),
+            ))
+        }
+        Err(e) => {
+            return Err(channel_error(
+                Status::ServiceUnavailable,
+                format!("Connection error: {}", e),
+            ))
+        }
+    };
+
+    let channel = tidy(JukectlChannel {
+        name: request.name.clone(),
+        any: tags.any,
+        not: tags.not,
+    });
+    edit_channels(app_state, |channels| {
+        info!("Saving jukectl's current tags as channel '{}'", channel.name);
+        place_channel(channels, None, channel)
+    })
+    .await
+}
+
+fn unknown_channel(name: &str) -> (Status, String) {
+    (Status::NotFound, format!("Channel '{}' not found", name))
+}
+
 // Return routes defined in this module
 pub fn routes() -> Vec<Route> {
     routes![
@@ -210,5 +412,11 @@ pub fn routes() -> Vec<Route> {
         proxy_toggle_album,
         proxy_update_tags,
         proxy_get_now_playing,
+        get_channels,
+        create_channel,
+        update_channel,
+        delete_channel,
+        reorder_channels,
+        save_current_as_channel,
     ]
 }
@@ -0,0 +1,226 @@
+// Daily TV mode slots and quiet hours, from the optional schedules.yml:
+//
+//   slots:
+//     - name: kids morning
+//       user: kids
+//       days: [mon, tue, wed, thu, fri]   # every day if left out
+//       start: "07:00"
+//       end: "07:45"
+//     - name: dad late
+//       user: dad
+//       start: "22:00"
+//       end: "00:30"                      # past midnight: belongs to the start day
+//       room: living room                 # default room if left out
+//       sleep_action: fade_out            # stop if left out
+//   quiet_hours:
+//     - start: "01:00"
+//       end: "06:00"
+//
+// Times are local to the server (set TZ in the container).
+
+use chrono::{Datelike, Duration, NaiveDateTime, NaiveTime, Weekday};
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::app_state::SleepAction;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Day {
+    #[serde(alias = "monday")]
+    Mon,
+    #[serde(alias = "tuesday")]
+    Tue,
+    #[serde(alias = "wednesday")]
+    Wed,
+    #[serde(alias = "thursday")]
+    Thu,
+    #[serde(alias = "friday")]
+    Fri,
+    #[serde(alias = "saturday")]
+    Sat,
+    #[serde(alias = "sunday")]
+    Sun,
+}
+
+impl From<Weekday> for Day {
+    fn from(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Mon => Day::Mon,
+            Weekday::Tue => Day::Tue,
+            Weekday::Wed => Day::Wed,
+            Weekday::Thu => Day::Thu,
+            Weekday::Fri => Day::Fri,
+            Weekday::Sat => Day::Sat,
+            Weekday::Sun => Day::Sun,
+        }
+    }
+}
+
+// "HH:MM", local time
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeOfDay(NaiveTime);
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        NaiveTime::parse_from_str(value.trim(), "%H:%M")
+            .map(TimeOfDay)
+            .map_err(|_| format!("'{}' is not a time like \"07:30\"", value))
+    }
+}
+
+impl From<TimeOfDay> for String {
+    fn from(time: TimeOfDay) -> Self {
+        time.to_string()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.format("%H:%M"))
+    }
+}
+
+// A daily stretch of time. An `end` at or before `start` runs past midnight
+// and belongs to the day it starts on.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TimeWindow {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub days: Vec<Day>,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+}
+
+impl TimeWindow {
+    // Start and end of the occurrence `now` falls in, if any
+    pub fn occurrence(&self, now: NaiveDateTime) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let today = now.date();
+        [today, today - Duration::days(1)]
+            .into_iter()
+            .filter(|day| self.days.is_empty() || self.days.contains(&day.weekday().into()))
+            .map(|day| {
+                let start = day.and_time(self.start.0);
+                let mut end = day.and_time(self.end.0);
+                if end <= start {
+                    end += Duration::days(1);
+                }
+                (start, end)
+            })
+            .find(|(start, end)| *start <= now && now < *end)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ScheduleSlot {
+    pub name: String,
+    pub user: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub room: Option<String>,
+    #[serde(flatten)]
+    pub window: TimeWindow,
+    // What the sleep timer does when the slot ends
+    #[serde(default)]
+    pub sleep_action: SleepAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Schedule {
+    #[serde(default)]
+    pub slots: Vec<ScheduleSlot>,
+    #[serde(default)]
+    pub quiet_hours: Vec<TimeWindow>,
+}
+
+// A slot that is on right now
+pub struct ActiveSlot<'a> {
+    pub slot: &'a ScheduleSlot,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl ActiveSlot<'_> {
+    // Marks this occurrence as handled, so a manual stop isn't undone
+    pub fn run_key(&self) -> String {
+        format!("{}@{}", self.slot.name, self.start.format("%Y-%m-%d %H:%M"))
+    }
+
+    // Whole minutes left, rounded up so the timer doesn't end early
+    pub fn minutes_left(&self, now: NaiveDateTime) -> u32 {
+        let seconds = (self.end - now).num_seconds().max(0);
+        ((seconds + 59) / 60).max(1) as u32
+    }
+}
+
+impl Schedule {
+    // The end of the quiet hours `now` falls in, if any
+    pub fn quiet_until(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        self.quiet_hours
+            .iter()
+            .filter_map(|window| window.occurrence(now))
+            .map(|(_, end)| end)
+            .max()
+    }
+
+    // The first slot for `room` that is on at `now`; slots without a room
+    // belong to `default_room`
+    pub fn active_slot(
+        &self,
+        room: &str,
+        default_room: &str,
+        now: NaiveDateTime,
+    ) -> Option<ActiveSlot<'_>> {
+        self.slots
+            .iter()
+            .filter(|slot| slot.room.as_deref().unwrap_or(default_room) == room)
+            .find_map(|slot| {
+                let (start, end) = slot.window.occurrence(now)?;
+                Some(ActiveSlot { slot, start, end })
+            })
+    }
+
+    // (entry, message) for everything that would stop the schedule working
+    pub fn problems(
+        &self,
+        is_user: impl Fn(&str) -> bool,
+        is_room: impl Fn(&str) -> bool,
+    ) -> Vec<(String, String)> {
+        let mut problems = Vec::new();
+        let mut names = HashSet::new();
+
+        for (index, slot) in self.slots.iter().enumerate() {
+            let entry = format!("slots[{}] ({})", index, slot.name);
+            if slot.name.trim().is_empty() {
+                problems.push((entry.clone(), "Slot name is empty".to_string()));
+            } else if !names.insert(slot.name.trim().to_lowercase()) {
+                problems.push((entry.clone(), "Another slot has the same name".to_string()));
+            }
+            if !is_user(&slot.user) {
+                problems.push((
+                    entry.clone(),
+                    format!("User '{}' not found in show mappings", slot.user),
+                ));
+            }
+            if let Some(room) = slot.room.as_deref().filter(|room| !is_room(room)) {
+                problems.push((entry.clone(), format!("Room '{}' not found", room)));
+            }
+            if slot.window.start == slot.window.end {
+                problems.push((entry, "start and end are the same time".to_string()));
+            }
+        }
+
+        for (index, window) in self.quiet_hours.iter().enumerate() {
+            if window.start == window.end {
+                problems.push((
+                    format!("quiet_hours[{}]", index),
+                    "start and end are the same time".to_string(),
+                ));
+            }
+        }
+
+        problems
+    }
+}
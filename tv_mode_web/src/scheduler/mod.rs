@@ -1,9 +1,16 @@
-use rand::prelude::*;
+use chrono::Local;
+use koditool::{Config, KodiError, KodiEvent, Subscription};
 use rocket::tokio;
 use rocket::tokio::time::{Duration, Instant};
 use std::time::SystemTime;
 
-use crate::app_state::AppState;
+use crate::app_state::{AppState, PlayRecord, Room, SleepAction, RECENTLY_PLAYED_FILE};
+use crate::history::{self, HistoryEntry};
+
+mod screen_time;
+mod sleep_timer;
+use screen_time::{meter_playback, PlayClock, WatchMeter};
+use sleep_timer::run_sleep_action;
 
 // Configuration constants
 const SCHEDULER_INTERVAL: Duration = Duration::from_secs(5); // Increased from 1s to 5s
@@ -11,6 +18,43 @@ const MAX_CONSECUTIVE_ERRORS: u32 = 5;
 const ERROR_BACKOFF_BASE: u64 = 30; // Base backoff in seconds
 const MAX_BACKOFF: u64 = 300; // Max backoff of 5 minutes
 
+#[derive(Debug)]
+enum SchedulerError {
+    // A Kodi call failed; `context` says which step we were on
+    Kodi { context: String, source: KodiError },
+    // TV mode state or show mappings don't allow us to pick anything
+    Config(String),
+}
+
+impl SchedulerError {
+    fn kodi(context: impl Into<String>, source: KodiError) -> Self {
+        SchedulerError::Kodi {
+            context: context.into(),
+            source,
+        }
+    }
+
+    // Only failures that mean "Kodi is unreachable or rejecting us" should
+    // trip the circuit breaker; a missing show just gets re-rolled next tick
+    fn counts_toward_backoff(&self) -> bool {
+        match self {
+            SchedulerError::Kodi { source, .. } => {
+                source.is_transient() || matches!(source, KodiError::Unauthorized)
+            }
+            SchedulerError::Config(_) => false,
+        }
+    }
+}
+
+impl std::fmt::Display for SchedulerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulerError::Kodi { context, source } => write!(f, "{}: {}", context, source),
+            SchedulerError::Config(message) => write!(f, "{}", message),
+        }
+    }
+}
+
 #[derive(Debug)]
 struct SchedulerState {
     consecutive_errors: u32,
@@ -55,32 +99,208 @@ impl SchedulerState {
     }
 }
 
+// Tracks what Kodi's notification socket has told us about the player, so
+// the scheduler can wake the moment an episode ends and skip polling while
+// the socket is up and something is known to be playing. It also times
+// playback for the screen time meter.
+struct PlayerWatch {
+    // The Kodi settings the subscription was made with
+    config: Config,
+    subscription: Option<Subscription>,
+    connected: bool,
+    playing: Option<bool>,
+    clock: PlayClock,
+    // Kodi started an item since the scheduler last looked
+    item_changed: bool,
+}
+
+impl PlayerWatch {
+    async fn new(room: &Room) -> Self {
+        let rpc_client = room.client().await;
+        let subscription = match rpc_client.subscribe() {
+            Ok(subscription) => Some(subscription),
+            Err(e) => {
+                warn!(
+                    "Kodi notifications unavailable in room '{}', polling only: {}",
+                    room.name, e
+                );
+                None
+            }
+        };
+
+        Self {
+            config: rpc_client.config.clone(),
+            subscription,
+            connected: false,
+            playing: None,
+            clock: PlayClock::default(),
+            item_changed: false,
+        }
+    }
+
+    // False once a config reload has pointed the room at different Kodi
+    // settings, so the subscription needs redoing
+    async fn is_current(&self, room: &Room) -> bool {
+        room.client().await.config == self.config
+    }
+
+    // Player state as last pushed by Kodi; None means we have to ask
+    fn known_playing(&self) -> Option<bool> {
+        if self.connected {
+            self.playing
+        } else {
+            None
+        }
+    }
+
+    // Whole seconds Kodi has spent playing since the last call. None while
+    // its notifications can't say (not connected, or nothing heard since
+    // connecting), so the meter has to ask Kodi instead.
+    fn take_played(&mut self) -> Option<u64> {
+        if self.known_playing().is_none() {
+            self.clock.reset();
+            return None;
+        }
+        Some(self.clock.take_seconds())
+    }
+
+    fn take_item_changed(&mut self) -> bool {
+        std::mem::take(&mut self.item_changed)
+    }
+
+    // Returns true when the event means the scheduler should run right away
+    fn apply(&mut self, event: &KodiEvent) -> bool {
+        match event {
+            KodiEvent::Connected => {
+                info!("Connected to Kodi notifications");
+                self.connected = true;
+                self.playing = None;
+                self.clock.reset();
+                false
+            }
+            KodiEvent::Disconnected => {
+                warn!("Lost Kodi notifications, falling back to polling");
+                self.connected = false;
+                self.playing = None;
+                self.clock.reset();
+                false
+            }
+            KodiEvent::PlayerOnPlay { .. } | KodiEvent::PlayerOnAVStart { .. } => {
+                self.playing = Some(true);
+                self.clock.start();
+                self.item_changed = true;
+                false
+            }
+            // Kodi 18 and later resume with OnResume rather than OnPlay
+            KodiEvent::Other { method, .. } if method == "Player.OnResume" => {
+                self.playing = Some(true);
+                self.clock.start();
+                false
+            }
+            KodiEvent::PlayerOnPause { .. } => {
+                self.playing = Some(true);
+                self.clock.halt();
+                false
+            }
+            KodiEvent::PlayerOnStop { ended, .. } => {
+                debug!("Kodi playback stopped (ended: {})", ended);
+                self.playing = Some(false);
+                self.clock.halt();
+                true
+            }
+            KodiEvent::SystemOnQuit => {
+                self.playing = None;
+                self.clock.reset();
+                false
+            }
+            _ => false,
+        }
+    }
+
+    // Sleep for `timeout`, returning early if Kodi reports playback stopped
+    // or the room is woken. Library notifications seen meanwhile go to the
+    // client's library cache.
+    async fn wait(&mut self, room: &Room, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let Some(subscription) = self.subscription.as_mut() else {
+                tokio::select! {
+                    _ = tokio::time::sleep_until(deadline) => {}
+                    _ = room.wake.notified() => self.playing = None,
+                }
+                return;
+            };
+
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => return,
+                // Whoever woke us may have changed playback; ask Kodi
+                _ = room.wake.notified() => {
+                    self.playing = None;
+                    return;
+                }
+                event = subscription.recv() => match event {
+                    Some(event) => {
+                        if room.client().await.invalidate_library_cache(&event) {
+                            debug!("Kodi library changed, dropped cached entries");
+                        }
+                        if self.apply(&event) {
+                            return;
+                        }
+                    }
+                    None => {
+                        // Listener task is gone; poll from here on
+                        self.subscription = None;
+                        self.connected = false;
+                        self.playing = None;
+                        self.clock.reset();
+                    }
+                },
+            }
+        }
+    }
+}
+
+// One loop per room, each with its own backoff and notification socket
 pub async fn start_scheduler(app_state: AppState) {
-    info!(
-        "Starting scheduler with {}s interval",
-        SCHEDULER_INTERVAL.as_secs()
-    );
-    tokio::spawn(scheduler_mainbody(app_state));
+    for room in app_state.rooms.values() {
+        info!(
+            "Starting scheduler for room '{}' with {}s interval",
+            room.name,
+            SCHEDULER_INTERVAL.as_secs()
+        );
+        tokio::spawn(scheduler_mainbody(app_state.clone(), room.clone()));
+    }
 }
 
-async fn scheduler_mainbody(app_state: AppState) {
+async fn scheduler_mainbody(app_state: AppState, room: Room) {
     let mut scheduler_state = SchedulerState::new();
+    let mut player_watch = PlayerWatch::new(&room).await;
+    let mut watch_meter = WatchMeter::new();
+    // When the live channel the scheduler put on has had its slot
+    let mut slot_end: Option<Instant> = None;
     let mut iteration_count = 0u64;
 
     loop {
         let start_time = Instant::now();
         iteration_count += 1;
 
+        if !player_watch.is_current(&room).await {
+            info!("Kodi settings changed in room '{}', resubscribing", room.name);
+            player_watch = PlayerWatch::new(&room).await;
+        }
+
         // Log iteration count periodically instead of every time
         if iteration_count.is_multiple_of(12) {
             // Every minute with 5s intervals
-            debug!("Scheduler iteration #{}", iteration_count);
+            debug!("Scheduler iteration #{} in room '{}'", iteration_count, room.name);
         }
 
         // Check if we should back off due to consecutive errors
         if let Some(backoff_duration) = scheduler_state.should_backoff() {
             warn!(
-                "Backing off for {}s due to {} consecutive errors",
+                "Backing off room '{}' for {}s due to {} consecutive errors",
+                room.name,
                 backoff_duration.as_secs(),
                 scheduler_state.consecutive_errors
             );
@@ -88,13 +308,26 @@ async fn scheduler_mainbody(app_state: AppState) {
             continue;
         }
 
-        match process_scheduler_iteration(&app_state).await {
+        match process_scheduler_iteration(
+            &app_state,
+            &room,
+            player_watch.known_playing(),
+            player_watch.take_played(),
+            player_watch.take_item_changed(),
+            &mut watch_meter,
+            &mut slot_end,
+        )
+        .await
+        {
             Ok(action_taken) => {
                 scheduler_state.record_success();
                 if action_taken {
                     info!("Successfully processed TV mode request");
                 }
             }
+            Err(e) if !e.counts_toward_backoff() => {
+                warn!("Scheduler skipped this tick in room '{}': {}", room.name, e);
+            }
             Err(e) => {
                 scheduler_state.record_error();
 
@@ -103,8 +336,9 @@ async fn scheduler_mainbody(app_state: AppState) {
                     || scheduler_state.consecutive_errors.is_multiple_of(10)
                 {
                     error!(
-                        "Scheduler error #{}: {} (will retry in {}s)",
+                        "Scheduler error #{} in room '{}': {} (will retry in {}s)",
                         scheduler_state.consecutive_errors,
+                        room.name,
                         e,
                         SCHEDULER_INTERVAL.as_secs()
                     );
@@ -115,7 +349,7 @@ async fn scheduler_mainbody(app_state: AppState) {
         // Calculate how long to sleep to maintain consistent interval
         let elapsed = start_time.elapsed();
         if elapsed < SCHEDULER_INTERVAL {
-            tokio::time::sleep(SCHEDULER_INTERVAL - elapsed).await;
+            player_watch.wait(&room, SCHEDULER_INTERVAL - elapsed).await;
         } else {
             // If processing took longer than interval, yield briefly
             tokio::task::yield_now().await;
@@ -123,47 +357,86 @@ async fn scheduler_mainbody(app_state: AppState) {
     }
 }
 
-async fn process_scheduler_iteration(app_state: &AppState) -> Result<bool, String> {
+async fn process_scheduler_iteration(
+    app_state: &AppState,
+    room: &Room,
+    known_playing: Option<bool>,
+    played: Option<u64>,
+    item_changed: bool,
+    watch_meter: &mut WatchMeter,
+    slot_end: &mut Option<Instant>,
+) -> Result<bool, SchedulerError> {
+    // Quiet hours and schedule slots come first
+    if apply_schedule(app_state, room).await? {
+        return Ok(true);
+    }
+
     // Get TV mode status
-    let tv_mode_status = app_state.tv_mode.read().await.clone();
+    let tv_mode_status = room.tv_mode.read().await.clone();
 
     if !tv_mode_status.active {
+        *slot_end = None;
         // TV mode is off, nothing to do (only log this occasionally)
+        watch_meter.stop(app_state).await;
+        history::update(&app_state.history, |history| {
+            history.finish(&room.name, false)
+        })
+        .await;
         return Ok(false);
     }
 
     // Check if sleep timer has expired
     if tv_mode_status.sleep_timer.is_expired() {
-        info!("Sleep timer expired, disabling TV mode");
-        
-        {
-            let mut tv_mode_write = app_state.tv_mode.write().await;
-            tv_mode_write.active = false;
-            tv_mode_write.user = None;
-            tv_mode_write.sleep_timer.stop();
-        }
-
-        app_state.save_to_disk().await;
-        
-        // Note: We just disable TV mode here. The media server will handle 
-        // stopping playback naturally, or you can add a stop call here if 
-        // your RpcClient has a stop method available.
-        
+        expire_sleep_timer(app_state, room, true).await?;
         return Ok(true);
     }
 
-    // Check if media is active
-    let is_active = {
-        let client = app_state.rpc_client.read().await;
-        client
-            .is_active()
-            .await
-            .map_err(|e| format!("Failed to check media status: {}", e))?
+    // Check if media is active - only ask Kodi when its notifications
+    // haven't already told us something is playing
+    let is_active = match known_playing {
+        Some(true) => true,
+        _ => {
+            let client = room.client().await;
+            client
+                .is_active()
+                .await
+                .map_err(|e| SchedulerError::kodi("Failed to check media status", e))?
+        }
     };
 
     if is_active {
-        // Something is already playing, no action needed
-        return Ok(false);
+        // Something is already playing; count it against the user's budget
+        if let Some(user) = tv_mode_status.user.as_deref() {
+            observe_playback(app_state, room, watch_meter, user, played, item_changed).await;
+            if stop_if_budget_used_up(app_state, room, watch_meter, user, true).await? {
+                return Ok(true);
+            }
+        }
+
+        // A live channel never ends by itself; it gets stopped once its slot
+        // is up so the rotation can move on
+        if !slot_end.is_some_and(|end| Instant::now() >= end) {
+            return Ok(false);
+        }
+
+        info!("Channel slot is over in room '{}', moving on", room.name);
+        room.client()
+            .await
+            .rpc_stop()
+            .await
+            .map_err(|e| SchedulerError::kodi("Failed to stop the channel", e))?;
+    }
+    *slot_end = None;
+    watch_meter.stop(app_state).await;
+    history::update(&app_state.history, |history| {
+        history.finish(&room.name, true)
+    })
+    .await;
+
+    // The episode the timer was waiting on has finished; don't start another
+    if tv_mode_status.sleep_timer.waits_for_end_of_episode() {
+        expire_sleep_timer(app_state, room, false).await?;
+        return Ok(true);
     }
 
     // TV mode is active but nothing is playing - time to act!
@@ -171,46 +444,246 @@ async fn process_scheduler_iteration(app_state: &AppState) -> Result<bool, Strin
 
     let user = tv_mode_status
         .user
-        .ok_or_else(|| "TV mode active but no user specified".to_string())?;
+        .ok_or_else(|| SchedulerError::Config("TV mode active but no user specified".to_string()))?;
 
-    // Get user's shows
-    let shows = app_state.show_mappings.read().await.sorted_shows();
-    let user_shows = shows
-        .get(&user)
-        .ok_or_else(|| format!("User '{}' not found in show mappings", user))?;
+    // Don't start anything more once the budget is gone
+    if stop_if_budget_used_up(app_state, room, watch_meter, &user, false).await? {
+        return Ok(true);
+    }
 
-    if user_shows.is_empty() {
-        return Err(format!("No shows configured for user '{}'", user));
+    // Get user's shows and movie entries
+    let user_mapping = app_state
+        .show_mappings
+        .read()
+        .await
+        .user(&user)
+        .cloned()
+        .ok_or_else(|| {
+            SchedulerError::Config(format!("User '{}' not found in show mappings", user))
+        })?;
+    if user_mapping.shows.is_empty() {
+        return Err(SchedulerError::Config(format!(
+            "No shows configured for user '{}'",
+            user
+        )));
     }
 
-    // Select random show and episode
-    let selected_show = select_random_show_name(user_shows)
-        .ok_or_else(|| "Failed to select random show".to_string())?;
+    // Weighted pick of an entry that wasn't just on, then an episode or
+    // movie from it that wasn't either
+    let window = user_mapping.repeat_window.unwrap_or_default();
+    let (recent_entries, mut options) = {
+        let recently_played = app_state.recently_played.read().await;
+        (
+            recently_played.recent_entries(&user, &window),
+            recently_played.select_options(&user, &user_mapping),
+        )
+    };
+    app_state.exclusions.read().await.apply(&user, &mut options);
+    let selected_entry = user_mapping
+        .choose_entry(&recent_entries, &mut rand::rng())
+        .ok_or_else(|| {
+            SchedulerError::Config(format!("No enabled shows configured for user '{}'", user))
+        })?;
 
-    debug!("Selected show '{}' for user '{}'", selected_show, user);
+    debug!("Selected '{}' for user '{}'", selected_entry, user);
 
-    let rpc_client = app_state.rpc_client.read().await;
+    let rpc_client = room.client().await;
 
-    let selected_episode = rpc_client
-        .select_random_episode_by_title(selected_show)
+    let selection = rpc_client
+        .select_for_entry(selected_entry, &options)
         .await
-        .map_err(|e| format!("Failed to select episode for '{}': {}", selected_show, e))?;
+        .map_err(|e| {
+            SchedulerError::kodi(format!("Failed to select content for '{}'", selected_entry), e)
+        })?;
 
     rpc_client
-        .rpc_play(&selected_episode)
+        .play_selection(&selection)
         .await
-        .map_err(|e| format!("Failed to play episode: {}", e))?;
+        .map_err(|e| SchedulerError::kodi("Failed to play selection", e))?;
+    *slot_end = selection
+        .slot()
+        .and_then(|slot| Instant::now().checked_add(slot));
 
     info!(
-        "Started playing content for user '{}': {}",
-        user, selected_show
+        "Started playing content for user '{}' in room '{}': {} ({})",
+        user, room.name, selected_entry, selection
+    );
+
+    let entry = HistoryEntry::new(
+        &user,
+        &room.name,
+        &selected_entry.to_string(),
+        selection.episode_id(),
+        selection.movie_id(),
+    );
+    history::update(&app_state.history, |history| history.start(entry)).await;
+    let mut recently_played = app_state.recently_played.write().await;
+    recently_played.record(
+        &user,
+        PlayRecord {
+            entry: selected_entry.to_string(),
+            episodeid: selection.episode_id(),
+            movieid: selection.movie_id(),
+            played_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        },
     );
+    app_state.save_json(
+        RECENTLY_PLAYED_FILE,
+        &*recently_played,
+        "recently played history",
+    );
+
     Ok(true)
 }
 
-fn select_random_show_name(shows: &[String]) -> Option<&String> {
-    if shows.is_empty() {
-        return None;
+// Count what's playing against `user`'s budget and note it in the history.
+// Kodi is only asked what's playing when the item changed or the meter is
+// due; in between the history entry just gains the seconds played.
+async fn observe_playback(
+    app_state: &AppState,
+    room: &Room,
+    watch_meter: &mut WatchMeter,
+    user: &str,
+    played: Option<u64>,
+    item_changed: bool,
+) {
+    let watched = meter_playback(app_state, room, watch_meter, user, played, item_changed).await;
+    history::update(&app_state.history, |history| {
+        history.progress(&room.name, watched.now_playing.as_ref(), watched.seconds)
+    })
+    .await;
+}
+
+// Quiet hours switch TV mode off and stop playback. A slot that has just
+// begun switches TV mode on for its user, with a sleep timer that ends with
+// the slot; each occurrence is acted on once, so a manual stop sticks.
+// Returns true when TV mode was switched off.
+async fn apply_schedule(app_state: &AppState, room: &Room) -> Result<bool, SchedulerError> {
+    let now = Local::now().naive_local();
+    // A copy, so the lock isn't held while Kodi is stopped
+    let schedule = app_state.schedule.read().await.clone();
+
+    if let Some(until) = schedule.quiet_until(now) {
+        if !room.tv_mode.read().await.active {
+            return Ok(false);
+        }
+        info!(
+            "Quiet hours until {}, disabling TV mode in room '{}'",
+            until.format("%H:%M"),
+            room.name
+        );
+        turn_off_tv_mode(app_state, room, SleepAction::Stop, true).await?;
+        return Ok(true);
     }
-    shows.choose(&mut rand::rng())
+
+    let Some(active) = schedule.active_slot(&room.name, &app_state.default_room, now) else {
+        return Ok(false);
+    };
+    let run_key = active.run_key();
+    let used_up = app_state
+        .screen_time_report(&active.slot.user)
+        .await
+        .used_up();
+    {
+        let mut tv_mode = room.tv_mode.write().await;
+        if tv_mode.schedule_run.as_deref() == Some(run_key.as_str()) {
+            return Ok(false);
+        }
+        tv_mode.schedule_run = Some(run_key);
+
+        if tv_mode.active {
+            info!(
+                "Schedule slot '{}' began with TV mode already on in room '{}', leaving it",
+                active.slot.name, room.name
+            );
+        } else if let Some(period) = used_up {
+            info!(
+                "Schedule slot '{}' began, but screen time for '{}' is used up for {}; \
+                 leaving TV mode off in room '{}'",
+                active.slot.name, active.slot.user, period, room.name
+            );
+        } else {
+            info!(
+                "Schedule slot '{}' began, enabling TV mode for '{}' in room '{}' until {}",
+                active.slot.name,
+                active.slot.user,
+                room.name,
+                active.end.format("%H:%M")
+            );
+            tv_mode.active = true;
+            tv_mode.user = Some(active.slot.user.clone());
+            tv_mode
+                .sleep_timer
+                .start(active.minutes_left(now), active.slot.sleep_action);
+            room.cancel_fade_out().await;
+        }
+    }
+
+    app_state.save_to_disk().await;
+    Ok(false)
+}
+
+// Turn TV mode off with the budget's action once `user` has used up their
+// screen time. Returns true if it did.
+async fn stop_if_budget_used_up(
+    app_state: &AppState,
+    room: &Room,
+    watch_meter: &mut WatchMeter,
+    user: &str,
+    playing: bool,
+) -> Result<bool, SchedulerError> {
+    let report = app_state.screen_time_report(user).await;
+    let (Some(period), Some(budget)) = (report.used_up(), report.budget) else {
+        return Ok(false);
+    };
+
+    info!(
+        "Screen time for '{}' is used up for {}, disabling TV mode in room '{}' ({})",
+        user, period, room.name, budget.action
+    );
+    watch_meter.stop(app_state).await;
+    turn_off_tv_mode(app_state, room, budget.action, playing).await?;
+    Ok(true)
+}
+
+async fn expire_sleep_timer(
+    app_state: &AppState,
+    room: &Room,
+    playing: bool,
+) -> Result<(), SchedulerError> {
+    let action = room.tv_mode.read().await.sleep_timer.action;
+    info!(
+        "Sleep timer expired, disabling TV mode in room '{}' ({})",
+        room.name, action
+    );
+    turn_off_tv_mode(app_state, room, action, playing).await
+}
+
+// Turn TV mode off, then carry out `action`. TV mode goes off first so a
+// failed action isn't retried every tick.
+async fn turn_off_tv_mode(
+    app_state: &AppState,
+    room: &Room,
+    action: SleepAction,
+    playing: bool,
+) -> Result<(), SchedulerError> {
+    {
+        let mut tv_mode_write = room.tv_mode.write().await;
+        tv_mode_write.active = false;
+        tv_mode_write.user = None;
+        tv_mode_write.sleep_timer.stop();
+    }
+
+    app_state.save_to_disk().await;
+    history::update(&app_state.history, |history| {
+        history.finish(&room.name, false)
+    })
+    .await;
+
+    run_sleep_action(room, action, playing)
+        .await
+        .map_err(|e| SchedulerError::kodi(format!("Failed to {} while turning TV mode off", action), e))
 }
@@ -0,0 +1,183 @@
+use chrono::Local;
+use koditool::NowPlaying;
+use rocket::tokio::time::{Duration, Instant};
+
+use crate::app_state::{AppState, Room, SCREEN_TIME_FILE};
+
+// Usage is written out once this much has built up, and when playback stops
+const SAVE_AFTER_SECONDS: u64 = 60;
+// Without notifications, how often the player's position is read
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+// Playing time as Kodi's notifications report it: the clock runs from OnPlay
+// (or OnAVStart, or OnResume) until OnPause or OnStop, so no requests are
+// needed to time it.
+#[derive(Default)]
+pub(super) struct PlayClock {
+    running_since: Option<Instant>,
+    banked: Duration,
+}
+
+impl PlayClock {
+    pub(super) fn start(&mut self) {
+        self.running_since.get_or_insert_with(Instant::now);
+    }
+
+    pub(super) fn halt(&mut self) {
+        if let Some(since) = self.running_since.take() {
+            self.banked += since.elapsed();
+        }
+    }
+
+    // Forget everything, e.g. when the notifications drop out
+    pub(super) fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    // Whole seconds played since the last call; the fraction carries over
+    pub(super) fn take_seconds(&mut self) -> u64 {
+        if let Some(since) = self.running_since.as_mut() {
+            let now = Instant::now();
+            self.banked += now - *since;
+            *since = now;
+        }
+        let seconds = self.banked.as_secs();
+        self.banked -= Duration::from_secs(seconds);
+        seconds
+    }
+}
+
+struct Reading {
+    user: String,
+    file: String,
+    elapsed: u64,
+    at: Instant,
+}
+
+// The player's position at the last reading, for when there are no
+// notifications to time playback with. One per room.
+pub(super) struct WatchMeter {
+    last: Option<Reading>,
+    // When Kodi was last asked what's playing, successfully or not
+    looked_at: Option<Instant>,
+    unsaved_seconds: u64,
+}
+
+impl WatchMeter {
+    pub(super) fn new() -> Self {
+        Self {
+            last: None,
+            looked_at: None,
+            unsaved_seconds: 0,
+        }
+    }
+
+    // Whether it's time to ask Kodi what's playing again
+    fn due(&self) -> bool {
+        self.looked_at
+            .is_none_or(|at| at.elapsed() >= POLL_INTERVAL)
+    }
+
+    // Seconds of `now_playing` watched since the last reading of the same
+    // item for the same user. Capped at the time in between, so a seek
+    // forward doesn't count; a paused player doesn't move at all.
+    fn advance(&mut self, user: &str, now_playing: &NowPlaying) -> u64 {
+        let elapsed = now_playing.elapsed.as_seconds();
+        let reading = Reading {
+            user: user.to_string(),
+            file: now_playing.file.clone(),
+            elapsed,
+            at: Instant::now(),
+        };
+
+        match self.last.replace(reading) {
+            Some(last) if last.user == user && last.file == now_playing.file => {
+                let between = last.at.elapsed().as_secs_f64().ceil() as u64;
+                elapsed.saturating_sub(last.elapsed).min(between)
+            }
+            _ => 0,
+        }
+    }
+
+    // Nothing is playing for TV mode; save whatever hasn't been
+    pub(super) async fn stop(&mut self, app_state: &AppState) {
+        self.last = None;
+        self.looked_at = None;
+        if self.unsaved_seconds > 0 {
+            save_screen_time(app_state).await;
+            self.unsaved_seconds = 0;
+        }
+    }
+}
+
+// What the meter made of a tick
+pub(super) struct Watched {
+    pub(super) seconds: u64,
+    // Set when Kodi was asked, which is only when `item_changed` or the
+    // meter was due
+    pub(super) now_playing: Option<NowPlaying>,
+}
+
+// Add what was watched since the last tick to `user`'s screen time. `played`
+// is what Kodi's notifications timed, when they're up. Otherwise the player's
+// position is read every POLL_INTERVAL, and if Kodi doesn't answer only that
+// stretch goes uncounted.
+pub(super) async fn meter_playback(
+    app_state: &AppState,
+    room: &Room,
+    meter: &mut WatchMeter,
+    user: &str,
+    played: Option<u64>,
+    item_changed: bool,
+) -> Watched {
+    let mut now_playing = None;
+    if item_changed || meter.due() {
+        meter.looked_at = Some(Instant::now());
+        match room.client().await.now_playing().await {
+            Ok(Some(playing)) => now_playing = Some(playing),
+            Ok(None) => {
+                meter.stop(app_state).await;
+                return Watched {
+                    seconds: 0,
+                    now_playing: None,
+                };
+            }
+            Err(e) => {
+                debug!("Couldn't read playback time in room '{}': {}", room.name, e);
+                meter.last = None;
+            }
+        }
+    }
+
+    let seconds = match (played, &now_playing) {
+        (Some(seconds), _) => {
+            meter.last = None;
+            seconds
+        }
+        (None, Some(playing)) => meter.advance(user, playing),
+        (None, None) => 0,
+    };
+
+    if seconds > 0 {
+        app_state
+            .screen_time
+            .write()
+            .await
+            .add(user, Local::now().date_naive(), seconds);
+
+        meter.unsaved_seconds += seconds;
+        if meter.unsaved_seconds >= SAVE_AFTER_SECONDS {
+            save_screen_time(app_state).await;
+            meter.unsaved_seconds = 0;
+        }
+    }
+    Watched {
+        seconds,
+        now_playing,
+    }
+}
+
+async fn save_screen_time(app_state: &AppState) {
+    let screen_time = app_state.screen_time.read().await;
+    app_state.save_json(SCREEN_TIME_FILE, &*screen_time, "screen time");
+}
@@ -0,0 +1,87 @@
+use koditool::{KodiError, RpcClient, Toggle, VolumeChange};
+use rocket::tokio;
+use rocket::tokio::sync::oneshot;
+use rocket::tokio::time::{sleep, Duration};
+use std::sync::Arc;
+
+use crate::app_state::{Room, SleepAction};
+
+// How long a fade-out takes, and in how many volume steps
+const FADE_OUT_DURATION: Duration = Duration::from_secs(30);
+const FADE_OUT_STEPS: u32 = 15;
+
+// Carry out what the sleep timer was set to do. `playing` is false when the
+// timer went off because the episode ended, so only suspend is left to do.
+pub(crate) async fn run_sleep_action(
+    room: &Room,
+    action: SleepAction,
+    playing: bool,
+) -> Result<(), KodiError> {
+    let rpc_client = room.client().await;
+    match action {
+        SleepAction::Stop | SleepAction::Pause | SleepAction::FadeOut if !playing => Ok(()),
+        SleepAction::Stop => rpc_client.rpc_stop().await,
+        SleepAction::Pause => pause(&rpc_client).await,
+        SleepAction::FadeOut => fade_out(room, rpc_client).await,
+        SleepAction::Suspend => {
+            if playing {
+                rpc_client.rpc_stop().await?;
+            }
+            rpc_client.suspend().await
+        }
+    }
+}
+
+// Pausing with nothing playing is not an error here
+async fn pause(rpc_client: &RpcClient) -> Result<(), KodiError> {
+    if rpc_client.active_player_id().await?.is_none() {
+        return Ok(());
+    }
+    rpc_client.play_pause(Toggle::Off).await?;
+    Ok(())
+}
+
+// Start ramping the volume down in the background, so the room keeps
+// answering for the 30s it takes. Turning TV mode on or off, or setting a new
+// sleep timer, calls it off through `Room::cancel_fade_out`.
+async fn fade_out(room: &Room, rpc_client: Arc<RpcClient>) -> Result<(), KodiError> {
+    if rpc_client.active_player_id().await?.is_none() {
+        return Ok(());
+    }
+    let volume = rpc_client.get_application_properties().await?.volume;
+
+    let (cancel, cancelled) = oneshot::channel();
+    // Replacing the sender calls off an earlier fade that is still running
+    *room.fade_out.lock().await = Some(cancel);
+
+    let room_name = room.name.clone();
+    tokio::spawn(async move {
+        let faded = tokio::select! {
+            faded = ramp_down_and_stop(&rpc_client, volume) => faded,
+            _ = cancelled => {
+                info!("Fade-out in room '{}' called off", room_name);
+                Ok(())
+            }
+        };
+        let restored = rpc_client.set_volume(VolumeChange::Set(volume)).await;
+        if let Err(e) = faded.and(restored.map(|_| ())) {
+            warn!("Fade-out in room '{}' failed: {}", room_name, e);
+        }
+    });
+    Ok(())
+}
+
+// Step the volume down from `volume` to 0, then stop. The caller puts the
+// volume back afterwards, so the next person to press play isn't met with
+// silence (even if a step along the way failed).
+async fn ramp_down_and_stop(rpc_client: &RpcClient, volume: u8) -> Result<(), KodiError> {
+    let step_delay = FADE_OUT_DURATION / FADE_OUT_STEPS;
+    for step in 1..=FADE_OUT_STEPS {
+        let level = volume as u32 * (FADE_OUT_STEPS - step) / FADE_OUT_STEPS;
+        rpc_client
+            .set_volume(VolumeChange::Set(level as u8))
+            .await?;
+        sleep(step_delay).await;
+    }
+    rpc_client.rpc_stop().await
+}
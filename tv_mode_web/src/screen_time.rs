@@ -0,0 +1,131 @@
+// Per-user watch budgets, from the optional budgets.yml next to
+// show_mappings.yml:
+//
+//   kids:
+//     daily_minutes: 60
+//     weekly_minutes: 300      # Monday to Sunday
+//     action: fade_out         # when the budget runs out; stop if left out
+//
+// What each user has watched is kept per day in screen_time.json. The
+// scheduler adds to it from the player's elapsed time, so pauses don't count.
+
+use chrono::{Datelike, Duration, NaiveDate};
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::app_state::SleepAction;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily_minutes: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly_minutes: Option<u32>,
+    // What happens to playback when the budget runs out
+    #[serde(default)]
+    pub action: SleepAction,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+pub struct Budgets {
+    #[serde(flatten)]
+    pub(crate) users: BTreeMap<String, Budget>,
+}
+
+impl Budgets {
+    pub fn user(&self, user: &str) -> Option<&Budget> {
+        self.users.get(user)
+    }
+}
+
+// Days of history kept; enough for this week and last
+const USAGE_DAYS: i64 = 14;
+
+// Seconds watched per user per day
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ScreenTimeUsage {
+    #[serde(flatten)]
+    users: HashMap<String, BTreeMap<NaiveDate, u64>>,
+}
+
+// One user's screen time, as /api/users reports it
+#[derive(Debug, Serialize, Clone)]
+pub struct ScreenTimeReport {
+    pub watched_today_seconds: u64,
+    pub watched_this_week_seconds: u64,
+    // None when the user has no budget
+    pub budget: Option<Budget>,
+    pub remaining_seconds: Option<u64>,
+}
+
+impl ScreenTimeReport {
+    // "this week" or "today" once the budget is used up
+    pub fn used_up(&self) -> Option<&'static str> {
+        let budget = self.budget.as_ref()?;
+        let over = |minutes: Option<u32>, watched: u64| {
+            minutes.is_some_and(|minutes| watched >= minutes as u64 * 60)
+        };
+
+        if over(budget.weekly_minutes, self.watched_this_week_seconds) {
+            Some("this week")
+        } else if over(budget.daily_minutes, self.watched_today_seconds) {
+            Some("today")
+        } else {
+            None
+        }
+    }
+}
+
+impl ScreenTimeUsage {
+    pub fn add(&mut self, user: &str, day: NaiveDate, seconds: u64) {
+        let days = self.users.entry(user.to_string()).or_default();
+        *days.entry(day).or_default() += seconds;
+        days.retain(|watched_on, _| *watched_on > day - Duration::days(USAGE_DAYS));
+    }
+
+    pub fn watched_on(&self, user: &str, day: NaiveDate) -> u64 {
+        self.users
+            .get(user)
+            .and_then(|days| days.get(&day))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // From the Monday before `day` up to and including `day`
+    pub fn watched_in_week(&self, user: &str, day: NaiveDate) -> u64 {
+        let monday = day - Duration::days(day.weekday().num_days_from_monday() as i64);
+        self.users
+            .get(user)
+            .map(|days| days.range(monday..=day).map(|(_, seconds)| seconds).sum())
+            .unwrap_or(0)
+    }
+
+    pub fn report(
+        &self,
+        user: &str,
+        budget: Option<&Budget>,
+        today: NaiveDate,
+    ) -> ScreenTimeReport {
+        let watched_today = self.watched_on(user, today);
+        let watched_this_week = self.watched_in_week(user, today);
+        let left = |minutes: Option<u32>, watched: u64| {
+            minutes.map(|minutes| (minutes as u64 * 60).saturating_sub(watched))
+        };
+        let remaining = budget.and_then(|budget| {
+            match (
+                left(budget.daily_minutes, watched_today),
+                left(budget.weekly_minutes, watched_this_week),
+            ) {
+                (Some(daily), Some(weekly)) => Some(daily.min(weekly)),
+                (daily, weekly) => daily.or(weekly),
+            }
+        });
+
+        ScreenTimeReport {
+            watched_today_seconds: watched_today,
+            watched_this_week_seconds: watched_this_week,
+            budget: budget.copied(),
+            remaining_seconds: remaining,
+        }
+    }
+}
@@ -0,0 +1,170 @@
+mod harness;
+
+use harness::KodiMock;
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+use tv_mode_web::config_check::{self, Severity};
+
+fn messages(report: &config_check::ValidationReport) -> Vec<String> {
+    report.diagnostics.iter().map(|d| d.to_string()).collect()
+}
+
+#[rocket::async_test]
+async fn test_structural_diagnostics() {
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        "\
+url: http://localhost:8080
+username: user
+password: pass
+rooms:
+  kids room:
+    url: localhost:8081
+    username: user
+    password: pass
+",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "\
+nobody: []
+user1:
+  - Show 1
+  - show: Show 2
+    min_runtime: 30
+    max_runtime: 10
+    exclude_special: true
+    exlude: [1]
+  - movies:
+      genra: Animation
+",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("jukectl_channels.yml"),
+        "\
+channels:
+  - name: Chill
+    any: [chill]
+  - name: chill
+    any: []
+",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("schedules.yml"),
+        "\
+slots:
+  - name: late
+    user: dad
+    room: kids room
+    start: \"22:00\"
+    end: \"22:00\"
+",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("budgets.yml"),
+        "\
+dad:
+  daily_minutes: 60
+user1:
+  daily_minutes: 120
+  weekly_minutes: 90
+",
+    )
+    .unwrap();
+
+    let report = config_check::validate(config_dir.to_str().unwrap(), false).await;
+    assert!(!report.valid);
+    assert!(!report.live);
+    assert_eq!(
+        messages(&report),
+        vec![
+            "error: config.yml: room kids room: url 'localhost:8081' must be http:// or https://",
+            "error: show_mappings.yml: nobody: No shows configured",
+            "warning: show_mappings.yml: user1 / Movies (any): No title, genre, set or tag \
+             given, so any movie can be picked",
+            "error: show_mappings.yml: user1 / Show 2: min_runtime (30) is more than \
+             max_runtime (10), so no episode can match",
+            "warning: show_mappings.yml: user1 / Show 2: Unknown key 'exclude_special', \
+             which is ignored",
+            "warning: show_mappings.yml: user1 / Show 2: Unknown key 'exlude', which is \
+             ignored",
+            "warning: show_mappings.yml: user1 / Movies (any): Unknown key 'genra', which \
+             is ignored",
+            "error: jukectl_channels.yml: channels[1] (chill): `any` has no tags, so the \
+             channel plays nothing",
+            "warning: jukectl_channels.yml: channels[1] (chill): Another channel has the \
+             same name",
+            "error: schedules.yml: slots[0] (late): User 'dad' not found in show mappings",
+            "error: schedules.yml: slots[0] (late): start and end are the same time",
+            "warning: budgets.yml: dad: User 'dad' not found in show mappings",
+            "warning: budgets.yml: user1: daily_minutes (120) is not less than \
+             weekly_minutes (90), so it never applies",
+        ]
+    );
+    assert_eq!(report.errors, 6);
+    assert_eq!(report.warnings, 7);
+
+    // Files that don't parse are reported rather than aborting the check
+    fs::write(config_dir.join("show_mappings.yml"), "user1: [[").unwrap();
+    let report = config_check::check_files(config_dir.to_str().unwrap());
+    assert!(report
+        .diagnostics
+        .iter()
+        .any(|d| d.file == "show_mappings.yml" && d.severity == Severity::Error));
+}
+
+#[rocket::async_test]
+async fn test_live_check_suggests_titles() {
+    let mut mock = KodiMock::new().await;
+    let _shows = mock.mock_get_tv_shows().await;
+
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        format!("url: {}\nusername: user\npassword: pass\n", mock.url()),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - The Ofice\n  - Breaking Bad\n",
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    // Structurally fine
+    let response = client.get("/api/config/validate").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["valid"], true);
+    assert_eq!(body["live"], false);
+    assert_eq!(body["diagnostics"], serde_json::json!([]));
+
+    let response = client
+        .get("/api/config/validate?live=true")
+        .dispatch()
+        .await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["valid"], false);
+    assert_eq!(body["live"], true);
+    assert_eq!(body["errors"], 1);
+    assert_eq!(body["diagnostics"][0]["severity"], "error");
+    assert_eq!(body["diagnostics"][0]["entry"], "user1 / The Ofice");
+    assert_eq!(
+        body["diagnostics"][0]["message"],
+        "TV show The Ofice not found; did you mean: The Office?"
+    );
+}
@@ -0,0 +1,106 @@
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration};
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+const CONFIG_YML: &str = "url: http://localhost:8080\nusername: user\npassword: pass\n";
+
+async fn users(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/users").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    body["show_mappings"].clone()
+}
+
+#[rocket::async_test]
+async fn test_reload_swaps_valid_config_only() {
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(config_dir.join("config.yml"), CONFIG_YML).unwrap();
+    fs::write(config_dir.join("show_mappings.yml"), "user1:\n  - Show 1\n").unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+    let app_state = client
+        .rocket()
+        .state::<tv_mode_web::app_state::AppState>()
+        .unwrap()
+        .clone();
+
+    // Nothing changed yet
+    let response = client.post("/api/config/reload").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["reloaded"], true);
+    assert_eq!(body["changes"], serde_json::json!([]));
+
+    // New mappings and a new Kodi address; an added room needs a restart
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - Show 1\n  - Show 2\n",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("config.yml"),
+        "\
+url: http://kodi.local:8080
+username: user
+password: pass
+rooms:
+  kids room:
+    url: http://localhost:8081
+    username: user
+    password: pass
+",
+    )
+    .unwrap();
+    let response = client.post("/api/config/reload").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(
+        body["changes"],
+        serde_json::json!(["room default: Kodi connection", "show_mappings.yml"])
+    );
+    assert_eq!(
+        body["restart_required"],
+        serde_json::json!(["room kids room added"])
+    );
+    assert_eq!(
+        users(&client).await["user1"],
+        serde_json::json!(["Show 1", "Show 2"])
+    );
+    assert_eq!(
+        app_state.default_room().rpc_client.read().await.config.url,
+        "http://kodi.local:8080"
+    );
+
+    // Invalid files are rejected and the running config is kept
+    fs::write(config_dir.join("show_mappings.yml"), "user1: []\n").unwrap();
+    let response = client.post("/api/config/reload").dispatch().await;
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["reloaded"], false);
+    assert_eq!(
+        body["validation"]["diagnostics"][0]["message"],
+        "No shows configured"
+    );
+    assert_eq!(
+        users(&client).await["user1"],
+        serde_json::json!(["Show 1", "Show 2"])
+    );
+
+    // The file watcher picks up edits on its own
+    fs::write(config_dir.join("show_mappings.yml"), "user1:\n  - Show 3\n").unwrap();
+    let mut reloaded = false;
+    for _ in 0..50 {
+        if users(&client).await["user1"] == serde_json::json!(["Show 3"]) {
+            reloaded = true;
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert!(reloaded, "file watcher didn't reload show_mappings.yml");
+}
@@ -1,6 +1,13 @@
+use futures_util::SinkExt;
 use mockito::{Server, Mock};
+use rocket::tokio;
+use rocket::tokio::net::TcpListener;
+use rocket::tokio::sync::mpsc;
 use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio_tungstenite::tungstenite::Message;
 
 use mockito::ServerGuard;
 
@@ -162,6 +169,92 @@ impl KodiMock {
             .await
     }
 
+    // Answers the Player.GetItem + Player.GetProperties batch behind now_playing.
+    // Ids are echoed back since the scheduler may have used some already.
+    pub async fn mock_now_playing_episode(&mut self) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!([{"method": "Player.GetItem"}])))
+            .with_header("content-type", "application/json")
+            .with_body_from_request(|request| {
+                let calls: serde_json::Value =
+                    serde_json::from_slice(request.body().unwrap()).unwrap();
+                let replies: Vec<serde_json::Value> = calls
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|call| {
+                        let result = match call["method"].as_str() {
+                            Some("Player.GetItem") => json!({
+                                "item": {
+                                    "type": "episode",
+                                    "id": 7,
+                                    "label": "Pilot",
+                                    "title": "Pilot",
+                                    "showtitle": "Breaking Bad",
+                                    "season": 1,
+                                    "episode": 1,
+                                    "file": "/path/to/episode.mkv"
+                                }
+                            }),
+                            _ => json!({
+                                "percentage": 10.0,
+                                "speed": 1,
+                                "time": { "hours": 0, "minutes": 5, "seconds": 0, "milliseconds": 0 },
+                                "totaltime": { "hours": 0, "minutes": 50, "seconds": 0, "milliseconds": 0 }
+                            }),
+                        };
+                        json!({ "id": call["id"], "jsonrpc": "2.0", "result": result })
+                    })
+                    .collect();
+                serde_json::to_vec(&replies).unwrap()
+            })
+            .create_async()
+            .await
+    }
+
+    pub async fn mock_application_properties(&mut self, volume: u8, muted: bool) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "Application.GetProperties"})))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": { "volume": volume, "muted": muted }
+            }).to_string())
+            .create_async()
+            .await
+    }
+
+    pub async fn mock_set_volume(&mut self, volume: serde_json::Value, result: u8) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Application.SetVolume",
+                "params": { "volume": volume }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": result
+            }).to_string())
+            .create_async()
+            .await
+    }
+
+    // Every Application.SetVolume, whatever the level, e.g. for a fade-out
+    pub async fn mock_set_volume_any(&mut self) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "Application.SetVolume"})))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": 0
+            }).to_string())
+            .create_async()
+            .await
+    }
+
     pub async fn mock_player_open(&mut self) -> Mock {
         self.server.mock("POST", "/jsonrpc")
             .match_body(mockito::Matcher::PartialJson(json!({"method": "Player.Open"})))
@@ -175,6 +268,38 @@ impl KodiMock {
             .await
     }
 
+    // One live TV channel, ABC Kids with channelid 12
+    pub async fn mock_get_channels(&mut self) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "PVR.GetChannels"})))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": {
+                    "channels": [
+                        { "channelid": 12, "label": "ABC Kids", "channel": "ABC Kids", "channeltype": "tv", "channelnumber": 22 }
+                    ],
+                    "limits": { "start": 0, "end": 1, "total": 1 }
+                }
+            }).to_string())
+            .create_async()
+            .await
+    }
+
+    pub async fn mock_play_channel(&mut self, channelid: u64) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Open",
+                "params": { "item": { "channelid": channelid } }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(json!({ "id": 1, "jsonrpc": "2.0", "result": "OK" }).to_string())
+            .expect_at_least(1)
+            .create_async()
+            .await
+    }
+
     pub async fn mock_player_stop(&mut self) -> Mock {
         self.server.mock("POST", "/jsonrpc")
             .match_body(mockito::Matcher::PartialJson(json!({"method": "Player.Stop"})))
@@ -188,6 +313,91 @@ impl KodiMock {
             .await
     }
 
+    pub async fn mock_system_suspend(&mut self) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "System.Suspend"})))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": "OK"
+            }).to_string())
+            .create_async()
+            .await
+    }
+
+    // A player that Player.Open starts and Player.Stop stops, as far as
+    // Player.GetActivePlayers is concerned. Mocks are (active players, open, stop).
+    pub async fn mock_player(&mut self) -> (Mock, Mock, Mock) {
+        let playing = Arc::new(AtomicBool::new(false));
+
+        let state = playing.clone();
+        let active = self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": "Player.GetActivePlayers"})))
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                let players = if state.load(Ordering::SeqCst) {
+                    json!([{ "playerid": 1, "type": "video" }])
+                } else {
+                    json!([])
+                };
+                json!({ "id": 1, "jsonrpc": "2.0", "result": players }).to_string().into()
+            })
+            .create_async()
+            .await;
+
+        let open = self.mock_player_switch("Player.Open", playing.clone(), true).await;
+        let stop = self.mock_player_switch("Player.Stop", playing, false).await;
+        (active, open, stop)
+    }
+
+    async fn mock_player_switch(
+        &mut self,
+        method: &str,
+        playing: Arc<AtomicBool>,
+        playing_after: bool,
+    ) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": method})))
+            .with_header("content-type", "application/json")
+            .with_body_from_request(move |_| {
+                playing.store(playing_after, Ordering::SeqCst);
+                json!({ "id": 1, "jsonrpc": "2.0", "result": "OK" }).to_string().into()
+            })
+            .create_async()
+            .await
+    }
+
+    pub async fn mock_player_seek(&mut self) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({
+                "method": "Player.Seek",
+                "params": { "value": { "percentage": 0.0 } }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "result": { "percentage": 0.0, "time": {}, "totaltime": {} }
+            }).to_string())
+            .create_async()
+            .await
+    }
+
+    // Kodi answering `method` with a JSON-RPC error
+    pub async fn mock_method_error(&mut self, method: &str) -> Mock {
+        self.server.mock("POST", "/jsonrpc")
+            .match_body(mockito::Matcher::PartialJson(json!({"method": method})))
+            .with_header("content-type", "application/json")
+            .with_body(json!({
+                "id": 1,
+                "jsonrpc": "2.0",
+                "error": { "code": -32100, "message": "Failed to execute method." }
+            }).to_string())
+            .create_async()
+            .await
+    }
+
     pub async fn mock_timeout(&mut self, delay: Duration) -> Mock {
         self.server.mock("POST", "/jsonrpc")
             .with_chunked_body(move |w| {
@@ -219,3 +429,44 @@ impl KodiMock {
             .await
     }
 }
+
+// A stand-in for Kodi's notification socket. Point `websocket_url` at
+// `url()`; notifications sent before the client connects are queued.
+pub struct KodiNotifier {
+    url: String,
+    frames: mpsc::UnboundedSender<String>,
+}
+
+#[allow(dead_code)]
+impl KodiNotifier {
+    pub async fn new() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/jsonrpc", listener.local_addr().unwrap());
+        let (frames, mut queued) = mpsc::unbounded_channel::<String>();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(frame) = queued.recv().await {
+                if socket.send(Message::Text(frame)).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self { url, frames }
+    }
+
+    pub fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    pub fn notify(&self, method: &str, data: serde_json::Value) {
+        let frame = json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": { "sender": "xbmc", "data": data }
+        });
+        self.frames.send(frame.to_string()).unwrap();
+    }
+}
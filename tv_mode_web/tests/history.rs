@@ -0,0 +1,230 @@
+mod harness;
+
+use chrono::Local;
+use harness::KodiMock;
+use koditool::NowPlaying;
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use rocket::tokio::sync::RwLock;
+use rocket::tokio::time::{sleep, Duration};
+use serde_json::json;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+use tv_mode_web::history::{self, HistoryEntry, HistoryFilter, WatchHistory, HISTORY_FILE};
+
+fn now_playing(file: &str, percentage: f64) -> NowPlaying {
+    serde_json::from_value(json!({
+        "media_type": "episode",
+        "player_type": "video",
+        "title": "Pilot",
+        "show_title": "Bluey",
+        "season": 1,
+        "episode": 1,
+        "artist": null,
+        "album": null,
+        "file": file,
+        "elapsed": {},
+        "total": {"minutes": 10},
+        "percentage": percentage,
+        "paused": false,
+        "thumbnail": null
+    }))
+    .unwrap()
+}
+
+#[rocket::async_test]
+async fn test_history_entries_and_stats() {
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let watch_history = RwLock::new(WatchHistory::new(tmp_dir.path().to_str().unwrap()));
+
+    // Moved on from halfway through: skipped
+    history::update(&watch_history, |history| {
+        history.start(HistoryEntry::new("kids", "den", "Bluey", Some(1), None))
+    })
+    .await;
+    history::update(&watch_history, |history| {
+        history.progress("den", Some(&now_playing("/tv/bluey/1.mkv", 50.0)), 300)
+    })
+    .await;
+    history::update(&watch_history, |history| {
+        history.progress("den", Some(&now_playing("/tv/bluey/2.mkv", 1.0)), 0)
+    })
+    .await;
+    // Played to the end, the last two minutes without asking Kodi
+    history::update(&watch_history, |history| {
+        history.start(HistoryEntry::new("kids", "den", "Bluey", Some(2), None))
+    })
+    .await;
+    history::update(&watch_history, |history| {
+        history.progress("den", Some(&now_playing("/tv/bluey/2.mkv", 85.0)), 300)
+    })
+    .await;
+    history::update(&watch_history, |history| history.progress("den", None, 120)).await;
+    history::update(&watch_history, |history| history.finish("den", true)).await;
+    // Cut short by TV mode going off, which isn't skipping
+    history::update(&watch_history, |history| {
+        history.start(HistoryEntry::new("dad", "den", "Movies", None, Some(9)))
+    })
+    .await;
+    history::update(&watch_history, |history| history.finish("den", false)).await;
+    // Still playing
+    let playing = HistoryEntry::new("dad", "living room", "Bluey", Some(3), None);
+    history::update(&watch_history, |history| history.start(playing)).await;
+
+    let entries = history::entries(&watch_history, HistoryFilter::default()).await;
+    let summary: Vec<(&str, bool, Option<u64>)> = entries
+        .iter()
+        .map(|entry| (entry.user.as_str(), entry.skipped, entry.episodeid))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("kids", true, Some(1)),
+            ("kids", false, Some(2)),
+            ("dad", false, None),
+            ("dad", false, Some(3)),
+        ]
+    );
+    assert_eq!(entries[0].title.as_deref(), Some("Pilot"));
+    assert_eq!(entries[0].watched_seconds, 300);
+    assert!(entries[1].ended_at.is_some());
+    assert_eq!(entries[1].percentage, 100.0);
+    assert_eq!(entries[1].watched_seconds, 420);
+    assert!(entries[3].ended_at.is_none());
+
+    // Only finished entries are on disk, one per line
+    let lines = fs::read_to_string(tmp_dir.path().join(HISTORY_FILE)).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+
+    let filter = HistoryFilter {
+        user: Some("dad".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(history::entries(&watch_history, filter).await.len(), 2);
+
+    // An entry from long ago at the top of the file is left out by `from`
+    let old = r#"{"user":"kids","room":"den","entry":"Bluey","started_at":86400,"ended_at":87000}"#;
+    fs::write(
+        tmp_dir.path().join(HISTORY_FILE),
+        format!("{}\n{}", old, lines),
+    )
+    .unwrap();
+    assert_eq!(
+        history::entries(&watch_history, HistoryFilter::default())
+            .await
+            .len(),
+        5
+    );
+    let filter = HistoryFilter {
+        from: Some(Local::now().date_naive()),
+        ..Default::default()
+    };
+    assert_eq!(history::entries(&watch_history, filter).await.len(), 4);
+
+    let stats = history::stats(&entries);
+    assert_eq!(stats.top_shows[0].show, "Bluey");
+    assert_eq!(stats.top_shows[0].plays, 3);
+    assert_eq!(stats.top_shows[0].skipped, 1);
+    assert_eq!(stats.top_shows[0].hours, 0.2);
+    assert_eq!(stats.top_shows[1].show, "Movies");
+    let kids_weeks = &stats.hours_per_user_per_week["kids"];
+    assert_eq!(
+        kids_weeks.values().copied().collect::<Vec<f64>>(),
+        vec![0.2]
+    );
+}
+
+async fn history_entries(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/history").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+// Polls the history until `done` holds for the newest entry
+async fn wait_for_newest(client: &Client, done: impl Fn(&serde_json::Value) -> bool) {
+    for _ in 0..100 {
+        let entries = history_entries(client).await;
+        if entries.get(0).is_some_and(&done) {
+            return;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("History never caught up: {}", history_entries(client).await);
+}
+
+#[rocket::async_test]
+async fn test_scheduler_records_history() {
+    let mut mock = KodiMock::new().await;
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        format!("url: {}\nusername: user\npassword: pass\n", mock.url()),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - The Office\n",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": false, "start_timestamp": null}}}"#,
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let _shows = mock.mock_get_tv_shows().await;
+    let _episodes = mock.mock_get_episodes().await;
+    let _details = mock.mock_get_episode_details().await;
+    let _open = mock.mock_player_open().await;
+    let idle = mock.mock_get_active_players_none().await;
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    let response = client.get("/history").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .contains("Watch History"));
+
+    // The first tick starts an episode
+    wait_for_newest(&client, |entry| entry["entry"] == "The Office").await;
+    let entries = history_entries(&client).await;
+    assert_eq!(entries[0]["user"], "user1");
+    assert_eq!(entries[0]["ended_at"], serde_json::Value::Null);
+
+    // Kodi reports it playing
+    idle.remove_async().await;
+    let _playing = mock.mock_get_active_players_active().await;
+    let _now_playing = mock.mock_now_playing_episode().await;
+    wait_for_newest(&client, |entry| entry["title"] == "Pilot").await;
+
+    // Stopping TV mode finishes the entry without calling it skipped
+    client.post("/api/stop").dispatch().await;
+    wait_for_newest(&client, |entry| !entry["ended_at"].is_null()).await;
+    let entries = history_entries(&client).await;
+    assert_eq!(entries.as_array().unwrap().len(), 1);
+    assert_eq!(entries[0]["show_title"], "Breaking Bad");
+    assert_eq!(entries[0]["skipped"], false);
+    let lines = fs::read_to_string(config_dir.join(HISTORY_FILE)).unwrap();
+    assert_eq!(lines.lines().count(), 1);
+
+    let response = client.get("/api/stats?user=user1").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let stats: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(stats["top_shows"][0]["show"], "Breaking Bad");
+    assert_eq!(stats["top_shows"][0]["plays"], 1);
+    assert!(stats["hours_per_user_per_week"]["user1"].is_object());
+
+    let response = client.get("/api/history?user=nobody").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body, json!([]));
+    let response = client.get("/api/history?from=yesterday").dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+}
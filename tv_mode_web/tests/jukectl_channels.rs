@@ -0,0 +1,168 @@
+use mockito::Server;
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::json;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+async fn send(
+    client: &Client,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (Status, serde_json::Value) {
+    let request = match method {
+        "POST" => client.post(uri.to_string()),
+        "PUT" => client.put(uri.to_string()),
+        "DELETE" => client.delete(uri.to_string()),
+        _ => client.get(uri.to_string()),
+    };
+    let request = match body {
+        Some(body) => request.header(ContentType::JSON).body(body.to_string()),
+        None => request,
+    };
+    let response = request.dispatch().await;
+    let status = response.status();
+    (status, response.into_json().await.unwrap())
+}
+
+fn names(channels: &serde_json::Value) -> Vec<&str> {
+    channels
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|channel| channel["name"].as_str().unwrap())
+        .collect()
+}
+
+#[rocket::async_test]
+async fn test_jukectl_channels_crud() {
+    let mut jukectl = Server::new_async().await;
+    let _tags = jukectl
+        .mock("GET", "/tags")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"any": ["jazz", "late night"], "not": ["xmas"], "album_aware": false}"#)
+        .create_async()
+        .await;
+
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        "url: http://localhost:8080\nusername: user\npassword: pass\n",
+    )
+    .unwrap();
+    fs::write(config_dir.join("show_mappings.yml"), "user1:\n  - Show 1\n").unwrap();
+    fs::write(
+        config_dir.join("jukectl_channels.yml"),
+        "channels:\n  - name: Chill\n    any: [chill]\n",
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+    env::set_var("JUKECTL_API_URL", jukectl.url());
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    // Create, with tags tidied up
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/jukectl/channels",
+        Some(json!({"name": " Rock ", "any": ["rock", " "], "not": []})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body[1], json!({"name": "Rock", "any": ["rock"]}));
+
+    let (status, _) = send(
+        &client,
+        "POST",
+        "/jukectl/channels",
+        Some(json!({"name": "rock", "any": ["metal"], "not": []})),
+    )
+    .await;
+    assert_eq!(status, Status::Conflict);
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/jukectl/channels",
+        Some(json!({"name": "Empty", "any": [], "not": []})),
+    )
+    .await;
+    assert_eq!(status, Status::BadRequest);
+    assert_eq!(
+        body["error"],
+        "`any` has no tags, so the channel plays nothing"
+    );
+
+    // Edit and rename
+    let (status, body) = send(
+        &client,
+        "PUT",
+        "/jukectl/channels/Rock",
+        Some(json!({"name": "Loud", "any": ["rock", "metal"], "not": ["ballad"]})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        body[1],
+        json!({"name": "Loud", "any": ["rock", "metal"], "not": ["ballad"]})
+    );
+
+    // Capture what jukectl is playing
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/jukectl/channels/from-current",
+        Some(json!({"name": "Evening"})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        body[2],
+        json!({"name": "Evening", "any": ["jazz", "late night"], "not": ["xmas"]})
+    );
+
+    // Reorder; leaving a channel out is refused
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/jukectl/channels/order",
+        Some(json!({"names": ["Evening", "Chill", "Loud"]})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(names(&body), ["Evening", "Chill", "Loud"]);
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/jukectl/channels/order",
+        Some(json!({"names": ["Evening", "Chill"]})),
+    )
+    .await;
+    assert_eq!(status, Status::BadRequest);
+    assert_eq!(body["error"], "The new order leaves out: Loud");
+
+    // Delete
+    let (status, body) = send(&client, "DELETE", "/jukectl/channels/chill", None).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(names(&body), ["Evening", "Loud"]);
+    let (status, _) = send(&client, "DELETE", "/jukectl/channels/Chill", None).await;
+    assert_eq!(status, Status::NotFound);
+
+    // Everything went to disk and reads back the same
+    let yaml = fs::read_to_string(config_dir.join("jukectl_channels.yml")).unwrap();
+    assert!(yaml.contains("late night"));
+    let (status, body) = send(&client, "POST", "/api/config/reload", None).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["changes"], json!([]));
+    let (_, body) = send(&client, "GET", "/jukectl/channels", None).await;
+    assert_eq!(names(&body), ["Evening", "Loud"]);
+
+    let response = client.get("/jukectl").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response.into_string().await.unwrap().contains("Evening"));
+}
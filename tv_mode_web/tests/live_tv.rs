@@ -0,0 +1,79 @@
+mod harness;
+
+use harness::KodiMock;
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration};
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+// The scheduler ticks every 5s, so give it a few ticks
+async fn eventually_matched(mock: &mockito::Mock) -> bool {
+    for _ in 0..150 {
+        if mock.matched_async().await {
+            return true;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
+
+// A channel entry is looked up by name and opened live, then stopped and
+// picked again once its slot is over. Never-play has nothing to exclude it by.
+#[rocket::async_test]
+async fn test_scheduler_rotates_channel_entry() {
+    let mut mock = KodiMock::new().await;
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        format!("url: {}\nusername: user\npassword: pass\n", mock.url()),
+    )
+    .unwrap();
+    // A slot of 1.2s
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - channel: abc kids\n    minutes: 0.02\n",
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let _idle = mock.mock_get_active_players_none().await;
+    let _channels = mock.mock_get_channels().await;
+    let open = mock.mock_play_channel(12).await;
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+    let response = client.post("/api/play/user1").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+
+    assert!(eventually_matched(&open).await, "Channel was never opened");
+
+    let response = client.get("/api/history").dispatch().await;
+    let entries: Vec<serde_json::Value> = response.into_json().await.unwrap();
+    assert_eq!(entries[0]["entry"], "Channel (abc kids)");
+    assert!(entries[0]["ended_at"].is_null());
+    assert!(entries[0].get("episodeid").is_none());
+    assert!(entries[0].get("movieid").is_none());
+
+    // There's no id to exclude a channel by
+    let response = client.post("/api/never-play").dispatch().await;
+    assert_eq!(response.status(), Status::BadRequest);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "'Channel (abc kids)' is not an episode or movie"
+    );
+    assert!(!config_dir.join("exclusions.json").exists());
+
+    // Still on when the slot runs out, so it is stopped and a second entry
+    // goes on
+    let _playing = mock.mock_get_active_players_active().await;
+    let stop = mock.mock_player_stop().await;
+    let reopen = mock.mock_play_channel(12).await;
+
+    assert!(eventually_matched(&stop).await, "Channel was never stopped");
+    assert!(eventually_matched(&reopen).await, "Nothing was played after the slot");
+}
@@ -0,0 +1,108 @@
+mod harness;
+
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+fn setup_config_dir(config_dir: &std::path::Path) {
+    let config_yml = "\
+url: http://localhost:8080
+username: user
+password: pass
+room: living room
+rooms:
+  kids room:
+    url: http://localhost:8081
+    username: user
+    password: pass
+";
+    let show_mappings_yml = "user1:\n  - Show 1\n";
+
+    fs::write(config_dir.join("config.yml"), config_yml).unwrap();
+    fs::write(config_dir.join("show_mappings.yml"), show_mappings_yml).unwrap();
+}
+
+async fn rooms(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/rooms").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    response.into_json().await.unwrap()
+}
+
+#[rocket::async_test]
+async fn test_rooms_have_separate_tv_mode() {
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    setup_config_dir(config_dir);
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        let body = rooms(&client).await;
+        assert_eq!(body["default_room"], "living room");
+        assert_eq!(body["rooms"][0]["name"], "kids room");
+        assert_eq!(body["rooms"][1]["name"], "living room");
+
+        // Room names are percent-decoded from the path
+        let response = client
+            .post("/api/rooms/kids%20room/play/user1")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+
+        let body = rooms(&client).await;
+        assert_eq!(body["rooms"][0]["tv_mode"]["active"], true);
+        assert_eq!(body["rooms"][0]["tv_mode"]["user"], "user1");
+        assert_eq!(body["rooms"][1]["tv_mode"]["active"], false);
+
+        // The room-less routes act on the default room
+        let response = client.get("/api/status").dispatch().await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["tv_mode"]["active"], false);
+
+        let response = client.get("/api/rooms/kids%20room/status").dispatch().await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["tv_mode"]["active"], true);
+
+        let response = client.post("/api/rooms/garage/stop").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["message"], "Room 'garage' not found");
+    }
+
+    // Each room's state survives a restart
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        let body = rooms(&client).await;
+        assert_eq!(body["rooms"][0]["tv_mode"]["active"], true);
+        assert_eq!(body["rooms"][1]["tv_mode"]["active"], false);
+
+        let response = client.post("/api/rooms/kids%20room/stop").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let body = rooms(&client).await;
+        assert_eq!(body["rooms"][0]["tv_mode"]["active"], false);
+    }
+
+    // A state file from before rooms belongs to the default room
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"active": true, "user": "user1", "sleep_timer": {"enabled": false, "duration_hours": 2, "start_timestamp": null}}"#,
+    )
+    .unwrap();
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        let body = rooms(&client).await;
+        assert_eq!(body["rooms"][0]["tv_mode"]["active"], false);
+        assert_eq!(body["rooms"][1]["tv_mode"]["active"], true);
+    }
+}
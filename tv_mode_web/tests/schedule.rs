@@ -0,0 +1,218 @@
+mod harness;
+
+use chrono::NaiveDate;
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration};
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+use tv_mode_web::schedule::Schedule;
+
+fn at(day: u32, hour: u32, minute: u32) -> chrono::NaiveDateTime {
+    // October 2026: the 16th is a Friday
+    NaiveDate::from_ymd_opt(2026, 10, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+#[test]
+fn test_slots_and_quiet_hours() {
+    let schedule: Schedule = serde_yaml::from_str(
+        r#"
+slots:
+  - name: kids morning
+    user: kids
+    days: [mon, tue, wed, thu, friday]
+    start: "07:00"
+    end: "07:45"
+  - name: dad late
+    user: dad
+    days: [fri]
+    start: "22:00"
+    end: "00:30"
+    room: den
+    sleep_action: fade_out
+quiet_hours:
+  - start: "01:00"
+    end: "06:00"
+"#,
+    )
+    .unwrap();
+
+    let active = schedule
+        .active_slot("living room", "living room", at(16, 7, 10))
+        .unwrap();
+    assert_eq!(active.slot.name, "kids morning");
+    assert_eq!(active.run_key(), "kids morning@2026-10-16 07:00");
+    assert_eq!(active.minutes_left(at(16, 7, 10)), 35);
+    // Weekends and other rooms are left alone
+    assert!(schedule
+        .active_slot("living room", "living room", at(17, 7, 10))
+        .is_none());
+    assert!(schedule
+        .active_slot("den", "living room", at(16, 7, 10))
+        .is_none());
+
+    // Past midnight still belongs to Friday
+    let active = schedule
+        .active_slot("den", "living room", at(17, 0, 15))
+        .unwrap();
+    assert_eq!(active.slot.name, "dad late");
+    assert_eq!(active.minutes_left(at(17, 0, 15)), 15);
+    assert!(schedule
+        .active_slot("den", "living room", at(17, 22, 15))
+        .is_none());
+    assert!(schedule
+        .active_slot("den", "living room", at(17, 0, 30))
+        .is_none());
+
+    assert_eq!(schedule.quiet_until(at(17, 3, 0)), Some(at(17, 6, 0)));
+    assert_eq!(schedule.quiet_until(at(17, 6, 0)), None);
+
+    let problems = schedule.problems(|user| user == "kids", |room| room == "living room");
+    assert_eq!(
+        problems,
+        vec![
+            (
+                "slots[1] (dad late)".to_string(),
+                "User 'dad' not found in show mappings".to_string()
+            ),
+            (
+                "slots[1] (dad late)".to_string(),
+                "Room 'den' not found".to_string()
+            ),
+        ]
+    );
+}
+
+// Two slots that cover the whole day, so one is always on
+const ALL_DAY_SLOTS: &str = r#"{"slots": [
+    {"name": "morning", "user": "user1", "start": "00:00", "end": "12:00"},
+    {"name": "afternoon", "user": "user1", "start": "12:00", "end": "00:00"}
+]}"#;
+
+const ALL_DAY_QUIET: &str = r#"{"quiet_hours": [
+    {"start": "00:00", "end": "12:00"},
+    {"start": "12:00", "end": "00:00"}
+]}"#;
+
+async fn put_schedule(client: &Client, body: &str) -> (Status, serde_json::Value) {
+    let response = client
+        .put("/api/schedule")
+        .header(ContentType::JSON)
+        .body(body)
+        .dispatch()
+        .await;
+    let status = response.status();
+    // Bodies that don't parse get Rocket's HTML 422 page
+    (status, response.into_json().await.unwrap_or_default())
+}
+
+async fn tv_mode(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/rooms").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    body["rooms"][0]["tv_mode"].clone()
+}
+
+async fn wait_for_tv_mode(client: &Client, active: bool) {
+    for _ in 0..50 {
+        if tv_mode(client).await["active"] == active {
+            return;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!(
+        "TV mode never became {}",
+        if active { "active" } else { "inactive" }
+    );
+}
+
+#[rocket::async_test]
+async fn test_schedule_api_and_scheduler() {
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        "url: http://localhost:8080\nusername: user\npassword: pass\n",
+    )
+    .unwrap();
+    fs::write(config_dir.join("show_mappings.yml"), "user1:\n  - Show 1\n").unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    // Editing the schedule
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        let (status, body) = put_schedule(
+            &client,
+            r#"{"slots": [{"name": "x", "user": "nobody", "start": "07:00", "end": "08:00"}]}"#,
+        )
+        .await;
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(
+            body["message"],
+            "slots[0] (x): User 'nobody' not found in show mappings"
+        );
+        let (status, _) = put_schedule(
+            &client,
+            r#"{"slots": [{"name": "x", "user": "user1", "start": "7am", "end": "08:00"}]}"#,
+        )
+        .await;
+        assert_eq!(status, Status::UnprocessableEntity);
+
+        let (status, body) = put_schedule(&client, ALL_DAY_SLOTS).await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["slots"][0]["sleep_action"], "stop");
+        assert!(body["active_slots"]["default"].is_string());
+        assert_eq!(body["quiet_until"], serde_json::Value::Null);
+
+        let response = client.get("/api/schedule").dispatch().await;
+        let body: serde_json::Value = response.into_json().await.unwrap();
+        assert_eq!(body["slots"][1]["name"], "afternoon");
+        assert!(config_dir.join("schedules.yml").exists());
+    }
+
+    // A slot starts TV mode with a timer; a manual stop sticks
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        wait_for_tv_mode(&client, true).await;
+        let started = tv_mode(&client).await;
+        assert_eq!(started["user"], "user1");
+        assert_eq!(started["sleep_timer"]["enabled"], true);
+        assert!(started["sleep_timer"]["duration_minutes"].as_u64().unwrap() <= 12 * 60);
+        assert!(started["schedule_run"].is_string());
+
+        client.post("/api/stop").dispatch().await;
+        // Longer than one scheduler tick
+        sleep(Duration::from_millis(5500)).await;
+        assert_eq!(tv_mode(&client).await["active"], false);
+
+        // Quiet hours refuse manual starts
+        let (status, body) = put_schedule(&client, ALL_DAY_QUIET).await;
+        assert_eq!(status, Status::Ok);
+        assert!(body["quiet_until"].is_string());
+        let response = client.post("/api/play/user1").dispatch().await;
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    // Quiet hours turn TV mode off
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": false, "start_timestamp": null}}}"#,
+    )
+    .unwrap();
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        wait_for_tv_mode(&client, false).await;
+    }
+}
@@ -0,0 +1,179 @@
+mod harness;
+
+use chrono::{Local, NaiveDate};
+use harness::{KodiMock, KodiNotifier};
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration};
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+use tv_mode_web::app_state::SleepAction;
+use tv_mode_web::screen_time::{Budget, ScreenTimeUsage};
+
+fn day(day: u32) -> NaiveDate {
+    // October 2026: the 12th is a Monday
+    NaiveDate::from_ymd_opt(2026, 10, day).unwrap()
+}
+
+#[test]
+fn test_daily_and_weekly_budgets() {
+    let budget = Budget {
+        daily_minutes: Some(30),
+        weekly_minutes: Some(60),
+        action: SleepAction::Stop,
+    };
+    let mut usage = ScreenTimeUsage::default();
+    // Sunday belongs to the week before
+    usage.add("kids", day(11), 3000);
+    usage.add("kids", day(12), 600);
+    usage.add("kids", day(16), 1200);
+
+    let report = usage.report("kids", Some(&budget), day(16));
+    assert_eq!(report.watched_today_seconds, 1200);
+    assert_eq!(report.watched_this_week_seconds, 1800);
+    assert_eq!(report.remaining_seconds, Some(600));
+    assert_eq!(report.used_up(), None);
+
+    usage.add("kids", day(16), 600);
+    let report = usage.report("kids", Some(&budget), day(16));
+    assert_eq!(report.remaining_seconds, Some(0));
+    assert_eq!(report.used_up(), Some("today"));
+
+    // A new day, but the week is nearly gone
+    let report = usage.report("kids", Some(&budget), day(17));
+    assert_eq!(report.watched_today_seconds, 0);
+    assert_eq!(report.remaining_seconds, Some(1200));
+    usage.add("kids", day(17), 1200);
+    let report = usage.report("kids", Some(&budget), day(17));
+    assert_eq!(report.used_up(), Some("this week"));
+
+    // Without a budget there's nothing to use up
+    let report = usage.report("kids", None, day(17));
+    assert_eq!(report.remaining_seconds, None);
+    assert_eq!(report.used_up(), None);
+
+    // Old days are dropped
+    usage.add("kids", day(31), 60);
+    assert_eq!(usage.watched_on("kids", day(17)), 0);
+    assert_eq!(usage.watched_on("kids", day(31)), 60);
+}
+
+async fn users(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/users").dispatch().await;
+    response.into_json().await.unwrap()
+}
+
+async fn tv_mode(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/rooms").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    body["rooms"][0]["tv_mode"].clone()
+}
+
+#[rocket::async_test]
+async fn test_budget_runs_out() {
+    let mut mock = KodiMock::new().await;
+    let notifier = KodiNotifier::new().await;
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    let today = Local::now().date_naive().format("%Y-%m-%d").to_string();
+
+    fs::write(
+        config_dir.join("config.yml"),
+        format!(
+            "url: {}\nwebsocket_url: {}\nusername: user\npassword: pass\n",
+            mock.url(),
+            notifier.url()
+        ),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - Show 1\nuser2:\n  - Show 2\n",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("budgets.yml"),
+        "user1:\n  daily_minutes: 1\n",
+    )
+    .unwrap();
+    // Five seconds left
+    fs::write(
+        config_dir.join("screen_time.json"),
+        format!(r#"{{"user1": {{"{}": 55}}}}"#, today),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": false, "start_timestamp": null}}}"#,
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    // Watch time comes from Kodi's notifications. The player's position
+    // never moves, so polling it would count nothing.
+    let _playing = mock.mock_get_active_players_active().await;
+    let _now_playing = mock.mock_now_playing_episode().await;
+    let stop = mock.mock_player_stop().await;
+    let item = serde_json::json!({"item": {"id": 1, "type": "episode"}});
+    notifier.notify("Player.OnPlay", item.clone());
+    notifier.notify("Player.OnPause", item.clone());
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    let body = users(&client).await;
+    assert_eq!(body["screen_time"]["user1"]["remaining_seconds"], 5);
+    assert_eq!(body["screen_time"]["user1"]["budget"]["daily_minutes"], 1);
+    assert_eq!(body["screen_time"]["user1"]["budget"]["action"], "stop");
+    assert_eq!(
+        body["screen_time"]["user2"]["budget"],
+        serde_json::Value::Null
+    );
+    assert_eq!(
+        body["screen_time"]["user2"]["remaining_seconds"],
+        serde_json::Value::Null
+    );
+
+    // Paused time doesn't count
+    sleep(Duration::from_secs(6)).await;
+    assert_eq!(tv_mode(&client).await["active"], true);
+    let body = users(&client).await;
+    assert_eq!(body["screen_time"]["user1"]["remaining_seconds"], 5);
+
+    // Once it plays on, the five seconds are soon gone
+    notifier.notify("Player.OnResume", item);
+    for _ in 0..100 {
+        if tv_mode(&client).await["active"] == false {
+            break;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    assert_eq!(tv_mode(&client).await["active"], false);
+    for _ in 0..20 {
+        if stop.matched_async().await {
+            break;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    assert!(stop.matched_async().await);
+
+    let body = users(&client).await;
+    assert_eq!(body["screen_time"]["user1"]["remaining_seconds"], 0);
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(config_dir.join("screen_time.json")).unwrap())
+            .unwrap();
+    assert!(saved["user1"][&today].as_u64().unwrap() >= 60);
+
+    // No more today for user1; user2 has no budget
+    let response = client.post("/api/play/user1").dispatch().await;
+    assert_eq!(response.status(), Status::Forbidden);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(
+        body["message"],
+        "Screen time for 'user1' is used up for today"
+    );
+    let response = client.post("/api/play/user2").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+}
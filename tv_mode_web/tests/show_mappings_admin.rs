@@ -0,0 +1,164 @@
+mod harness;
+
+use harness::KodiMock;
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use serde_json::json;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+async fn send(
+    client: &Client,
+    method: &str,
+    uri: &str,
+    body: Option<serde_json::Value>,
+) -> (Status, serde_json::Value) {
+    let request = match method {
+        "POST" => client.post(uri.to_string()),
+        "DELETE" => client.delete(uri.to_string()),
+        _ => client.get(uri.to_string()),
+    };
+    let request = match body {
+        Some(body) => request.header(ContentType::JSON).body(body.to_string()),
+        None => request,
+    };
+    let response = request.dispatch().await;
+    let status = response.status();
+    (status, response.into_json().await.unwrap())
+}
+
+#[rocket::async_test]
+async fn test_show_mappings_crud() {
+    let mut mock = KodiMock::new().await;
+    let _shows = mock.mock_get_tv_shows().await;
+
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        format!("url: {}\nusername: user\npassword: pass\n", mock.url()),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - Show 1\n  - Show 2\nkids:\n  strategy: next_unwatched\n  shows:\n    - show: Bluey\n      strategy: resume_in_progress\n",
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    // Users
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users",
+        Some(json!({"user": "guest", "shows": ["The Office"]})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["show_mappings"]["guest"], json!(["The Office"]));
+
+    let (status, _) = send(
+        &client,
+        "POST",
+        "/api/users",
+        Some(json!({"user": "guest", "shows": ["Show 1"]})),
+    )
+    .await;
+    assert_eq!(status, Status::Conflict);
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users",
+        Some(json!({"user": "empty", "shows": [" "]})),
+    )
+    .await;
+    assert_eq!(status, Status::BadRequest);
+    assert_eq!(body["message"], "User 'empty' needs at least one show");
+
+    // Shows
+    let (status, body) = send(
+        &client,
+        "POST",
+        "/api/users/guest/shows",
+        Some(json!({"show": "Breaking Bad"})),
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        body["show_mappings"]["guest"],
+        json!(["Breaking Bad", "The Office"])
+    );
+    let (status, _) = send(
+        &client,
+        "POST",
+        "/api/users/guest/shows",
+        Some(json!({"show": "Breaking Bad"})),
+    )
+    .await;
+    assert_eq!(status, Status::Conflict);
+
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        "/api/users/guest/shows/The%20Office",
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["show_mappings"]["guest"], json!(["Breaking Bad"]));
+    let (status, body) = send(
+        &client,
+        "DELETE",
+        "/api/users/guest/shows/Breaking%20Bad",
+        None,
+    )
+    .await;
+    assert_eq!(status, Status::BadRequest);
+    assert_eq!(
+        body["message"],
+        "'Breaking Bad' is guest's only show; remove the user instead"
+    );
+
+    // The file was written back, keeping other users' options, and reads
+    // back to the same mappings
+    let yaml = fs::read_to_string(config_dir.join("show_mappings.yml")).unwrap();
+    assert!(yaml.contains("guest"));
+    assert!(yaml.contains("resume_in_progress"));
+    assert!(!config_dir.join(".show_mappings.yml.tmp").exists());
+    let (status, body) = send(&client, "POST", "/api/config/reload", None).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body["changes"], json!([]));
+
+    // Users that TV mode is running for can't be removed
+    let (status, _) = send(&client, "POST", "/api/play/guest", None).await;
+    assert_eq!(status, Status::Ok);
+    let (status, _) = send(&client, "DELETE", "/api/users/guest", None).await;
+    assert_eq!(status, Status::Conflict);
+    send(&client, "POST", "/api/stop", None).await;
+    let (status, body) = send(&client, "DELETE", "/api/users/guest", None).await;
+    assert_eq!(status, Status::Ok);
+    assert!(body["show_mappings"].get("guest").is_none());
+    let (status, _) = send(&client, "DELETE", "/api/users/guest", None).await;
+    assert_eq!(status, Status::NotFound);
+
+    // The picker's library search
+    let (status, body) = send(&client, "GET", "/api/library/shows?query=office", None).await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(
+        body,
+        json!([{"tvshowid": 1, "title": "The Office", "year": 0}])
+    );
+
+    let response = client.get("/admin").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .contains("Show Mappings"));
+}
@@ -0,0 +1,149 @@
+mod harness;
+
+use harness::KodiMock;
+use rocket::http::Status;
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration, Instant};
+use serde_json::json;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+async fn history_entries(client: &Client) -> Vec<serde_json::Value> {
+    let response = client.get("/api/history").dispatch().await;
+    response.into_json().await.unwrap()
+}
+
+// Waits for the scheduler to start item number `count`, returning the
+// history newest first
+async fn wait_for_playing(client: &Client, count: usize) -> Vec<serde_json::Value> {
+    for _ in 0..100 {
+        let entries = history_entries(client).await;
+        if entries.len() == count && entries[0]["ended_at"].is_null() {
+            return entries;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    panic!(
+        "Item {} never started: {:?}",
+        count,
+        history_entries(client).await
+    );
+}
+
+async fn post_message(client: &Client, uri: &str) -> (Status, String) {
+    let response = client.post(uri.to_string()).dispatch().await;
+    let status = response.status();
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    (
+        status,
+        body["message"].as_str().unwrap_or_default().to_string(),
+    )
+}
+
+#[rocket::async_test]
+async fn test_skip_replay_and_never_play() {
+    let mut mock = KodiMock::new().await;
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    fs::write(
+        config_dir.join("config.yml"),
+        format!("url: {}\nusername: user\npassword: pass\n", mock.url()),
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("show_mappings.yml"),
+        "user1:\n  - The Office\n",
+    )
+    .unwrap();
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": false, "start_timestamp": null}}}"#,
+    )
+    .unwrap();
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    let _shows = mock.mock_get_tv_shows().await;
+    let _episodes = mock.mock_get_episodes().await;
+    let _details = mock.mock_get_episode_details().await;
+    let _player = mock.mock_player().await;
+    let _now_playing = mock.mock_now_playing_episode().await;
+    let seek = mock.mock_player_seek().await;
+
+    let client = Client::tracked(tv_mode_web::build_rocket())
+        .await
+        .expect("valid rocket instance");
+
+    // The first tick starts something
+    wait_for_playing(&client, 1).await;
+
+    // Skipping starts the next item straight away, well before the next tick
+    let skipped_at = Instant::now();
+    let (status, message) = post_message(&client, "/api/skip").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(message, "Skipped, picking something else for 'user1'");
+    let entries = wait_for_playing(&client, 2).await;
+    assert!(skipped_at.elapsed() < Duration::from_secs(3));
+    assert_eq!(entries[1]["skipped"], true);
+    assert!(!entries[1]["ended_at"].is_null());
+
+    // If Kodi won't stop it, nothing gets excluded
+    let failing_stop = mock.mock_method_error("Player.Stop").await;
+    let (status, _) = post_message(&client, "/api/never-play").await;
+    assert_eq!(status, Status::BadGateway);
+    assert!(failing_stop.matched_async().await);
+    assert!(!config_dir.join("exclusions.json").exists());
+    let response = client.get("/api/exclusions").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body, json!({}));
+    failing_stop.remove_async().await;
+
+    // Never play the current episode again
+    let excluded = entries[0]["episodeid"].as_u64().unwrap();
+    let (status, message) = post_message(&client, "/api/never-play").await;
+    assert_eq!(status, Status::Ok);
+    assert!(message.starts_with("Won't play '"), "{}", message);
+    assert!(message.ends_with("' for 'user1' again"), "{}", message);
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(config_dir.join("exclusions.json")).unwrap())
+            .unwrap();
+    assert_eq!(saved["user1"][0]["episodeid"], excluded);
+
+    // Of the show's two episodes, only the other one comes up from now on
+    let entries = wait_for_playing(&client, 3).await;
+    assert_eq!(entries[1]["skipped"], true);
+    assert_ne!(entries[0]["episodeid"], excluded);
+    let (status, _) = post_message(&client, "/api/rooms/default/skip").await;
+    assert_eq!(status, Status::Ok);
+    let entries = wait_for_playing(&client, 4).await;
+    assert_ne!(entries[0]["episodeid"], excluded);
+
+    let response = client.get("/api/exclusions").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["user1"][0]["episodeid"], excluded);
+    let uri = format!("/api/exclusions/user1/episodes/{}", excluded);
+    let response = client.delete(uri.clone()).dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body, json!({}));
+    let response = client.delete(uri).dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+
+    // Replay seeks back to the start of what's on
+    let (status, message) = post_message(&client, "/api/replay").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(message, "Playing again from the start");
+    assert!(seek.matched_async().await);
+
+    // None of it does anything without TV mode
+    client.post("/api/stop").dispatch().await;
+    let (status, message) = post_message(&client, "/api/skip").await;
+    assert_eq!(status, Status::BadRequest);
+    assert_eq!(message, "Cannot skip when TV mode is not active");
+    let (status, _) = post_message(&client, "/api/replay").await;
+    assert_eq!(status, Status::BadRequest);
+    let (status, _) = post_message(&client, "/api/never-play").await;
+    assert_eq!(status, Status::BadRequest);
+    let (status, _) = post_message(&client, "/api/rooms/nowhere/skip").await;
+    assert_eq!(status, Status::NotFound);
+}
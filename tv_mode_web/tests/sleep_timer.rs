@@ -0,0 +1,209 @@
+mod harness;
+
+use harness::KodiMock;
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use rocket::tokio::time::{sleep, Duration, Instant};
+use serde_json::json;
+use std::env;
+use std::fs;
+use tempfile::tempdir;
+
+fn setup_config_dir(config_dir: &std::path::Path, kodi_url: &str) {
+    let config_yml = format!("url: {}\nusername: user\npassword: pass\n", kodi_url);
+    fs::write(config_dir.join("config.yml"), config_yml).unwrap();
+    fs::write(config_dir.join("show_mappings.yml"), "user1:\n  - Show 1\n").unwrap();
+}
+
+async fn post_json(
+    client: &Client,
+    uri: &str,
+    body: serde_json::Value,
+) -> (Status, serde_json::Value) {
+    let response = client
+        .post(uri.to_string())
+        .header(ContentType::JSON)
+        .body(body.to_string())
+        .dispatch()
+        .await;
+    let status = response.status();
+    (status, response.into_json().await.unwrap())
+}
+
+// The default room's TV mode, without asking Kodi anything
+async fn tv_mode(client: &Client) -> serde_json::Value {
+    let response = client.get("/api/rooms").dispatch().await;
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    body["rooms"][0]["tv_mode"].clone()
+}
+
+// Give the scheduler a few ticks to turn TV mode off
+async fn wait_for_tv_mode_off(client: &Client) {
+    for _ in 0..50 {
+        if tv_mode(client).await["active"] == false {
+            return;
+        }
+        sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Sleep timer never went off");
+}
+
+// The timer's action runs right after TV mode is saved as off
+async fn eventually_matched(mock: &mockito::Mock) -> bool {
+    for _ in 0..20 {
+        if mock.matched_async().await {
+            return true;
+        }
+        sleep(Duration::from_millis(50)).await;
+    }
+    false
+}
+
+#[rocket::async_test]
+async fn test_sleep_timer_requests_and_expiry() {
+    let mut mock = KodiMock::new().await;
+    let tmp_dir = tempdir().expect("Failed to create temp dir");
+    let config_dir = tmp_dir.path();
+    setup_config_dir(config_dir, "http://localhost:8080");
+    env::set_var("CONFIG_DIR", config_dir.to_str().unwrap());
+
+    // Minutes, the older hours, end of episode, and what's refused
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        let (status, body) = post_json(
+            &client,
+            "/api/play/user1",
+            json!({"sleep_timer_minutes": 45, "sleep_action": "fade_out"}),
+        )
+        .await;
+        assert_eq!(status, Status::Ok);
+        let timer = &body["tv_mode"]["sleep_timer"];
+        assert_eq!(timer["duration_minutes"], 45);
+        assert_eq!(timer["action"], "fade_out");
+        assert!(timer["remaining_seconds"].as_u64().unwrap() > 44 * 60);
+
+        let (status, body) = post_json(&client, "/api/sleep-timer", json!({"hours": 1})).await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["tv_mode"]["sleep_timer"]["duration_minutes"], 60);
+        assert_eq!(body["tv_mode"]["sleep_timer"]["action"], "stop");
+
+        let (status, body) = post_json(
+            &client,
+            "/api/sleep-timer",
+            json!({"mode": "end_of_episode", "action": "suspend"}),
+        )
+        .await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["tv_mode"]["sleep_timer"]["mode"], "end_of_episode");
+        assert!(body["tv_mode"]["sleep_timer"]
+            .get("remaining_seconds")
+            .is_none());
+
+        for request in [json!({"minutes": 0}), json!({"minutes": 2000}), json!({})] {
+            let (status, _) = post_json(&client, "/api/sleep-timer", request).await;
+            assert_eq!(status, Status::BadRequest);
+        }
+
+        // Zero minutes on play means no timer
+        let (status, body) = post_json(
+            &client,
+            "/api/play/user1",
+            json!({"sleep_timer_minutes": 0}),
+        )
+        .await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(body["tv_mode"]["sleep_timer"]["enabled"], false);
+
+        client.post("/api/stop").dispatch().await;
+    }
+
+    // An expired timer stops what's playing
+    setup_config_dir(config_dir, &mock.url());
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": true, "duration_minutes": 1, "action": "stop", "start_timestamp": 1000}}}"#,
+    )
+    .unwrap();
+    let playing = mock.mock_get_active_players_active().await;
+    let stop = mock.mock_player_stop().await;
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        wait_for_tv_mode_off(&client).await;
+        assert_eq!(tv_mode(&client).await["sleep_timer"]["enabled"], false);
+        assert!(eventually_matched(&stop).await);
+    }
+    playing.remove_async().await;
+
+    // End of episode: nothing new is started, and Kodi is suspended. State
+    // files from before minutes still load.
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": true, "duration_hours": 3, "mode": "end_of_episode", "action": "suspend", "start_timestamp": 1000}}}"#,
+    )
+    .unwrap();
+    let idle = mock.mock_get_active_players_none().await;
+    let suspend = mock.mock_system_suspend().await;
+    let open = mock.mock_player_open().await;
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        assert_eq!(
+            tv_mode(&client).await["sleep_timer"]["duration_minutes"],
+            180
+        );
+        wait_for_tv_mode_off(&client).await;
+        assert!(eventually_matched(&suspend).await);
+        assert!(!open.matched_async().await);
+    }
+    idle.remove_async().await;
+
+    // A fade-out takes 30s and runs in the background: the room still
+    // answers, and a config reload doesn't wait for it
+    fs::write(
+        config_dir.join("persistent_state.json"),
+        r#"{"default": {"active": true, "user": "user1", "sleep_timer": {"enabled": true, "duration_minutes": 1, "action": "fade_out", "start_timestamp": 1000}}}"#,
+    )
+    .unwrap();
+    let _playing = mock.mock_get_active_players_active().await;
+    let _volume = mock.mock_application_properties(80, false).await;
+    let fading = mock.mock_set_volume_any().await;
+    let restore = mock.mock_set_volume(json!(80), 80).await;
+    let stop = mock.mock_player_stop().await;
+    {
+        let client = Client::tracked(tv_mode_web::build_rocket())
+            .await
+            .expect("valid rocket instance");
+
+        wait_for_tv_mode_off(&client).await;
+        assert!(eventually_matched(&fading).await);
+
+        setup_config_dir(config_dir, "http://kodi.local:8080");
+        let started = Instant::now();
+        let response = client.post("/api/config/reload").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let app_state = client
+            .rocket()
+            .state::<tv_mode_web::app_state::AppState>()
+            .unwrap();
+        assert_eq!(
+            app_state.default_room().client().await.config.url,
+            "http://kodi.local:8080"
+        );
+
+        // Turning TV mode back on calls the fade off: the volume goes back
+        // and nothing is stopped
+        let (status, _) = post_json(&client, "/api/play/user1", json!({})).await;
+        assert_eq!(status, Status::Ok);
+        assert!(eventually_matched(&restore).await);
+        assert!(!stop.matched_async().await);
+    }
+}
@@ -95,3 +95,44 @@ async fn test_state_persistence_stop_tv_mode() {
         assert_eq!(body["tv_mode"]["user"], serde_json::Value::Null);
     }
 }
+
+#[test]
+fn test_recently_played_round_trip_and_window() {
+    use koditool::{RepeatWindow, UserMapping};
+    use tv_mode_web::app_state::{PlayRecord, RecentlyPlayed};
+
+    let mut recently_played = RecentlyPlayed::default();
+    for (entry, episodeid) in [("Bluey", 11), ("Bob's Burgers", 40), ("Bluey", 12)] {
+        recently_played.record(
+            "user1",
+            PlayRecord {
+                entry: entry.to_string(),
+                episodeid: Some(episodeid),
+                movieid: None,
+                played_at: 0,
+            },
+        );
+    }
+
+    // Saved as JSON keyed by user, newest first
+    let json = serde_json::to_string(&recently_played).unwrap();
+    let restored: RecentlyPlayed = serde_json::from_str(&json).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["user1"][0]["entry"], "Bluey");
+    assert_eq!(value["user1"][0]["episodeid"], 12);
+
+    let window = RepeatWindow { shows: 2, episodes: 2 };
+    assert_eq!(
+        restored.recent_entries("user1", &window),
+        vec!["Bluey".to_string(), "Bob's Burgers".to_string()]
+    );
+    assert!(restored.recent_entries("user2", &window).is_empty());
+
+    let mapping = UserMapping {
+        repeat_window: Some(window),
+        ..Default::default()
+    };
+    let options = restored.select_options("user1", &mapping);
+    assert_eq!(options.avoid_episodes, [12, 40].into_iter().collect());
+    assert!(options.avoid_movies.is_empty());
+}
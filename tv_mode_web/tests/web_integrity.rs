@@ -83,6 +83,89 @@ async fn test_resilience_kodi_malformed_json() {
     assert!(body["error_details"].as_str().is_some());
 }
 
+#[rocket::async_test]
+async fn test_resilience_kodi_unauthorized() {
+    let mut mock = KodiMock::new().await;
+    let _m = mock.mock_http_error(401).await;
+
+    let client = create_test_client(Some(&mock.url())).await;
+    let response = client.get("/api/status").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["status"], "error");
+    assert_eq!(body["error_kind"], "unauthorized");
+}
+
+#[rocket::async_test]
+async fn test_status_reports_now_playing() {
+    let mut mock = KodiMock::new().await;
+    let _players = mock.mock_get_active_players_active().await;
+    let _batch = mock.mock_now_playing_episode().await;
+
+    let client = create_test_client(Some(&mock.url())).await;
+    let response = client.get("/api/status").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["status"], "active");
+    assert_eq!(body["now_playing"]["media_type"], "episode");
+    assert_eq!(body["now_playing"]["show_title"], "Breaking Bad");
+    assert_eq!(body["now_playing"]["season"], 1);
+    assert_eq!(body["now_playing"]["elapsed"]["minutes"], 5);
+    assert_eq!(body["now_playing"]["paused"], false);
+}
+
+#[rocket::async_test]
+async fn test_api_volume() {
+    let mut mock = KodiMock::new().await;
+    let _properties = mock.mock_application_properties(42, false).await;
+    let set_volume = mock.mock_set_volume(serde_json::json!("increment"), 42).await;
+
+    let client = create_test_client(Some(&mock.url())).await;
+    let response = client.get("/api/volume").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["volume"], 42);
+    assert_eq!(body["muted"], false);
+
+    let response = client
+        .post("/api/volume")
+        .header(rocket::http::ContentType::JSON)
+        .body(r#"{"step": "up"}"#)
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    set_volume.assert_async().await;
+
+    // Out of range, and both fields at once, are rejected before reaching Kodi
+    for body in [
+        r#"{"volume": 101}"#,
+        r#"{"volume": 300}"#,
+        r#"{"volume": -1}"#,
+        r#"{"volume": 10, "step": "up"}"#,
+        r#"{}"#,
+    ] {
+        let response = client
+            .post("/api/volume")
+            .header(rocket::http::ContentType::JSON)
+            .body(body)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+}
+
+#[rocket::async_test]
+async fn test_api_volume_kodi_unreachable() {
+    let client = create_test_client(Some("http://127.0.0.1:1")).await;
+
+    let response = client.post("/api/mute").dispatch().await;
+    assert_eq!(response.status(), Status::ServiceUnavailable);
+    let body: serde_json::Value = response.into_json().await.unwrap();
+    assert_eq!(body["error_kind"], "transport");
+}
+
 async fn create_test_client(kodi_url: Option<&str>) -> Client {
     // Set up a temporary config directory
     let tmp_dir = tempdir().expect("Failed to create temp dir");
@@ -91,7 +174,7 @@ async fn create_test_client(kodi_url: Option<&str>) -> Client {
     // Create dummy config files
     let url = kodi_url.unwrap_or("http://localhost:8080");
     let config_yml = format!("url: {}\nusername: user\npassword: pass\n", url);
-    let show_mappings_yml = "user1:\n  - Show 1\n  - Show 2\n";
+    let show_mappings_yml = "user1:\n  - Show 1\n  - Show 2\nmovie_night:\n  - movies:\n      genre: Animation\nkids:\n  strategy: next_unwatched\n  shows:\n    - show: Bluey\n      strategy: resume_in_progress\n";
     let jukectl_channels_yml = "channels:\n  - name: Channel 1\n    any: [\"tag1\"]\n";
     
     fs::write(config_dir.join("config.yml"), config_yml).unwrap();
@@ -115,6 +198,11 @@ async fn test_api_users() {
     let body = response.into_string().await.unwrap();
     assert!(body.contains("user1"));
     assert!(body.contains("Show 1"));
+    // Movie entries are listed by their filter
+    assert!(body.contains("Movies (genre: Animation)"));
+    // Users with options still list their shows by title
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["show_mappings"]["kids"], serde_json::json!(["Bluey"]));
 }
 
 #[rocket::async_test]